@@ -0,0 +1,90 @@
+// Band limited random picks a new random value `rate` times a second and
+// interpolates between them. With rounding interpolation this is a classic
+// sample and hold. Linear and cubic interpolation give smoother random
+// modulation with most of the energy below the rate.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+use crate::wavetable::{Interpolation, cubic_interpolate};
+
+#[derive(Clone)]
+pub struct BandLimitedRandom {
+    rate: Knob, // New values per sample (values per second / sample rate)
+    interpolation: Interpolation,
+    seed: u64,
+    duration: SampleTime,
+}
+
+impl BandLimitedRandom {
+    pub fn new(rate: Knob, interpolation: Interpolation, seed: u64, duration: SampleTime) -> Self {
+        BandLimitedRandom { rate: rate, interpolation: interpolation, seed: seed, duration: duration }
+    }
+}
+
+struct BandLimitedRandomData {
+    rng: StdRng,
+    rate_data: SoundData,
+    phase: f64,
+    // The random values at x0 - 1, x0, x0 + 1 and x0 + 2. We are part way
+    // between x0 and x0 + 1.
+    points: [f32; 4],
+}
+
+impl SoundSource for BandLimitedRandom {
+    fn init_state(&self) -> SoundData {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut points = [0.0; 4];
+        for point in points.iter_mut() {
+            *point = rng.gen_range(-1.0..1.0);
+        }
+        Box::new(BandLimitedRandomData {
+            rng: rng,
+            rate_data: self.rate.init_state(),
+            phase: 0.0,
+            points: points,
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            (0.0, 0.0)
+        } else {
+            let data = state.downcast_mut::<BandLimitedRandomData>().unwrap();
+            let f = data.phase as f32;
+            let [ym1, y0, y1, y2] = data.points;
+            let val = match self.interpolation {
+                Interpolation::Rounding => y0,
+                Interpolation::Linear => y0 + (y1 - y0) * f,
+                Interpolation::Cubic => cubic_interpolate(f, ym1, y0, y1, y2),
+            };
+            let rate = self.rate.next_value(n, &mut data.rate_data);
+            data.phase += rate.max(0.0) as f64;
+            while data.phase >= 1.0 {
+                data.phase -= 1.0;
+                data.points.rotate_left(1);
+                data.points[3] = data.rng.gen_range(-1.0..1.0);
+            }
+            (val, val)
+        }
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let rate = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let interpolation = Interpolation::from_param(&params[1]);
+        let duration = reader.get_duration(&params[2]);
+        // An optional fourth parameter fixes the seed for this node
+        let seed = match params.get(3) {
+            Some(seed) => seed.parse::<u64>().unwrap(),
+            None => reader.next_seed(),
+        };
+        Box::new(Self::new(rate, interpolation, seed, duration))
+    }
+}
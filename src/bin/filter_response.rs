@@ -0,0 +1,98 @@
+use clap::{ArgGroup, Parser};
+
+use mattmusic::frequency_response::{FrequencyResponse, log_frequencies};
+use mattmusic::impulse::Impulse;
+use mattmusic::read_song::open_song;
+
+/// Work out the frequency response of a filter from a song file. Filters made
+/// from poles and zeros are worked out exactly, anything else is measured with
+/// an impulse.
+#[derive(Parser, Debug)]
+#[command(author, version, about, long_about = None)]
+#[command(group(ArgGroup::new("filter").required(true).args(["sound", "patch"])))]
+struct Args {
+    /// Song file containing the filter
+    file: String,
+    /// Sound to analyse
+    #[arg(long)]
+    sound: Option<String>,
+    /// Patch to analyse, with the impulse as its PATCH_INPUT
+    #[arg(long)]
+    patch: Option<String>,
+    /// Parameter to pass to the patch, given once per INPUT(N)
+    #[arg(long = "param", requires = "patch")]
+    params: Vec<String>,
+    /// Sound in the song to replace with the impulse
+    #[arg(long, default_value = "input")]
+    input: String,
+    /// Measure with an impulse even if the filter gives its poles and zeros
+    #[arg(long)]
+    measure: bool,
+    /// Level of the impulse. Lower levels show the small signal response of
+    /// filters that saturate.
+    #[arg(long, default_value_t = 1.0)]
+    level: f32,
+    /// Length in seconds of the impulse response to measure
+    #[arg(long, default_value_t = 2.0)]
+    length: f64,
+    #[arg(long, default_value_t = 48000)]
+    sample_rate: i32,
+    /// Lowest frequency in Hz
+    #[arg(long, default_value_t = 20.0)]
+    from: f64,
+    /// Highest frequency in Hz, kept below nyquist
+    #[arg(long, default_value_t = 20000.0)]
+    to: f64,
+    /// Number of frequencies, spaced evenly on a log scale
+    #[arg(long, default_value_t = 400)]
+    points: usize,
+    /// Write the response to this CSV file
+    #[arg(long)]
+    csv: Option<String>,
+    /// Plot the response to this SVG file
+    #[arg(long)]
+    svg: Option<String>,
+}
+
+fn main() -> anyhow::Result<()> {
+    let args = Args::parse();
+    let mut reader = open_song(&args.file, args.sample_rate, None);
+    let length = (args.length * args.sample_rate as f64).round() as i64;
+    reader.set_placeholder(&args.input, Box::new(Impulse::new(args.level, length)));
+    let (name, filter) = match (&args.sound, &args.patch) {
+        (Some(sound), _) => (sound.clone(), reader.get_sound(sound)),
+        (_, Some(patch)) => (patch.clone(), reader.get_patch(&format!("{} {}", patch, args.input), &args.params)),
+        _ => unreachable!(),
+    };
+
+    let freqs = log_frequencies(args.from, args.to.min(args.sample_rate as f64 * 0.499), args.points);
+    let (method, response) = match filter.transfer_function() {
+        Some(transfer_function) if !args.measure => {
+            let mut stage = Some(&transfer_function);
+            while let Some(filter) = stage {
+                println!("Poles: {:?}", filter.poles);
+                println!("Zeros: {:?}", filter.zeros);
+                println!("Second form zeros: {:?}", filter.zero2s);
+                stage = filter.input.as_deref();
+            }
+            ("poles and zeros", FrequencyResponse::from_transfer_function(&transfer_function, &freqs, args.sample_rate))
+        },
+        _ => ("impulse response", FrequencyResponse::measure(&filter, args.level, length, &freqs, args.sample_rate)),
+    };
+
+    let peak = response.peak();
+    println!("Response of {} from its {}", name, method);
+    println!("Peak {:.2} dB at {:.1} Hz", peak.magnitude, peak.freq);
+    for freq in response.crossings(peak.magnitude - 3.0) {
+        println!("3 dB below the peak at {:.1} Hz", freq);
+    }
+    if let Some(filename) = &args.csv {
+        response.write_csv(filename)?;
+        println!("Wrote {}", filename);
+    }
+    if let Some(filename) = &args.svg {
+        response.write_svg(filename, &format!("{} ({})", name, method))?;
+        println!("Wrote {}", filename);
+    }
+    Ok(())
+}
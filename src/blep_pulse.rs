@@ -0,0 +1,60 @@
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+use crate::poly_blep::{PolyBlep, Shape};
+
+// Anti-aliased pulse wave with variable duty cycle, ranging -1 to 1. See PolyBlep.
+#[derive(Clone)]
+pub struct BlepPulse {
+    poly_blep: PolyBlep
+}
+
+impl BlepPulse {
+    pub fn new(
+        freq: Knob,
+        duty: Knob,
+        gain: Knob,
+        sync: Option<Knob>,
+        duration: SampleTime
+    ) -> Self {
+        BlepPulse { poly_blep: PolyBlep::new(
+            Shape::Pulse,
+            freq,
+            duty,
+            gain,
+            sync,
+            duration
+        ) }
+    }
+}
+
+struct BlepPulseData {
+    poly_blep_data: SoundData,
+}
+
+impl SoundSource for BlepPulse {
+    fn init_state(&self) -> SoundData {
+        Box::new(BlepPulseData { poly_blep_data: self.poly_blep.init_state() })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = &mut state.downcast_mut::<BlepPulseData>().unwrap();
+        self.poly_blep.next_value(n, &mut data.poly_blep_data)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.poly_blep.duration()
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let freq = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let duty = reader.get_knob(&params[1], 1.0);
+        let strength = reader.get_knob(&params[2], 1.0);
+        let duration = reader.get_duration(&params[3]);
+        // An optional last parameter is a sound that resets the phase each time
+        // it goes above zero
+        let sync = params.get(4).map(|sync| reader.get_knob(sync, 1.0));
+        Box::new(Self::new(freq, duty, strength, sync, duration))
+    }
+}
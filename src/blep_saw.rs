@@ -0,0 +1,58 @@
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+use crate::poly_blep::{PolyBlep, Shape};
+
+// Anti-aliased saw wave. See PolyBlep.
+#[derive(Clone)]
+pub struct BlepSaw {
+    poly_blep: PolyBlep
+}
+
+impl BlepSaw {
+    pub fn new(
+        freq: Knob,
+        gain: Knob,
+        sync: Option<Knob>,
+        duration: SampleTime
+    ) -> Self {
+        BlepSaw { poly_blep: PolyBlep::new(
+            Shape::Saw,
+            freq,
+            Knob::dc(0.5),
+            gain,
+            sync,
+            duration
+        ) }
+    }
+}
+
+struct BlepSawData {
+    poly_blep_data: SoundData,
+}
+
+impl SoundSource for BlepSaw {
+    fn init_state(&self) -> SoundData {
+        Box::new(BlepSawData { poly_blep_data: self.poly_blep.init_state() })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = &mut state.downcast_mut::<BlepSawData>().unwrap();
+        self.poly_blep.next_value(n, &mut data.poly_blep_data)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.poly_blep.duration()
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let freq = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let strength = reader.get_knob(&params[1], 1.0);
+        let duration = reader.get_duration(&params[2]);
+        // An optional last parameter is a sound that resets the phase each time
+        // it goes above zero
+        let sync = params.get(3).map(|sync| reader.get_knob(sync, 1.0));
+        Box::new(Self::new(freq, strength, sync, duration))
+    }
+}
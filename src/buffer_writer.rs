@@ -1,9 +1,7 @@
-pub mod buffer_writer {
-
 use std::sync::{Arc, Mutex};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub struct BufferWriter {
@@ -15,12 +13,12 @@ impl BufferWriter {
     pub fn new(input: DynSoundSource, buffer: Arc<Mutex<Vec<(f32,f32)>>>) -> Self {
         BufferWriter { source: input, buffer: buffer }
     }
-    fn add_sample(&self, n: i32, sample: (f32, f32)) {
+    fn add_sample(&self, n: SampleTime, sample: (f32, f32)) {
         let buffer = &mut self.buffer.lock().unwrap();
-        if (buffer.len() as i32) < n {
+        if (buffer.len() as SampleTime) < n {
             buffer.resize(n as usize, (0.0, 0.0));
         }
-        if (buffer.len() as i32) == n {
+        if (buffer.len() as SampleTime) == n {
             buffer.push(sample);
         } else {
             buffer[n as usize] = sample;
@@ -36,14 +34,14 @@ impl SoundSource for BufferWriter {
     fn init_state(&self) -> SoundData {
         Box::new(BufferWriterData{source_data: self.source.init_state()})
     }
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<BufferWriterData>().unwrap();
         let sample = self.source.next_value(n, &mut data.source_data);
-        self.add_sample(n, sample.clone());
+        self.add_sample(n, sample);
         sample
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.source.duration()
     }
 
@@ -53,5 +51,3 @@ impl SoundSource for BufferWriter {
         Box::new(BufferWriter::new(input, buffer))
     }
 }
-
-}
\ No newline at end of file
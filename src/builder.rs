@@ -0,0 +1,555 @@
+// A chaining API for putting sound sources together in Rust code without
+// boxing every node by hand. A Builder holds the sample rate so that
+// frequencies and times can be given in Hz and seconds, and every Sound it
+// makes remembers the rate so that methods like lowpass() can do the same.
+//
+//     let b = Builder::new(sample_rate);
+//     let note = b.sine(440.0, 2.0).mul(b.envelope(&[(0.01, 1.0), (1.99, 0.0)])).lowpass(1000.0);
+//
+// A Sound converts into a DynSoundSource when it needs to be handed to the
+// rest of the crate.
+
+use std::cell::Cell;
+use std::sync::Arc;
+use std::ops::{Add, Mul};
+
+use crate::traits::{DynSoundSource, DynInstrument, SampleTime};
+use crate::knob::Knob;
+use crate::read_song::read_song;
+use crate::render::{render, write_wav};
+
+use crate::band_limited_random::BandLimitedRandom;
+use crate::chorus::Chorus;
+use crate::clip::Clip;
+use crate::coloured_noise::{ColouredNoise, NoiseColour};
+use crate::compressor::Compressor;
+use crate::convolve::{Convolve, ImpulseResponse};
+use crate::dc::DC;
+use crate::envelope::{Envelope, EnvelopePoint, Curve};
+use crate::fdn_reverb::FdnReverb;
+use crate::flanger::Flanger;
+use crate::fm_voice::{FmAlgorithm, FmInstrument, FmOperatorSettings};
+use crate::gate::Gate;
+use crate::import_wav::ImportWav;
+use crate::lfo::{Lfo, LfoShape};
+use crate::limiter::Limiter;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::noise::{Noise, derive_seed};
+use crate::poly_blep::{PolyBlep, Shape};
+use crate::pre_render::PreRender;
+use crate::ramp::Ramp;
+use crate::resampler::Quality;
+use crate::reverberator::Reverberator;
+use crate::saw::Saw;
+use crate::sequence::Sequence;
+use crate::sine::Sine;
+use crate::square::Square;
+use crate::stereo_delay::StereoDelay;
+use crate::time_box::TimeBox;
+use crate::triangle::Triangle;
+use crate::varispeed::Varispeed;
+use crate::velvet_noise::VelvetNoise;
+use crate::vibrato::Vibrato;
+use crate::wavetable::Interpolation;
+use crate::wavetable_oscillator::{WavetableBank, WavetableOscillator};
+
+use crate::filters::biquad_filter::{BiquadBand, BiquadFilter, BiquadType};
+use crate::filters::equaliser::Equaliser;
+use crate::filters::high_pass_filter::HighPassFilter;
+use crate::filters::ladder_filter::LadderFilter;
+use crate::filters::low_pass_filter::LowPassFilter;
+use crate::filters::state_variable_filter::{FilterMode, StateVariableFilter};
+
+#[derive(Clone)]
+pub struct Builder {
+    sample_rate: i32,
+    seed: u64,
+    // Number of random nodes made so far, used to give each its own seed
+    seed_count: Cell<u64>,
+}
+
+#[derive(Clone)]
+pub struct Sound {
+    source: DynSoundSource,
+    sample_rate: i32,
+}
+
+// A parameter that is either a fixed value in natural units (Hz, seconds,
+// gain) or another sound that modulates it. As with knobs in song files, fixed
+// values are scaled to the units the node wants but sounds are used as is.
+#[derive(Clone)]
+pub enum Param {
+    Value(f32),
+    Sound(Sound),
+}
+
+impl From<f32> for Param {
+    fn from(value: f32) -> Self {
+        Param::Value(value)
+    }
+}
+
+impl From<Sound> for Param {
+    fn from(sound: Sound) -> Self {
+        Param::Sound(sound)
+    }
+}
+
+impl Param {
+    fn into_knob(self, scale: f32) -> Knob {
+        match self {
+            Param::Value(v) => Knob::dc(v * scale),
+            Param::Sound(s) => Knob::new(s.source),
+        }
+    }
+
+    fn into_source(self, scale: f32, duration: SampleTime) -> DynSoundSource {
+        match self {
+            Param::Value(v) => Box::new(DC::new(v * scale, duration)),
+            Param::Sound(s) => s.source,
+        }
+    }
+
+    // Like into_source but sounds are scaled too, for nodes that want their
+    // input in other units
+    fn into_scaled_source(self, scale: f32, duration: SampleTime) -> DynSoundSource {
+        match self {
+            Param::Value(v) => Box::new(DC::new(v * scale, duration)),
+            Param::Sound(s) => s.mul(scale).source,
+        }
+    }
+}
+
+impl Builder {
+    pub fn new(sample_rate: i32) -> Self {
+        Builder::with_seed(sample_rate, 0)
+    }
+
+    pub fn with_seed(sample_rate: i32, seed: u64) -> Self {
+        Builder { sample_rate: sample_rate, seed: seed, seed_count: Cell::new(0) }
+    }
+
+    pub fn sample_rate(&self) -> i32 {
+        self.sample_rate
+    }
+
+    fn t2n(&self, t: f32) -> SampleTime {
+        (t as f64 * self.sample_rate as f64).round() as SampleTime
+    }
+
+    fn next_seed(&self) -> u64 {
+        let count = self.seed_count.get();
+        self.seed_count.set(count + 1);
+        derive_seed(self.seed, count)
+    }
+
+    pub fn wrap(&self, source: DynSoundSource) -> Sound {
+        Sound { source: source, sample_rate: self.sample_rate }
+    }
+
+    pub fn dc(&self, value: f32, duration: f32) -> Sound {
+        self.wrap(Box::new(DC::new(value, self.t2n(duration))))
+    }
+
+    pub fn sine(&self, freq: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(Sine::new(freq, Knob::dc(1.0), self.t2n(duration))))
+    }
+
+    pub fn saw(&self, freq: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(Saw::new(freq, Knob::dc(1.0), self.t2n(duration))))
+    }
+
+    pub fn square(&self, freq: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(Square::new(freq, Knob::dc(1.0), self.t2n(duration))))
+    }
+
+    pub fn triangle(&self, freq: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(Triangle::new(freq, Knob::dc(1.0), self.t2n(duration))))
+    }
+
+    // Anti-aliased saw, square, triangle or pulse. Duty is the fraction of the
+    // cycle a pulse is high and is ignored for the other shapes.
+    pub fn poly_blep(&self, shape: Shape, freq: impl Into<Param>, duty: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        let duty = duty.into().into_knob(1.0);
+        self.wrap(Box::new(PolyBlep::new(shape, freq, duty, Knob::dc(1.0), None, self.t2n(duration))))
+    }
+
+    // A note of `duration` seconds from FM operators wired by algorithm number.
+    // The sound carries on for the operators' release.
+    pub fn fm_voice(&self, operators: Vec<FmOperatorSettings>, algorithm: usize, feedback: f32, freq: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        let algorithm = FmAlgorithm::new(operators.len(), algorithm);
+        let instrument = FmInstrument::new(self.sample_rate, operators, algorithm, feedback);
+        self.wrap(Box::new(instrument.voice(freq, Knob::dc(1.0), self.t2n(duration))))
+    }
+
+    // Position runs from 0 for the bank's first frame to 1 for its last
+    pub fn wavetable_oscillator(&self, bank: &Arc<WavetableBank>, freq: impl Into<Param>, position: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        let position = position.into().into_knob(1.0);
+        self.wrap(Box::new(WavetableOscillator::new(bank.clone(), freq, position, Interpolation::Cubic, self.t2n(duration))))
+    }
+
+    // A bipolar low frequency oscillator, for use as a knob. Scale it with
+    // mul and mix in a dc to get other ranges.
+    pub fn lfo(&self, shape: LfoShape, freq: impl Into<Param>, duration: f32) -> Sound {
+        let freq = freq.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(Lfo::new(shape, freq, self.next_seed(), self.t2n(duration))))
+    }
+
+    pub fn noise(&self, duration: f32) -> Sound {
+        self.wrap(Box::new(Noise::new(self.next_seed(), self.t2n(duration))))
+    }
+
+    pub fn coloured_noise(&self, colour: NoiseColour, duration: f32) -> Sound {
+        self.wrap(Box::new(ColouredNoise::new(colour, self.next_seed(), self.t2n(duration))))
+    }
+
+    // Density in impulses per second
+    pub fn velvet_noise(&self, density: impl Into<Param>, duration: f32) -> Sound {
+        let density = density.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(VelvetNoise::new(density, self.next_seed(), self.t2n(duration))))
+    }
+
+    // Rate in new values per second
+    pub fn band_limited_random(&self, rate: impl Into<Param>, interpolation: Interpolation, duration: f32) -> Sound {
+        let rate = rate.into().into_knob(1.0 / self.sample_rate as f32);
+        self.wrap(Box::new(BandLimitedRandom::new(rate, interpolation, self.next_seed(), self.t2n(duration))))
+    }
+
+    // Ramps from 0 to amplitude every period seconds
+    pub fn ramp(&self, period: impl Into<Param>, amplitude: impl Into<Param>, duration: f32) -> Sound {
+        let period = period.into().into_knob(self.sample_rate as f32);
+        let amplitude = amplitude.into().into_knob(1.0);
+        self.wrap(Box::new(Ramp::new(period, amplitude, self.t2n(duration))))
+    }
+
+    // Points are (time offset in seconds from the previous point, value)
+    pub fn envelope(&self, points: &[(f32, f32)]) -> Sound {
+        let mut env_points = Vec::<EnvelopePoint>::new();
+        for (time_offset, value) in points {
+            env_points.push(EnvelopePoint::new(self.t2n(*time_offset), *value));
+        }
+        self.wrap(Box::new(Envelope::new(env_points)))
+    }
+
+    // Rises to 1 over the attack and falls to the sustain level over the
+    // decay, with times in seconds. The sustain holds until the note ends
+    // after `duration` seconds, then the release starts from wherever the
+    // envelope has got to. Decay and release follow `curve`.
+    pub fn adsr(
+        &self,
+        attack: impl Into<Param>,
+        decay: impl Into<Param>,
+        sustain: impl Into<Param>,
+        release: impl Into<Param>,
+        duration: f32,
+        curve: Curve,
+    ) -> Sound {
+        let sample_rate = self.sample_rate as f32;
+        self.wrap(Box::new(Envelope::adsr_knobs(
+            attack.into().into_knob(sample_rate),
+            Knob::dc(1.0),
+            decay.into().into_knob(sample_rate),
+            sustain.into().into_knob(1.0),
+            release.into().into_knob(sample_rate),
+            self.t2n(duration),
+            curve,
+        )))
+    }
+
+    // Play sounds at start times given in seconds
+    pub fn sequence(&self, sounds: Vec<(f32, Sound)>) -> Sound {
+        let mut sequence = Sequence::new();
+        for (start_time, sound) in sounds {
+            sequence.add(self.t2n(start_time), sound.source);
+        }
+        self.wrap(Box::new(sequence))
+    }
+
+    pub fn mix(&self, sounds: Vec<Sound>) -> Sound {
+        let mut mix = Mix::new();
+        for sound in sounds {
+            mix.add(sound.source);
+        }
+        self.wrap(Box::new(mix))
+    }
+
+    pub fn play(&self, instrument: &DynInstrument, freq: f32, duration: f32, strength: f32) -> Sound {
+        self.wrap(instrument.play(freq / self.sample_rate as f32, self.t2n(duration), strength))
+    }
+
+    pub fn import_wav(&self, filename: &str) -> Sound {
+        self.wrap(Box::new(ImportWav::new(filename, self.sample_rate, Quality::Best)))
+    }
+
+    pub fn read_song(&self, filename: &str) -> Sound {
+        // Songs are seeded from their own file so they sound the same however
+        // they are loaded
+        self.wrap(read_song(filename, self.sample_rate, None))
+    }
+}
+
+impl Sound {
+    // Feed this sound into a new node
+    fn map(self, node: impl FnOnce(DynSoundSource) -> DynSoundSource) -> Sound {
+        Sound { source: node(self.source), sample_rate: self.sample_rate }
+    }
+
+    fn t2n(&self, t: f32) -> SampleTime {
+        (t as f64 * self.sample_rate as f64).round() as SampleTime
+    }
+
+    fn angular(&self) -> f32 {
+        2.0 * std::f32::consts::PI / self.sample_rate as f32
+    }
+
+    pub fn source(&self) -> &DynSoundSource {
+        &self.source
+    }
+
+    pub fn into_source(self) -> DynSoundSource {
+        self.source
+    }
+
+    // Duration in seconds
+    pub fn duration(&self) -> f32 {
+        self.source.duration() as f32 / self.sample_rate as f32
+    }
+
+    // Multiply by another sound (ring modulation, envelopes) or by a fixed gain.
+    // This is the same as the * operator but doesn't need std::ops::Mul in scope.
+    #[allow(clippy::should_implement_trait)]
+    pub fn mul(self, other: impl Into<Param>) -> Sound {
+        let other = other.into().into_source(1.0, self.source.duration());
+        self.map(|source| {
+            let mut multiply = Multiply::new();
+            multiply.add(source, 0.0);
+            multiply.add(other, 0.0);
+            Box::new(multiply)
+        })
+    }
+
+    // Multiply by another sound after adding a dc offset to it, so a sound
+    // ranging -1 to 1 with an offset of 1 will range 0 to 2
+    pub fn mul_offset(self, other: Sound, offset: f32) -> Sound {
+        self.map(|source| {
+            let mut multiply = Multiply::new();
+            multiply.add(source, 0.0);
+            multiply.add(other.source, offset);
+            Box::new(multiply)
+        })
+    }
+
+    pub fn mix(self, other: Sound) -> Sound {
+        self.map(|source| {
+            let mut mix = Mix::new();
+            mix.add(source);
+            mix.add(other.source);
+            Box::new(mix)
+        })
+    }
+
+    // Start the sound later by delay seconds
+    pub fn delay(self, delay: f32) -> Sound {
+        let delay = self.t2n(delay);
+        self.map(|source| {
+            let mut sequence = Sequence::new();
+            sequence.add(delay, source);
+            Box::new(sequence)
+        })
+    }
+
+    // Cutoff in Hz, or a sound giving the cutoff in Hz
+    pub fn lowpass(self, cutoff: impl Into<Param>) -> Sound {
+        let cutoff = cutoff.into().into_scaled_source(self.angular(), self.source.duration());
+        self.map(|source| Box::new(LowPassFilter::new(source, cutoff)))
+    }
+
+    // Cutoff in Hz, or a sound giving the cutoff in Hz
+    pub fn highpass(self, cutoff: impl Into<Param>) -> Sound {
+        let cutoff = cutoff.into().into_scaled_source(self.angular(), self.source.duration());
+        self.map(|source| Box::new(HighPassFilter::new(source, cutoff)))
+    }
+
+    // Resonant filter with the cutoff in Hz and resonance from 0 to 1
+    pub fn svf(self, mode: FilterMode, cutoff: impl Into<Param>, resonance: impl Into<Param>) -> Sound {
+        let sample_rate = self.sample_rate;
+        let (cutoff, resonance) = (cutoff.into().into_knob(1.0), resonance.into().into_knob(1.0));
+        self.map(|source| Box::new(StateVariableFilter::new(source, mode, cutoff, resonance, sample_rate)))
+    }
+
+    // Moog style low pass with the cutoff in Hz, resonance from 0 to 1 and
+    // drive into the saturation
+    pub fn ladder(self, cutoff: impl Into<Param>, resonance: impl Into<Param>, drive: impl Into<Param>) -> Sound {
+        let sample_rate = self.sample_rate;
+        let (cutoff, resonance, drive) = (cutoff.into().into_knob(1.0), resonance.into().into_knob(1.0), drive.into().into_knob(1.0));
+        self.map(|source| Box::new(LadderFilter::new(source, cutoff, resonance, drive, sample_rate)))
+    }
+
+    // Audio EQ Cookbook filter with the frequency in Hz and the gain in dB
+    // (only used by the peaking and shelving types)
+    pub fn biquad(self, filter_type: BiquadType, freq: impl Into<Param>, q: impl Into<Param>, gain: impl Into<Param>) -> Sound {
+        let band = BiquadBand::new(filter_type, freq.into().into_knob(1.0), q.into().into_knob(1.0), gain.into().into_knob(1.0), self.sample_rate);
+        self.map(|source| Box::new(BiquadFilter::new(source, band)))
+    }
+
+    // Bands of (type, frequency in Hz, Q, gain in dB), applied in order
+    pub fn eq(self, bands: &[(BiquadType, f32, f32, f32)]) -> Sound {
+        let bands = bands.iter()
+            .map(|(filter_type, freq, q, gain)| BiquadBand::new(*filter_type, Knob::dc(*freq), Knob::dc(*q), Knob::dc(*gain), self.sample_rate))
+            .collect();
+        self.map(|source| Box::new(Equaliser::new(source, bands)))
+    }
+
+    pub fn clip(self, limit: f32) -> Sound {
+        self.map(|source| Box::new(Clip::new(limit, source)))
+    }
+
+    // Turn down the parts over threshold dB so they come out 1 / ratio as far
+    // over, with attack and release in seconds. Compressor has the rest of the
+    // settings.
+    pub fn compress(self, threshold: f32, ratio: f32, attack: f32, release: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        self.map(|source| Box::new(Compressor::new(source, threshold, ratio, sample_rate).attack(attack).release(release)))
+    }
+
+    // Compress on the level of the sidechain instead, to turn this down
+    // whenever the sidechain plays
+    pub fn duck(self, sidechain: Sound, threshold: f32, ratio: f32, attack: f32, release: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        self.map(|source| Box::new(Compressor::new(source, threshold, ratio, sample_rate)
+            .attack(attack)
+            .release(release)
+            .sidechain(sidechain.source)))
+    }
+
+    // Keep every sample under ceiling dB, looking 5 ms ahead
+    pub fn limit(self, ceiling: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        self.map(|source| Box::new(Limiter::new(source, ceiling, sample_rate)))
+    }
+
+    // Shut out everything under threshold dB. Gate has the expander settings.
+    pub fn gate(self, threshold: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        self.map(|source| Box::new(Gate::new(source, threshold, sample_rate)))
+    }
+
+    // Cut the sound to duration seconds with a linear fade of ramp_time
+    // seconds at each end
+    pub fn time_box(self, duration: f32, ramp_time: f32) -> Sound {
+        let (duration, ramp_time) = (self.t2n(duration), self.t2n(ramp_time));
+        self.map(|source| Box::new(TimeBox::new(duration, ramp_time, source)))
+    }
+
+    pub fn reverb(self, gain: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        self.map(|source| Box::new(Reverberator::new(source, gain, sample_rate)))
+    }
+
+    // Convolve with an impulse response (see ImpulseResponse::load for
+    // reading one from a file), with mix from 0 (dry) to 1 (only the
+    // convolved sound)
+    pub fn convolve(self, impulse_response: &ImpulseResponse, mix: f32) -> Sound {
+        self.map(|source| Box::new(Convolve::new(source, impulse_response, mix)))
+    }
+
+    // Feedback delay network reverb with a tail of rt60 seconds and mix from
+    // 0 (dry) to 1 (only reverb). FdnReverb has the rest of the settings.
+    pub fn fdn_reverb(self, rt60: f32, mix: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        self.map(|source| Box::new(FdnReverb::new(source, sample_rate).rt60(rt60).mix(mix)))
+    }
+
+    // Rate in Hz and depth in seconds either side of a 20 ms delay, with
+    // feedback from -0.95 to 0.95 and mix from 0 (dry) to 1 (only the chorus).
+    // Chorus has the rest of the settings.
+    pub fn chorus(self, voices: usize, rate: impl Into<Param>, depth: impl Into<Param>, feedback: impl Into<Param>, mix: impl Into<Param>) -> Sound {
+        let sample_rate = self.sample_rate;
+        let rate = rate.into().into_knob(1.0 / sample_rate as f32);
+        let depth = depth.into().into_knob(sample_rate as f32);
+        let feedback = feedback.into().into_knob(1.0);
+        let mix = mix.into().into_knob(1.0);
+        self.map(|source| Box::new(Chorus::new(source, rate, depth, feedback, mix, sample_rate).voices(voices)))
+    }
+
+    // Rate in Hz and depth in seconds either side of a 2.5 ms delay, with
+    // feedback from -0.95 to 0.95 and mix from 0 (dry) to 1 (only the swept
+    // copy)
+    pub fn flanger(self, rate: impl Into<Param>, depth: impl Into<Param>, feedback: impl Into<Param>, mix: impl Into<Param>, through_zero: bool) -> Sound {
+        let sample_rate = self.sample_rate;
+        let rate = rate.into().into_knob(1.0 / sample_rate as f32);
+        let depth = depth.into().into_knob(sample_rate as f32);
+        let feedback = feedback.into().into_knob(1.0);
+        let mix = mix.into().into_knob(1.0);
+        self.map(|source| Box::new(Flanger::new(source, rate, depth, feedback, mix, sample_rate).through_zero(through_zero)))
+    }
+
+    // Echoes after left and right seconds, each at feedback times the level of
+    // the one before, with mix from 0 (dry) to 1 (only echoes). StereoDelay
+    // has the ping pong mode and feedback filter.
+    pub fn stereo_delay(self, left: f32, right: f32, feedback: f32, mix: impl Into<Param>) -> Sound {
+        let sample_rate = self.sample_rate;
+        let (left, right) = (self.t2n(left) as usize, self.t2n(right) as usize);
+        let mix = mix.into().into_knob(1.0);
+        self.map(|source| Box::new(StereoDelay::new(source, left, right, Knob::dc(feedback), mix, sample_rate).tail_for(feedback)))
+    }
+
+    // Rate in Hz and depth in seconds (up to max_depth)
+    pub fn vibrato(self, rate: impl Into<Param>, depth: impl Into<Param>, max_depth: f32) -> Sound {
+        let sample_rate = self.sample_rate;
+        let rate = rate.into().into_knob(1.0 / sample_rate as f32);
+        let depth = depth.into().into_knob(sample_rate as f32);
+        self.map(|source| Box::new(Vibrato::new(source, rate, depth, sample_rate).max_depth(max_depth * sample_rate as f32)))
+    }
+
+    // Play at a speed that changes pitch and time together, like a tape
+    pub fn varispeed(self, speed: impl Into<Param>, duration: f32) -> Sound {
+        let speed = speed.into().into_knob(1.0);
+        let duration = self.t2n(duration);
+        self.map(|source| Box::new(Varispeed::new(source, speed, Quality::Best, duration)))
+    }
+
+    // Render now and play back from memory. Useful for sounds that are used
+    // more than once or are expensive to calculate.
+    pub fn pre_render(self) -> Sound {
+        self.map(|source| Box::new(PreRender::new(source)))
+    }
+
+    pub fn render(&self) -> Vec<(f32, f32)> {
+        render(&self.source)
+    }
+
+    pub fn export_wav(&self, filename: &str) -> Result<(), hound::Error> {
+        write_wav(filename, self.sample_rate, &self.render())
+    }
+}
+
+impl From<Sound> for DynSoundSource {
+    fn from(sound: Sound) -> Self {
+        sound.source
+    }
+}
+
+impl<T: Into<Param>> Mul<T> for Sound {
+    type Output = Sound;
+
+    fn mul(self, other: T) -> Sound {
+        Sound::mul(self, other)
+    }
+}
+
+impl Add for Sound {
+    type Output = Sound;
+
+    fn add(self, other: Sound) -> Sound {
+        self.mix(other)
+    }
+}
@@ -0,0 +1,164 @@
+// A chorus: several copies of the input, each delayed by a slowly wobbling
+// amount, so they drift slightly in and out of tune with each other like a
+// group of players. The voices' wobbles are spread evenly through the cycle
+// and the voices are panned across the stereo field by the spread.
+//
+// All the voices read from the same delay buffer (one for each side). Feedback
+// sends the voices back into it, as the flanger does, for a thicker, more
+// metallic sound.
+
+use std::f64::consts::PI;
+use std::f32::consts::{FRAC_PI_4, SQRT_2};
+
+use crate::delay_buffer::DelayBuffer;
+use crate::flanger::MAX_FEEDBACK;
+use crate::knob::Knob;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+#[derive(Clone)]
+pub struct Chorus {
+    input: DynSoundSource,
+    rate: Knob, // Cycles per sample
+    depth: Knob, // Samples either side of the delay
+    feedback: Knob,
+    mix: Knob,
+    voices: usize,
+    delay: f32, // Samples
+    spread: f32,
+}
+
+impl Chorus {
+    // Three voices around a 20 ms delay, spread all the way across
+    pub fn new(input: DynSoundSource, rate: Knob, depth: Knob, feedback: Knob, mix: Knob, sample_rate: i32) -> Self {
+        Chorus {
+            input: input,
+            rate: rate,
+            depth: depth,
+            feedback: feedback,
+            mix: mix,
+            voices: 3,
+            delay: 0.02 * sample_rate as f32,
+            spread: 1.0,
+        }
+    }
+
+    pub fn voices(mut self, voices: usize) -> Self {
+        self.voices = voices.max(1);
+        self
+    }
+
+    // The delay in samples the voices wobble around. The depth is kept below
+    // it.
+    pub fn delay(mut self, delay: f32) -> Self {
+        self.delay = delay.max(1.0);
+        self
+    }
+
+    // From 0 (all the voices in the middle) to 1 (from hard left to hard right)
+    pub fn spread(mut self, spread: f32) -> Self {
+        self.spread = spread.clamp(0.0, 1.0);
+        self
+    }
+
+    // Left and right gains for each voice, keeping the power the same wherever
+    // it is panned and scaled so the voices together are as loud as one
+    fn voice_gains(&self) -> Vec<(f32, f32)> {
+        let scale = SQRT_2 / self.voices as f32;
+        (0..self.voices).map(|i| {
+            let pan = if self.voices == 1 { 0.0 } else { self.spread * (2.0 * i as f32 / (self.voices - 1) as f32 - 1.0) };
+            let angle = (pan + 1.0) * FRAC_PI_4;
+            (angle.cos() * scale, angle.sin() * scale)
+        }).collect()
+    }
+
+    fn new_data(&self) -> ChorusData {
+        let buffer = DelayBuffer::new(2 * self.delay.ceil() as usize);
+        ChorusData {
+            input_data: self.input.init_state(),
+            rate_data: self.rate.init_state(),
+            depth_data: self.depth.init_state(),
+            feedback_data: self.feedback.init_state(),
+            mix_data: self.mix.init_state(),
+            next_n: 0,
+            buffers: [buffer.clone(), buffer],
+            gains: self.voice_gains(),
+            phase: 0.0,
+        }
+    }
+}
+
+struct ChorusData {
+    input_data: SoundData,
+    rate_data: SoundData,
+    depth_data: SoundData,
+    feedback_data: SoundData,
+    mix_data: SoundData,
+    next_n: SampleTime,
+    buffers: [DelayBuffer; 2],
+    gains: Vec<(f32, f32)>,
+    phase: f64, // Fraction of a cycle
+}
+
+impl SoundSource for Chorus {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<ChorusData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let dry = if n < self.input.duration() { self.input.next_value(n, &mut data.input_data) } else { (0.0, 0.0) };
+        let rate = self.rate.next_value(n, &mut data.rate_data) as f64;
+        let depth = self.depth.next_value(n, &mut data.depth_data).clamp(0.0, self.delay - 1.0);
+        let feedback = self.feedback.next_value(n, &mut data.feedback_data).clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+        let mix = self.mix.next_value(n, &mut data.mix_data).clamp(0.0, 1.0);
+
+        let mut wet = (0.0, 0.0);
+        // The voices on each side before panning, averaged for the feedback
+        let mut voices = (0.0, 0.0);
+        for (i, gains) in data.gains.iter().enumerate() {
+            let phase = data.phase + i as f64 / self.voices as f64;
+            let delay = self.delay + depth * (2.0 * PI * phase).sin() as f32;
+            let voice = (data.buffers[0].read(delay), data.buffers[1].read(delay));
+            wet.0 += voice.0 * gains.0;
+            wet.1 += voice.1 * gains.1;
+            voices.0 += voice.0 / self.voices as f32;
+            voices.1 += voice.1 / self.voices as f32;
+        }
+        data.buffers[0].push(dry.0 + feedback * voices.0);
+        data.buffers[1].push(dry.1 + feedback * voices.1);
+        data.phase = (data.phase + rate).rem_euclid(1.0);
+        (dry.0 * (1.0 - mix) + wet.0 * mix, dry.1 * (1.0 - mix) + wet.1 * mix)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(2 * self.delay.ceil() as SampleTime)
+    }
+
+    // params: input, voices, rate, depth in ms, delay in ms, feedback, spread,
+    // mix
+    // The rate is in Hz or a note value like 1/4 at the song's tempo. Rate,
+    // depth, feedback and mix can be sounds. The voices wobble by up to the
+    // depth either side of the delay. Feedback runs from -0.95 to 0.95, and
+    // spread and mix from 0 to 1.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let sample_rate = reader.sample_rate as f32;
+        let voices = params[1].parse::<usize>().unwrap();
+        let rate = reader.get_rate_knob(&params[2], 1.0 / sample_rate);
+        let depth = reader.get_value_knob(&params[3], sample_rate / 1000.0);
+        let delay = params[4].parse::<f32>().unwrap() * sample_rate / 1000.0;
+        let feedback = reader.get_value_knob(&params[5], 1.0);
+        let spread = params[6].parse::<f32>().unwrap();
+        let mix = reader.get_value_knob(&params[7], 1.0);
+        Box::new(Chorus::new(input, rate, depth, feedback, mix, reader.sample_rate)
+            .voices(voices)
+            .delay(delay)
+            .spread(spread))
+    }
+}
@@ -0,0 +1,130 @@
+// White noise shaped to have a sloped spectrum:
+//   pink   -3 dB/octave (equal energy per octave)
+//   brown  -6 dB/octave (also called red noise, a random walk)
+//   blue   +3 dB/octave
+//   violet +6 dB/octave
+// Pink uses Paul Kellet's refined filter, which is within 0.05 dB of the ideal
+// slope above about 10 Hz at 44.1 kHz. Brown is a leaky integrator so that it
+// doesn't wander off. Blue and violet are the first difference of pink and
+// white noise, which tilts the spectrum up by 6 dB/octave. Each colour is scaled
+// so that its peaks stay roughly within -1 to 1.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum NoiseColour {
+    White,
+    Pink,
+    Brown,
+    Blue,
+    Violet,
+}
+
+impl NoiseColour {
+    pub fn from_param(param: &str) -> Self {
+        match param.to_lowercase().as_str() {
+            "white" => NoiseColour::White,
+            "pink" => NoiseColour::Pink,
+            "brown" | "red" => NoiseColour::Brown,
+            "blue" => NoiseColour::Blue,
+            "violet" | "purple" => NoiseColour::Violet,
+            _ => panic!("Noise colour must be white, pink, brown, red, blue, violet or purple")
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct ColouredNoise {
+    colour: NoiseColour,
+    seed: u64,
+    duration: SampleTime,
+}
+
+impl ColouredNoise {
+    pub fn new(colour: NoiseColour, seed: u64, duration: SampleTime) -> Self {
+        ColouredNoise { colour: colour, seed: seed, duration: duration }
+    }
+}
+
+struct ColouredNoiseData {
+    rng: StdRng,
+    // Pink filter state
+    b: [f32; 7],
+    // Brown integrator state
+    brown: f32,
+    // Previous pink or white value for the blue and violet differences
+    prev: f32,
+}
+
+impl ColouredNoiseData {
+    fn pink(&mut self, white: f32) -> f32 {
+        let b = &mut self.b;
+        b[0] = 0.99886 * b[0] + white * 0.0555179;
+        b[1] = 0.99332 * b[1] + white * 0.0750759;
+        b[2] = 0.96900 * b[2] + white * 0.153852;
+        b[3] = 0.86650 * b[3] + white * 0.3104856;
+        b[4] = 0.55000 * b[4] + white * 0.5329522;
+        b[5] = -0.7616 * b[5] - white * 0.0168980;
+        let pink = b[0] + b[1] + b[2] + b[3] + b[4] + b[5] + b[6] + white * 0.5362;
+        b[6] = white * 0.115926;
+        pink * 0.11
+    }
+}
+
+impl SoundSource for ColouredNoise {
+    fn init_state(&self) -> SoundData {
+        Box::new(ColouredNoiseData {
+            rng: StdRng::seed_from_u64(self.seed),
+            b: [0.0; 7],
+            brown: 0.0,
+            prev: 0.0,
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            (0.0, 0.0)
+        } else {
+            let data = state.downcast_mut::<ColouredNoiseData>().unwrap();
+            let white: f32 = data.rng.gen_range(-1.0..1.0);
+            let val = match self.colour {
+                NoiseColour::White => white,
+                NoiseColour::Pink => data.pink(white),
+                NoiseColour::Brown => {
+                    data.brown = (data.brown + 0.02 * white) / 1.02;
+                    data.brown * 3.5
+                },
+                NoiseColour::Blue => {
+                    let pink = data.pink(white);
+                    let blue = pink - data.prev;
+                    data.prev = pink;
+                    blue * 3.0
+                },
+                NoiseColour::Violet => {
+                    let violet = white - data.prev;
+                    data.prev = white;
+                    violet * 0.5
+                },
+            };
+            (val, val)
+        }
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let colour = NoiseColour::from_param(&params[0]);
+        let duration = reader.get_duration(&params[1]);
+        // An optional third parameter fixes the seed for this node
+        let seed = match params.get(2) {
+            Some(seed) => seed.parse::<u64>().unwrap(),
+            None => reader.next_seed(),
+        };
+        Box::new(Self::new(colour, seed, duration))
+    }
+}
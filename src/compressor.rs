@@ -0,0 +1,173 @@
+// A compressor: when the level goes over the threshold the sound is turned
+// down so that each dB over comes out as only 1 / ratio dB over. The knee
+// eases into the compression over a range of levels around the threshold
+// rather than starting suddenly at it. The gain reduction jumps up to follow
+// the level and falls back at the release rate, so it holds between the peaks
+// of a waveform, then the attack smooths the jumps. The makeup gain brings the
+// quieter result back up.
+//
+// With a sidechain the level is measured from another sound instead, so one
+// sound can be turned down whenever another plays, like a pad ducking under
+// the kick.
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::dynamics::{Detection, LevelDetector, db_to_gain, smooth, time_coefficient};
+
+#[derive(Clone)]
+pub struct Compressor {
+    input: DynSoundSource,
+    sidechain: Option<DynSoundSource>,
+    threshold: f32, // dB
+    ratio: f32,
+    knee: f32, // dB
+    attack: f32, // Seconds
+    release: f32, // Seconds
+    makeup: f32, // dB
+    detection: Detection,
+    sample_rate: i32,
+}
+
+impl Compressor {
+    // A hard knee, 10 ms attack, 100 ms release, no makeup gain and peak
+    // detection
+    pub fn new(input: DynSoundSource, threshold: f32, ratio: f32, sample_rate: i32) -> Self {
+        Compressor {
+            input: input,
+            sidechain: None,
+            threshold: threshold,
+            ratio: ratio.max(1.0),
+            knee: 0.0,
+            attack: 0.01,
+            release: 0.1,
+            makeup: 0.0,
+            detection: Detection::Peak,
+            sample_rate: sample_rate,
+        }
+    }
+
+    // The width in dB of the range of levels the compression eases in over,
+    // centred on the threshold
+    pub fn knee(mut self, knee: f32) -> Self {
+        self.knee = knee.max(0.0);
+        self
+    }
+
+    pub fn attack(mut self, attack: f32) -> Self {
+        self.attack = attack.max(0.0);
+        self
+    }
+
+    pub fn release(mut self, release: f32) -> Self {
+        self.release = release.max(0.0);
+        self
+    }
+
+    pub fn makeup(mut self, makeup: f32) -> Self {
+        self.makeup = makeup;
+        self
+    }
+
+    pub fn detection(mut self, detection: Detection) -> Self {
+        self.detection = detection;
+        self
+    }
+
+    pub fn sidechain(mut self, sidechain: DynSoundSource) -> Self {
+        self.sidechain = Some(sidechain);
+        self
+    }
+
+    // The change in dB (zero or below) for a level in dB
+    fn gain_reduction(&self, level: f32) -> f32 {
+        let over = level - self.threshold;
+        let slope = 1.0 / self.ratio - 1.0;
+        if 2.0 * over <= -self.knee {
+            0.0
+        } else if 2.0 * over < self.knee {
+            // Eases in along a curve that meets both straight parts smoothly
+            slope * (over + self.knee / 2.0).powi(2) / (2.0 * self.knee)
+        } else {
+            slope * over
+        }
+    }
+
+    fn new_data(&self) -> CompressorData {
+        CompressorData {
+            input_data: self.input.init_state(),
+            sidechain_data: self.sidechain.as_ref().map(|sidechain| sidechain.init_state()),
+            next_n: 0,
+            detector: LevelDetector::new(self.detection, self.sample_rate),
+            attack: time_coefficient(self.attack, self.sample_rate),
+            release: time_coefficient(self.release, self.sample_rate),
+            held: 0.0,
+            gain_reduction: 0.0,
+        }
+    }
+}
+
+struct CompressorData {
+    input_data: SoundData,
+    sidechain_data: Option<SoundData>,
+    next_n: SampleTime,
+    detector: LevelDetector,
+    // Smoothing coefficients
+    attack: f32,
+    release: f32,
+    // dB
+    held: f32,
+    gain_reduction: f32,
+}
+
+impl SoundSource for Compressor {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<CompressorData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let input = self.input.next_value(n, &mut data.input_data);
+        let detected = match (&self.sidechain, &mut data.sidechain_data) {
+            (Some(sidechain), Some(sidechain_data)) if n < sidechain.duration() => sidechain.next_value(n, sidechain_data),
+            (Some(_), _) => (0.0, 0.0),
+            _ => input,
+        };
+        let target = self.gain_reduction(data.detector.level(detected));
+        smooth(&mut data.held, target, data.release);
+        data.held = data.held.min(target);
+        smooth(&mut data.gain_reduction, data.held, data.attack);
+        let gain = db_to_gain(data.gain_reduction + self.makeup);
+        (input.0 * gain, input.1 * gain)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration()
+    }
+
+    // params: input, threshold in dB, ratio, knee in dB, attack in seconds,
+    // release in seconds, makeup gain in dB, detection (peak or rms),
+    // [sidechain]
+    // Levels are relative to full scale, so a threshold of -12 is a quarter of
+    // the loudest a sample can be. The sidechain is a sound to measure the
+    // level from instead of the input, for ducking.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let param = |i: usize| params[i].parse::<f32>().unwrap();
+        let mut compressor = Compressor::new(input, param(1), param(2), reader.sample_rate)
+            .knee(param(3))
+            .attack(param(4))
+            .release(param(5))
+            .makeup(param(6))
+            .detection(Detection::from_param(&params[7]));
+        if let Some(sidechain) = params.get(8) {
+            compressor = compressor.sidechain(reader.get_sound(sidechain));
+        }
+        Box::new(compressor)
+    }
+}
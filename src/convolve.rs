@@ -0,0 +1,298 @@
+// Convolution with an impulse response, for reverbs made from recordings of
+// real rooms (or anything else with a recorded response). The impulse
+// response is read from a sound file with ImportWav, so it is resampled to
+// the song's rate and can be trimmed, or rendered from a sound.
+//
+// Mono impulse responses are applied to each side. Stereo ones apply their
+// left channel to the left and their right channel to the right. Four channel
+// "true stereo" ones hold the responses from the left input to the left and
+// right outputs followed by those from the right input to the left and right
+// outputs.
+//
+// Convolving directly would take a multiply for every impulse response sample
+// for every output sample, so the impulse response is cut into blocks and the
+// convolution done with FFTs (uniformly partitioned overlap-save). The input
+// is read a block ahead so the output is not delayed.
+
+use std::f32::consts::PI;
+use std::sync::Arc;
+use rustfft::{Fft, FftPlanner};
+use rustfft::num_complex::Complex;
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime, is_forever};
+
+use crate::import_wav::{ImportWav, Channels, read_interleaved, select_channels, is_sound_file};
+use crate::render::render;
+use crate::resampler::Quality;
+
+const BLOCK_SIZE: usize = 1024;
+
+// The response from each input side to each output side. Empty responses are
+// left out of the convolution.
+#[derive(Clone)]
+pub struct ImpulseResponse {
+    // Indexed by input side then output side
+    paths: [[Vec<f32>; 2]; 2],
+}
+
+impl ImpulseResponse {
+    // Each side of the response goes from one side of the input to the same
+    // side of the output
+    pub fn stereo(samples: &[(f32, f32)]) -> Self {
+        ImpulseResponse {
+            paths: [
+                [samples.iter().map(|s| s.0).collect(), Vec::new()],
+                [Vec::new(), samples.iter().map(|s| s.1).collect()],
+            ],
+        }
+    }
+
+    // Responses from the left input and from the right input, each to the left
+    // and right outputs
+    pub fn true_stereo(from_left: &[(f32, f32)], from_right: &[(f32, f32)]) -> Self {
+        ImpulseResponse {
+            paths: [
+                [from_left.iter().map(|s| s.0).collect(), from_left.iter().map(|s| s.1).collect()],
+                [from_right.iter().map(|s| s.0).collect(), from_right.iter().map(|s| s.1).collect()],
+            ],
+        }
+    }
+
+    // Read `length` seconds (or the rest of the file) from `start` seconds in.
+    // The file's channels decide between stereo and true stereo. The file is
+    // only decoded once, even when it's split into two pairs of channels.
+    pub fn load(filename: &str, sample_rate: i32, start: f32, length: Option<f32>) -> Self {
+        let (interleaved, channels, file_sample_rate) = read_interleaved(filename);
+        let import = |selection| ImportWav::from_samples(select_channels(&interleaved, channels, selection),
+            file_sample_rate, sample_rate, Quality::Best, start, length);
+        match channels {
+            1 | 2 => ImpulseResponse::stereo(import(Channels::Auto).samples()),
+            4 => ImpulseResponse::true_stereo(import(Channels::Pair(0, 1)).samples(), import(Channels::Pair(2, 3)).samples()),
+            channels => panic!("Impulse responses must have 1, 2 or 4 channels, not {}", channels),
+        }
+    }
+
+    // Keep `length` samples (or the rest) from `start` samples in
+    pub fn trim(mut self, start: usize, length: Option<usize>) -> Self {
+        for path in self.paths.iter_mut().flatten() {
+            let start = start.min(path.len());
+            let end = length.map_or(path.len(), |length| (start + length).min(path.len()));
+            path.truncate(end);
+            path.drain(..start);
+        }
+        self
+    }
+
+    // Fade out over the last `fade` samples, to end a response that was cut
+    // short without a click
+    pub fn fade_out(mut self, fade: usize) -> Self {
+        for path in self.paths.iter_mut().flatten() {
+            let fade = fade.min(path.len());
+            let fade_start = path.len() - fade;
+            for (i, x) in path[fade_start..].iter_mut().enumerate() {
+                *x *= 0.5 + 0.5 * (PI * (i + 1) as f32 / fade as f32).cos();
+            }
+        }
+        self
+    }
+
+    pub fn len(&self) -> usize {
+        self.paths.iter().flatten().map(|path| path.len()).max().unwrap()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+}
+
+// One input side's response to one output side, as the spectra of its blocks
+struct Path {
+    from: usize,
+    to: usize,
+    partitions: Vec<Vec<Complex<f32>>>,
+}
+
+#[derive(Clone)]
+pub struct Convolve {
+    input: DynSoundSource,
+    paths: Arc<Vec<Path>>,
+    // The most blocks in any path
+    num_partitions: usize,
+    length: usize, // Of the impulse response
+    mix: f32,
+    fft: Arc<dyn Fft<f32>>,
+    ifft: Arc<dyn Fft<f32>>,
+}
+
+impl Convolve {
+    // Mix runs from 0 (dry) to 1 (only the convolved sound)
+    pub fn new(input: DynSoundSource, impulse_response: &ImpulseResponse, mix: f32) -> Self {
+        let mut planner = FftPlanner::new();
+        let fft = planner.plan_fft_forward(2 * BLOCK_SIZE);
+        let ifft = planner.plan_fft_inverse(2 * BLOCK_SIZE);
+        let mut paths = Vec::new();
+        for (from, responses) in impulse_response.paths.iter().enumerate() {
+            for (to, response) in responses.iter().enumerate() {
+                if response.is_empty() {
+                    continue;
+                }
+                // Each block goes in the first half of an FFT, with the second
+                // half left as zeros. The signal is real so only the first
+                // half of the spectrum is needed.
+                let partitions = response.chunks(BLOCK_SIZE).map(|block| {
+                    let mut buffer = vec![Complex::new(0.0, 0.0); 2 * BLOCK_SIZE];
+                    for (b, x) in buffer.iter_mut().zip(block) {
+                        b.re = *x;
+                    }
+                    fft.process(&mut buffer);
+                    buffer.truncate(BLOCK_SIZE + 1);
+                    buffer
+                }).collect();
+                paths.push(Path { from: from, to: to, partitions: partitions });
+            }
+        }
+        let num_partitions = paths.iter().map(|path| path.partitions.len()).max().unwrap_or(1);
+        Convolve {
+            input: input,
+            paths: Arc::new(paths),
+            num_partitions: num_partitions,
+            length: impulse_response.len(),
+            mix: mix,
+            fft: fft,
+            ifft: ifft,
+        }
+    }
+
+    fn new_data(&self) -> ConvolveData {
+        ConvolveData {
+            input_data: self.input.init_state(),
+            next_n: 0,
+            block_start: -(BLOCK_SIZE as SampleTime),
+            input_blocks: [vec![0.0; 2 * BLOCK_SIZE], vec![0.0; 2 * BLOCK_SIZE]],
+            spectra: std::array::from_fn(|_| vec![vec![Complex::new(0.0, 0.0); BLOCK_SIZE + 1]; self.num_partitions]),
+            newest: 0,
+            dry: vec![(0.0, 0.0); BLOCK_SIZE],
+            wet: vec![(0.0, 0.0); BLOCK_SIZE],
+        }
+    }
+
+    // Read the next block of input and work out the output for it
+    fn process_block(&self, data: &mut ConvolveData) {
+        let input_duration = self.input.duration();
+        for (i, dry) in data.dry.iter_mut().enumerate() {
+            let n = data.block_start + i as SampleTime;
+            *dry = if n < input_duration { self.input.next_value(n, &mut data.input_data) } else { (0.0, 0.0) };
+        }
+        // The spectra are kept in a ring with the newest first
+        data.newest = (data.newest + self.num_partitions - 1) % self.num_partitions;
+        let mut buffer = vec![Complex::new(0.0, 0.0); 2 * BLOCK_SIZE];
+        for ch in 0..2 {
+            let block = &mut data.input_blocks[ch];
+            block.copy_within(BLOCK_SIZE.., 0);
+            for (x, dry) in block[BLOCK_SIZE..].iter_mut().zip(&data.dry) {
+                *x = if ch == 0 { dry.0 } else { dry.1 };
+            }
+            for (b, x) in buffer.iter_mut().zip(block.iter()) {
+                *b = Complex::new(*x, 0.0);
+            }
+            self.fft.process(&mut buffer);
+            data.spectra[ch][data.newest].copy_from_slice(&buffer[..=BLOCK_SIZE]);
+        }
+
+        let mut output = [vec![Complex::new(0.0, 0.0); BLOCK_SIZE + 1], vec![Complex::new(0.0, 0.0); BLOCK_SIZE + 1]];
+        for path in self.paths.iter() {
+            for (k, partition) in path.partitions.iter().enumerate() {
+                let spectrum = &data.spectra[path.from][(data.newest + k) % self.num_partitions];
+                for ((out, x), h) in output[path.to].iter_mut().zip(spectrum).zip(partition) {
+                    *out += x * h;
+                }
+            }
+        }
+        for (ch, spectrum) in output.iter().enumerate() {
+            // Fill in the second half of the spectrum, which mirrors the first
+            buffer[..=BLOCK_SIZE].copy_from_slice(spectrum);
+            for i in 1..BLOCK_SIZE {
+                buffer[2 * BLOCK_SIZE - i] = spectrum[i].conj();
+            }
+            self.ifft.process(&mut buffer);
+            // The first half wraps round, the second half is this block
+            let scale = 1.0 / (2 * BLOCK_SIZE) as f32;
+            for (wet, y) in data.wet.iter_mut().zip(&buffer[BLOCK_SIZE..]) {
+                if ch == 0 {
+                    wet.0 = y.re * scale;
+                } else {
+                    wet.1 = y.re * scale;
+                }
+            }
+        }
+    }
+}
+
+struct ConvolveData {
+    input_data: SoundData,
+    next_n: SampleTime,
+    // Where the current block starts
+    block_start: SampleTime,
+    // The previous and current blocks of input for each side
+    input_blocks: [Vec<f32>; 2],
+    // The spectra of recent blocks of input for each side
+    spectra: [Vec<Vec<Complex<f32>>>; 2],
+    newest: usize,
+    // The input and convolved output for the current block
+    dry: Vec<(f32, f32)>,
+    wet: Vec<(f32, f32)>,
+}
+
+impl SoundSource for Convolve {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<ConvolveData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        while n >= data.block_start + BLOCK_SIZE as SampleTime {
+            data.block_start += BLOCK_SIZE as SampleTime;
+            self.process_block(data);
+        }
+        let i = (n - data.block_start) as usize;
+        let (dry, wet) = (data.dry[i], data.wet[i]);
+        (dry.0 * (1.0 - self.mix) + wet.0 * self.mix, dry.1 * (1.0 - self.mix) + wet.1 * self.mix)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(self.length as SampleTime)
+    }
+
+    // params: input, impulse response, mix, [start], [length], [fade]
+    // The impulse response is a sound file or a sound name. Mix runs from 0
+    // (dry) to 1 (only the convolved sound). The part of the impulse response
+    // to use starts at start seconds in and lasts length seconds (or "all"),
+    // then fades out over its last fade seconds.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let mix = params[2].parse::<f32>().unwrap();
+        let seconds = |time: &String| time.parse::<f32>()
+            .unwrap_or_else(|_| panic!("Couldn't parse \"{}\" as a time in seconds", time));
+        let start = params.get(3).map_or(0.0, seconds);
+        let length = params.get(4).filter(|length| *length != "all").map(seconds);
+        let fade = params.get(5).map_or(0.0, seconds);
+        let sample_rate = reader.sample_rate as f32;
+        let t2n = |t: f32| (t * sample_rate).round().max(0.0) as usize;
+        let impulse_response = if is_sound_file(&params[1]) {
+            ImpulseResponse::load(&params[1], reader.sample_rate, start, length)
+        } else {
+            let sound = reader.get_sound(&params[1]);
+            if is_forever(sound.duration()) {
+                panic!("Can't use a sound that goes on for ever as an impulse response, give it a duration");
+            }
+            ImpulseResponse::stereo(&render(&sound)).trim(t2n(start), length.map(t2n))
+        };
+        Box::new(Convolve::new(input, &impulse_response.fade_out(t2n(fade)), mix))
+    }
+}
@@ -1,7 +1,5 @@
-pub mod cos_transfer {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub struct CosTransfer {
@@ -27,7 +25,7 @@ impl SoundSource for CosTransfer {
         Box::new(CosTransferState { source_state: self.source.init_state() })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<CosTransferState>().unwrap();
         let mut val = self.source.next_value(n, &mut data.source_state);
         val.0 = (val.0 * 2.0 * std::f32::consts::PI).cos();
@@ -35,7 +33,7 @@ impl SoundSource for CosTransfer {
         val
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.source.duration()
     }
 
@@ -44,5 +42,3 @@ impl SoundSource for CosTransfer {
         Box::new(Self::new(source))
     }
 }
-
-}
@@ -1,16 +1,14 @@
-pub mod db2amp {
-
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-use crate::read_song::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
 
 #[derive(Clone)]
 pub struct Db2Amp {
     amp: f32,
-    duration: i32
+    duration: SampleTime
 }
 
 impl Db2Amp {
-    pub fn new(amp: f32, duration: i32) -> Self {
+    pub fn new(amp: f32, duration: SampleTime) -> Self {
         Db2Amp { amp: amp, duration: duration }
     }
 }
@@ -29,7 +27,7 @@ impl SoundSource for Db2Amp {
         Box::new(0)
     }
 
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (f32, f32) {
         if n < self.duration {
             (self.amp, self.amp)
         } else {
@@ -37,15 +35,13 @@ impl SoundSource for Db2Amp {
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let value = db2amp(params[0].parse::<f32>().unwrap());
-        let duration = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Self::new(value, duration.round() as i32))
+        let duration = reader.get_duration(&params[1]);
+        Box::new(Self::new(value, duration))
     }
 }
-
-}
@@ -1,18 +1,16 @@
-pub mod dc {
-
 use num::Complex;
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData, ComplexSoundSource};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, ComplexSoundSource, SampleTime};
 
 #[derive(Clone)]
 pub struct DC {
     value: f32,
-    duration: i32
+    duration: SampleTime
 }
 
 impl DC {
-    pub fn new(value: f32, duration: i32) -> Self {
+    pub fn new(value: f32, duration: SampleTime) -> Self {
         DC { value: value, duration: duration }
     }
 }
@@ -20,20 +18,20 @@ impl SoundSource for DC {
     fn init_state(&self) -> SoundData {
         Box::new(0)
     }
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (f32, f32) {
         if n > self.duration {
             (0.0, 0.0)
         } else {
             (self.value, self.value)
         }
     }
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let value = params[0].parse::<f32>().unwrap();
-        let duration = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Self::new(value, duration.round() as i32))
+        let duration = reader.get_duration(&params[1]);
+        Box::new(Self::new(value, duration))
     }
 
 }
@@ -41,11 +39,11 @@ impl SoundSource for DC {
 #[derive(Clone)]
 pub struct ComplexDC {
     value: Complex<f32>,
-    duration: i32
+    duration: SampleTime
 }
 
 impl ComplexDC {
-    pub fn new(value: Complex<f32>, duration: i32) -> Self {
+    pub fn new(value: Complex<f32>, duration: SampleTime) -> Self {
         ComplexDC { value: value, duration: duration }
     }
 }
@@ -53,16 +51,14 @@ impl ComplexSoundSource for ComplexDC {
     fn init_state(&self) -> SoundData {
         Box::new(0)
     }
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
         if n > self.duration {
             (Complex::new(0.0, 0.0), Complex::new(0.0, 0.0))
         } else {
             (self.value, self.value)
         }
     }
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 }
-
-}
\ No newline at end of file
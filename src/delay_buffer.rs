@@ -0,0 +1,45 @@
+// A ring buffer of past samples for effects that read their input back after
+// a delay. Samples are pushed in one at a time and read back from any number
+// of taps, at whole or fractional delays, so the voices of a chorus or the
+// lines of a reverb don't each need their own copy of the input.
+
+use crate::wavetable::cubic_interpolate;
+
+#[derive(Clone)]
+pub struct DelayBuffer {
+    buffer: Vec<f32>,
+    // Where the next sample goes
+    pos: usize,
+}
+
+impl DelayBuffer {
+    // Room for delays of up to max_delay samples
+    pub fn new(max_delay: usize) -> Self {
+        // Two more for the interpolation either side of the longest delay
+        DelayBuffer { buffer: vec![0.0; max_delay.max(1) + 2], pos: 0 }
+    }
+
+    pub fn max_delay(&self) -> usize {
+        self.buffer.len() - 2
+    }
+
+    pub fn push(&mut self, x: f32) {
+        self.buffer[self.pos] = x;
+        self.pos = (self.pos + 1) % self.buffer.len();
+    }
+
+    // The sample pushed delay samples ago, where 1 is the last one pushed
+    pub fn tap(&self, delay: usize) -> f32 {
+        let len = self.buffer.len();
+        self.buffer[(self.pos + len - delay.clamp(1, len)) % len]
+    }
+
+    // A delay in samples that needn't be whole, kept between 1 and max_delay.
+    // Read before pushing the next sample for the delay to be exact.
+    pub fn read(&self, delay: f32) -> f32 {
+        let delay = delay.clamp(1.0, self.max_delay() as f32);
+        let whole = delay.floor() as usize;
+        // Going back in time from y0 to y1
+        cubic_interpolate(delay - whole as f32, self.tap(whole.max(2) - 1), self.tap(whole), self.tap(whole + 1), self.tap(whole + 2))
+    }
+}
@@ -1,21 +1,22 @@
-pub mod delay_line {
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::knob::Knob;
 
-use crate::knob::knob::Knob;
-
-const MAX_DELAY: i32 = 48000 * 10;
+// The longest delay the YAML delay_line allows unless told otherwise, in
+// seconds
+const MAX_DELAY_TIME: f32 = 10.0;
 
 #[derive(Clone)]
 pub struct DelayLine {
     input: DynSoundSource,
     delay: Knob,
+    max_delay: SampleTime, // Delays longer than this many samples are silent
 }
 
 impl DelayLine {
-    pub fn new(input: DynSoundSource, delay: Knob) -> Self {
-        DelayLine { input: input, delay: delay}
+    pub fn new(input: DynSoundSource, delay: Knob, max_delay: SampleTime) -> Self {
+        DelayLine { input: input, delay: delay, max_delay: max_delay }
     }
 }
 
@@ -40,11 +41,11 @@ impl SoundSource for DelayLine {
         })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<DelayLineData>().unwrap();
         let d = self.delay.next_value(n, &mut data.delay_data);
-        let x0 = (n as f32 - d).floor() as i32 - 2;
-        if x0 >= 1 && d >= 0.0 && d.round() as i32 <= MAX_DELAY {
+        let x0 = (n as f64 - d as f64).floor() as SampleTime - 2;
+        if x0 >= 1 && d >= 0.0 && d.round() as SampleTime <= self.max_delay {
             // Cubic interpolation
             // We apply a base delay of 2 so we don't have to see into the future
             // when getting x0 + 2
@@ -67,14 +68,16 @@ impl SoundSource for DelayLine {
         }
     }
 
-    fn duration(&self) -> i32 {
-        self.input.duration() + MAX_DELAY
+    fn duration(&self) -> SampleTime {
+        self.input.duration() + self.max_delay
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
         let delay = reader.get_knob(&params[1], 1.0);
-        Box::new(DelayLine::new(input, delay))
+        // An optional third param is the longest delay in seconds
+        let max_delay = params.get(2).map(|max_delay| max_delay.parse::<f32>().unwrap()).unwrap_or(MAX_DELAY_TIME)
+            * reader.sample_rate as f32;
+        Box::new(DelayLine::new(input, delay, max_delay.round() as SampleTime))
     }
 }
-}
\ No newline at end of file
@@ -0,0 +1,83 @@
+// Pieces shared by the dynamics processors (Compressor, Limiter and Gate):
+// measuring how loud the input is and smoothing the gain so it changes at the
+// attack and release rates rather than jumping.
+//
+// Levels are in dB relative to full scale (1.0), not the dB SPL that db2amp
+// uses. Both sides are measured together so the stereo image doesn't move
+// when one side is louder.
+
+// How long the RMS level is averaged over
+const RMS_TIME: f32 = 0.01;
+
+// Quieter than anything that matters, to keep the log of silence finite
+const MIN_DB: f32 = -150.0;
+
+pub fn gain_to_db(gain: f32) -> f32 {
+    (20.0 * gain.abs().log10()).max(MIN_DB)
+}
+
+pub fn db_to_gain(db: f32) -> f32 {
+    10.0_f32.powf(db / 20.0)
+}
+
+// The coefficient for a one pole smoother that gets most (1 - 1/e) of the way
+// to a new value in time seconds
+pub fn time_coefficient(time: f32, sample_rate: i32) -> f32 {
+    if time <= 0.0 {
+        0.0
+    } else {
+        (-1.0 / (time * sample_rate as f32)).exp()
+    }
+}
+
+// Move state towards target by the coefficient
+pub fn smooth(state: &mut f32, target: f32, coefficient: f32) {
+    *state = target + (*state - target) * coefficient;
+}
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Detection {
+    // The louder side's level at each sample
+    Peak,
+    // The power of both sides averaged over RMS_TIME, for a level closer to
+    // how loud it sounds
+    Rms,
+}
+
+impl Detection {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "peak" => Detection::Peak,
+            "rms" => Detection::Rms,
+            _ => panic!("Detection must be peak or rms, not \"{}\"", param)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct LevelDetector {
+    detection: Detection,
+    coefficient: f32,
+    mean_square: f32,
+}
+
+impl LevelDetector {
+    pub fn new(detection: Detection, sample_rate: i32) -> Self {
+        LevelDetector {
+            detection: detection,
+            coefficient: time_coefficient(RMS_TIME, sample_rate),
+            mean_square: 0.0,
+        }
+    }
+
+    // The level in dB of the next sample
+    pub fn level(&mut self, x: (f32, f32)) -> f32 {
+        match self.detection {
+            Detection::Peak => gain_to_db(x.0.abs().max(x.1.abs())),
+            Detection::Rms => {
+                smooth(&mut self.mean_square, (x.0 * x.0 + x.1 * x.1) / 2.0, self.coefficient);
+                10.0 * self.mean_square.log10().max(MIN_DB / 10.0)
+            },
+        }
+    }
+}
@@ -1,73 +1,316 @@
-pub mod envelope {
+// A multi-segment envelope. Each point gives the time since the previous point
+// and the level to reach by then, and the segment leading up to it can be
+// straight or curved. Times and levels can be knobs; they are read once, at
+// the start of their segment, so a velocity knob can stretch an attack.
+//
+// The envelope keeps track of the segment it is in, so each sample costs the
+// same however many points there are. A range of segments can be looped, for
+// LFO-like shapes or a sustain that holds until a release time. When that time
+// is reached the loop is left straight away and the segments after it start
+// from wherever the level had got to.
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use std::f32::consts::PI;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime, FOREVER};
 
+use crate::knob::Knob;
+
+// How sharply the exponential and logarithmic curves bend. Every curve is
+// scaled to finish its change, but at 5 the raw exponential would be about 99%
+// of the way there by the end, so most of the change happens early.
+const CURVATURE: f32 = 5.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Curve {
+    Linear,
+    // Moves quickly at first then slows down as it gets close, like an
+    // analogue envelope charging or discharging a capacitor
+    Exponential,
+    // Starts slowly and speeds up towards the end
+    Logarithmic,
+    // Eases in and out
+    SCurve,
+    // Positive values bend like Exponential and negative like Logarithmic,
+    // 0 is straight
+    Curvature(f32),
+}
+
+impl Curve {
+    // linear, exp, log, s or a curvature number
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "linear" | "lin" => Curve::Linear,
+            "exponential" | "exp" => Curve::Exponential,
+            "logarithmic" | "log" => Curve::Logarithmic,
+            "s" | "s_curve" => Curve::SCurve,
+            _ => match param.parse::<f32>() {
+                Ok(curvature) => Curve::Curvature(curvature),
+                Err(_) => panic!("Envelope curve must be linear, exp, log, s or a curvature, not \"{}\"", param),
+            }
+        }
+    }
+
+    // How far through the change in level we are when x of the way through
+    // the segment
+    fn shape(&self, x: f32) -> f32 {
+        match self {
+            Curve::Linear => x,
+            Curve::Exponential => Curve::Curvature(CURVATURE).shape(x),
+            Curve::Logarithmic => Curve::Curvature(-CURVATURE).shape(x),
+            Curve::SCurve => 0.5 - 0.5 * (PI * x).cos(),
+            Curve::Curvature(c) => if c.abs() < 1e-3 {
+                x
+            } else {
+                (1.0 - (-c * x).exp()) / (1.0 - (-c).exp())
+            },
+        }
+    }
+}
+
+#[derive(Clone)]
+enum SegmentTime {
+    Fixed(SampleTime),
+    Knob(Knob), // In samples
+}
 
 #[derive(Clone)]
 pub struct EnvelopePoint {
-    time_offset: i32, // each time offset is relative to the previous point
-    value: f32
+    time_offset: SegmentTime, // each time offset is relative to the previous point
+    value: Knob,
+    curve: Curve,
 }
 
 impl EnvelopePoint {
-    pub fn new(time_offset: i32, value: f32) -> Self {
-        EnvelopePoint { time_offset: time_offset, value: value }
+    pub fn new(time_offset: SampleTime, value: f32) -> Self {
+        EnvelopePoint { time_offset: SegmentTime::Fixed(time_offset), value: Knob::dc(value), curve: Curve::Linear }
+    }
+
+    // The time knob is in samples
+    pub fn with_knobs(time_offset: Knob, value: Knob) -> Self {
+        EnvelopePoint { time_offset: SegmentTime::Knob(time_offset), value: value, curve: Curve::Linear }
+    }
+
+    // The shape of the segment leading up to this point
+    pub fn curve(mut self, curve: Curve) -> Self {
+        self.curve = curve;
+        self
+    }
+
+    fn init_state(&self) -> (SoundData, SoundData) {
+        let time_data = match &self.time_offset {
+            SegmentTime::Fixed(_) => Box::new(0),
+            SegmentTime::Knob(knob) => knob.init_state(),
+        };
+        (time_data, self.value.init_state())
+    }
+
+    fn time_at(&self, n: SampleTime, state: &mut SoundData) -> SampleTime {
+        match &self.time_offset {
+            SegmentTime::Fixed(time_offset) => *time_offset,
+            SegmentTime::Knob(knob) => (knob.next_value(n, state).round() as SampleTime).max(0),
+        }
+    }
+
+    // The time as it would be read at the start, for working out the duration
+    fn initial_time(&self) -> SampleTime {
+        let (mut time_data, _) = self.init_state();
+        self.time_at(0, &mut time_data)
     }
 }
 
 #[derive(Clone)]
 pub struct Envelope {
-    points: Vec::<EnvelopePoint>
+    points: Vec::<EnvelopePoint>,
+    // First and last point of the looped segments
+    loop_points: Option<(usize, usize)>,
+    // When to leave the loop. Without one a looping envelope goes on for ever.
+    release_time: Option<SampleTime>,
 }
 
 impl Envelope {
     pub fn new(points: Vec::<EnvelopePoint>) -> Self {
-        Envelope { points: points }
+        Envelope { points: points, loop_points: None, release_time: None }
+    }
+
+    // Repeat the segments leading up to points loop_start to loop_end
+    // (counting from 0) until the release time
+    pub fn with_loop(mut self, loop_start: usize, loop_end: usize, release_time: Option<SampleTime>) -> Self {
+        if loop_start > loop_end || loop_end >= self.points.len() {
+            panic!("Envelope loop {} to {} doesn't fit {} points", loop_start, loop_end, self.points.len());
+        }
+        self.loop_points = Some((loop_start, loop_end));
+        self.release_time = release_time;
+        self
+    }
+
+    // Rise to `level` over the attack, fall to `level * sustain` over the
+    // decay and hold there until the note ends after `duration` samples, then
+    // fall to zero over the release. Times are in samples.
+    pub fn adsr(level: f32, attack: SampleTime, decay: SampleTime, sustain: f32, release: SampleTime, duration: SampleTime) -> Self {
+        let mut points = Vec::<EnvelopePoint>::new();
+        points.push(EnvelopePoint::new(attack, level));
+        points.push(EnvelopePoint::new(decay, level * sustain));
+        points.push(EnvelopePoint::new((duration - attack - decay).max(0), level * sustain));
+        points.push(EnvelopePoint::new(release, 0.0));
+        Envelope::new(points)
+    }
+
+    // An ADSR whose times (in samples) and levels are knobs, with the decay
+    // and release following `curve`. The sustain holds until the note ends
+    // after `duration` samples and the release starts from wherever the
+    // envelope has got to, even if that is part way through the attack.
+    pub fn adsr_knobs(attack: Knob, level: Knob, decay: Knob, sustain_level: Knob, release: Knob, duration: SampleTime, curve: Curve) -> Self {
+        let mut points = Vec::<EnvelopePoint>::new();
+        points.push(EnvelopePoint::with_knobs(attack, level));
+        points.push(EnvelopePoint::with_knobs(decay, sustain_level.clone()).curve(curve));
+        points.push(EnvelopePoint::with_knobs(Knob::dc(1.0), sustain_level));
+        points.push(EnvelopePoint::with_knobs(release, Knob::dc(0.0)).curve(curve));
+        Envelope::new(points).with_loop(2, 2, Some(duration))
+    }
+
+    // Which segment follows this one, taking the loop into account
+    fn next_segment(&self, segment: usize, released: bool) -> usize {
+        match self.loop_points {
+            Some((loop_start, loop_end)) if segment == loop_end && !released => loop_start,
+            _ => segment + 1,
+        }
+    }
+
+    // Read the time and level of a segment and start it at sample n
+    fn enter_segment(&self, segment: usize, n: SampleTime, data: &mut EnvelopeData) {
+        data.segment = segment;
+        data.segment_start = n;
+        data.start_level = data.level;
+        if let Some(point) = self.points.get(segment) {
+            let (time_data, value_data) = &mut data.point_data[segment];
+            data.segment_length = point.time_at(n, time_data);
+            if let Some((loop_start, loop_end)) = self.loop_points {
+                // Looped segments can't be empty or the loop would never
+                // get anywhere
+                if !data.released && segment >= loop_start && segment <= loop_end {
+                    data.segment_length = data.segment_length.max(1);
+                }
+            }
+            data.end_level = point.value.next_value(n, value_data);
+        }
+    }
+
+    fn start(&self) -> EnvelopeData {
+        let mut data = EnvelopeData {
+            point_data: self.points.iter().map(|point| point.init_state()).collect(),
+            segment: 0,
+            segment_start: 0,
+            segment_length: 0,
+            start_level: 0.0,
+            end_level: 0.0,
+            level: 0.0,
+            released: false,
+        };
+        self.enter_segment(0, 0, &mut data);
+        data
+    }
+
+    // Move on past any segments that have finished by sample n, including
+    // ones with no length
+    fn advance(&self, n: SampleTime, data: &mut EnvelopeData) {
+        while data.segment < self.points.len() && n >= data.segment_start + data.segment_length {
+            data.level = data.end_level;
+            let next = self.next_segment(data.segment, data.released);
+            let start = data.segment_start + data.segment_length;
+            self.enter_segment(next, start, data);
+        }
+    }
+
+    fn level_at(&self, n: SampleTime, data: &EnvelopeData) -> f32 {
+        if data.segment < self.points.len() && n < data.segment_start + data.segment_length {
+            let x = (n - data.segment_start) as f32 / data.segment_length as f32;
+            data.start_level + self.points[data.segment].curve.shape(x) * (data.end_level - data.start_level)
+        } else {
+            data.level
+        }
     }
 }
+
+struct EnvelopeData {
+    point_data: Vec<(SoundData, SoundData)>,
+    segment: usize,
+    segment_start: SampleTime,
+    segment_length: SampleTime,
+    start_level: f32,
+    end_level: f32,
+    // The level at the end of the last finished segment
+    level: f32,
+    released: bool,
+}
+
 impl SoundSource for Envelope {
     fn init_state(&self) -> SoundData {
-        Box::new(0)
-    }
-
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (f32, f32) {
-        let mut point_start_time = 0;
-        let mut output = 0.0;
-        for point in &self.points {
-            if n == 0 && point.time_offset == 0 {
-                output = point.value;
-                break;
-            } else if n < point_start_time + point.time_offset {
-                output = (n - point_start_time) as f32 / point.time_offset as f32 * (point.value - output) + output;
-                break;
-            } else {
-                point_start_time += point.time_offset;
-                output = point.value;
+        Box::new(self.start())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<EnvelopeData>().unwrap();
+        if n < data.segment_start {
+            // Sequences replay a note with the same state, so going back in
+            // time means starting again
+            *data = self.start();
+        }
+        self.advance(n, data);
+        if let (Some((_, loop_end)), Some(release_time)) = (self.loop_points, self.release_time) {
+            if !data.released && n >= release_time {
+                data.released = true;
+                if data.segment <= loop_end {
+                    // Leave the loop from the current level
+                    data.level = self.level_at(n, data);
+                    self.enter_segment(loop_end + 1, n, data);
+                    self.advance(n, data);
+                }
             }
         }
+        let output = self.level_at(n, data);
         (output, output)
     }
 
-    fn duration(&self) -> i32 {
-        let mut res = 0;
-        for point in &self.points {
-            res += point.time_offset;
+    fn duration(&self) -> SampleTime {
+        match self.loop_points {
+            Some((_, loop_end)) => match self.release_time {
+                Some(release_time) => self.points[loop_end + 1..].iter()
+                    .fold(release_time, |res, point| res.saturating_add(point.initial_time())),
+                None => FOREVER,
+            },
+            None => self.points.iter().fold(0, |res: SampleTime, point| res.saturating_add(point.initial_time())),
         }
-        res
     }
 
+    // Each param is "time_offset value [curve]" where the time is in seconds
+    // since the previous point and the curve is linear, exp, log, s or a
+    // curvature. Times and values can also be sound names. A param
+    // "loop first last [release_time]" loops the segments leading up to
+    // points first to last (counting from 0) until the release time.
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let mut points = Vec::<EnvelopePoint>::new();
+        let mut loop_points = None;
+        let mut release_time = None;
         for param in params {
             println!("Envelope::from_yaml param {}", &param);
-            let parts: Vec<_> = param.split(" ").collect();
-            let time_offset = parts[0].parse::<f32>().unwrap() * reader.sample_rate as f32;
-            let value = parts[1].parse::<f32>().unwrap();
-            points.push(EnvelopePoint::new(time_offset.round() as i32, value));
+            let parts: Vec<_> = param.split_whitespace().collect();
+            if parts[0] == "loop" {
+                loop_points = Some((parts[1].parse::<usize>().unwrap(), parts[2].parse::<usize>().unwrap()));
+                release_time = parts.get(3).map(|release_time| reader.get_duration(release_time));
+                continue;
+            }
+            let time_offset = match parts[0].parse::<f64>() {
+                Ok(time_offset) => SegmentTime::Fixed((time_offset * reader.sample_rate as f64).round() as SampleTime),
+                Err(_) => SegmentTime::Knob(reader.get_knob(parts[0], reader.sample_rate as f32)),
+            };
+            let value = reader.get_value_knob(parts[1], 1.0);
+            let curve = parts.get(2).map(|curve| Curve::from_param(curve)).unwrap_or(Curve::Linear);
+            points.push(EnvelopePoint { time_offset: time_offset, value: value, curve: curve });
+        }
+        let mut envelope = Self::new(points);
+        if let Some((loop_start, loop_end)) = loop_points {
+            envelope = envelope.with_loop(loop_start, loop_end, release_time);
         }
-        Box::new(Self::new(points))
+        Box::new(envelope)
     }
 }
-
-}
\ No newline at end of file
@@ -1,9 +1,7 @@
-pub mod export_wav {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-use crate::pre_render::pre_render::PreRender;
-use hound;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::pre_render::PreRender;
+use crate::render::write_wav;
 
 #[derive(Clone)]
 pub struct ExportWav {
@@ -14,19 +12,7 @@ impl ExportWav {
     pub fn new(filename: &str, sample_rate: i32, source: DynSoundSource) -> Self {
         println!("Writing file {}, {}s...", filename, source.duration() as f32 / sample_rate as f32);
         let pre_render = PreRender::new(source);
-        let spec = hound::WavSpec {
-            channels: 2,
-            sample_rate: sample_rate.try_into().unwrap(),
-            bits_per_sample: 32,
-            sample_format: hound::SampleFormat::Float,
-        };
-        let mut writer = hound::WavWriter::create(filename, spec).unwrap();
-        let mut pre_render_data = pre_render.init_state();
-        for n in 0..pre_render.duration() {
-            let val = pre_render.next_value(n, &mut pre_render_data);
-            writer.write_sample(val.0).unwrap();
-            writer.write_sample(val.1).unwrap();
-        }
+        write_wav(filename, sample_rate, pre_render.samples()).unwrap();
         println!("done");
         ExportWav { buffer: pre_render }
     }
@@ -41,12 +27,12 @@ impl SoundSource for ExportWav {
         Box::new(ExportWavData{buffer_data: self.buffer.init_state()})
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<ExportWavData>().unwrap();
         self.buffer.next_value(n, &mut data.buffer_data)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.buffer.duration()
     }
 
@@ -56,5 +42,3 @@ impl SoundSource for ExportWav {
         Box::new(Self::new(filename, reader.sample_rate, source))
     }
 }
-
-}
\ No newline at end of file
@@ -0,0 +1,268 @@
+// A reverb built from a feedback delay network: eight delay lines whose
+// outputs are mixed by a Hadamard matrix and fed back into each other. The
+// matrix is orthogonal, like the rotations in RotationTransfer (it is made of
+// 45 degree rotations), so the feedback neither gains nor loses energy and the
+// decay is set entirely by the gain on each line. Each line's gain is worked
+// out from its length so that every line dies away by 60 dB in the RT60 time,
+// and a low pass filter in each loop makes high frequencies die away faster.
+//
+// Before the network the input goes through a pre-delay and a chain of
+// allpass filters that smear each sound into a dense cloud (the diffusion).
+// The left input feeds the even lines and the right input the odd ones, and
+// the outputs are taken the same way so the tail comes out in stereo.
+//
+// The settings are fixed for the life of the reverb because the length of the
+// tail depends on them.
+
+use std::f32::consts::FRAC_1_SQRT_2;
+
+use crate::delay_buffer::DelayBuffer;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+const NUM_LINES: usize = 8;
+
+// Delay line lengths in seconds for a room size of 0.5. They're spread
+// unevenly so their echoes don't pile up at the same times.
+const LINE_DELAYS: [f32; NUM_LINES] = [0.0297, 0.0371, 0.0411, 0.0437, 0.0533, 0.0599, 0.0677, 0.0793];
+
+// Allpass lengths in seconds for the diffusion, from Jon Dattorro's plate
+// reverb
+const DIFFUSER_DELAYS: [f32; 4] = [0.00477, 0.00359, 0.01273, 0.00930];
+
+// The allpass coefficient at full diffusion
+const MAX_DIFFUSION: f32 = 0.7;
+
+// A Schroeder allpass filter: flat frequency response but the sound is spread
+// out in time
+#[derive(Clone)]
+struct Diffuser {
+    delay: DelayBuffer,
+    length: usize,
+}
+
+impl Diffuser {
+    fn process(&mut self, x: f32, coefficient: f32) -> f32 {
+        let delayed = self.delay.tap(self.length);
+        let v = x + coefficient * delayed;
+        self.delay.push(v);
+        delayed - coefficient * v
+    }
+}
+
+// Mix the lines with an 8x8 Hadamard matrix scaled to be orthogonal. Each
+// butterfly is a 45 degree rotation (with one output flipped).
+fn hadamard(x: &mut [f32; NUM_LINES]) {
+    let mut size = 1;
+    while size < NUM_LINES {
+        for start in (0..NUM_LINES).step_by(2 * size) {
+            for i in start..start + size {
+                let (a, b) = (x[i], x[i + size]);
+                x[i] = (a + b) * FRAC_1_SQRT_2;
+                x[i + size] = (a - b) * FRAC_1_SQRT_2;
+            }
+        }
+        size *= 2;
+    }
+}
+
+#[derive(Clone)]
+pub struct FdnReverb {
+    input: DynSoundSource,
+    sample_rate: i32,
+    room_size: f32,
+    rt60: f32, // Seconds
+    damping: f32,
+    pre_delay: f32, // Seconds
+    diffusion: f32,
+    width: f32,
+    mix: f32,
+}
+
+impl FdnReverb {
+    // A medium room with a two second tail, some damping and diffusion, full
+    // width and an even mix of wet and dry
+    pub fn new(input: DynSoundSource, sample_rate: i32) -> Self {
+        FdnReverb {
+            input: input,
+            sample_rate: sample_rate,
+            room_size: 0.5,
+            rt60: 2.0,
+            damping: 0.3,
+            pre_delay: 0.02,
+            diffusion: 0.7,
+            width: 1.0,
+            mix: 0.5,
+        }
+    }
+
+    // From 0 to 1, scaling the delay line lengths from a quarter to four times
+    // their length at 0.5
+    pub fn room_size(mut self, room_size: f32) -> Self {
+        self.room_size = room_size.clamp(0.0, 1.0);
+        self
+    }
+
+    // The time in seconds for the tail to die away by 60 dB
+    pub fn rt60(mut self, rt60: f32) -> Self {
+        self.rt60 = rt60.max(0.01);
+        self
+    }
+
+    // From 0 (high frequencies last as long as low ones) to 1 (dull)
+    pub fn damping(mut self, damping: f32) -> Self {
+        self.damping = damping.clamp(0.0, 0.99);
+        self
+    }
+
+    // Seconds before the reverb starts
+    pub fn pre_delay(mut self, pre_delay: f32) -> Self {
+        self.pre_delay = pre_delay.max(0.0);
+        self
+    }
+
+    // From 0 (separate echoes at the start) to 1 (smooth from the start)
+    pub fn diffusion(mut self, diffusion: f32) -> Self {
+        self.diffusion = diffusion.clamp(0.0, 1.0);
+        self
+    }
+
+    // From 0 (mono) to 1 (as wide as the network makes it)
+    pub fn width(mut self, width: f32) -> Self {
+        self.width = width.clamp(0.0, 1.0);
+        self
+    }
+
+    // From 0 (dry) to 1 (only reverb)
+    pub fn mix(mut self, mix: f32) -> Self {
+        self.mix = mix.clamp(0.0, 1.0);
+        self
+    }
+
+    fn t2n(&self, t: f32) -> usize {
+        (t * self.sample_rate as f32).round() as usize
+    }
+
+    fn line_delays(&self) -> [usize; NUM_LINES] {
+        // 0.25 at size 0, 1 at 0.5 and 4 at 1
+        let scale = 4.0_f32.powf(2.0 * self.room_size - 1.0);
+        LINE_DELAYS.map(|delay| self.t2n(delay * scale).max(1))
+    }
+
+    fn tail(&self) -> SampleTime {
+        let longest = self.line_delays().into_iter().max().unwrap();
+        (self.t2n(self.pre_delay + self.rt60) + longest) as SampleTime
+    }
+
+    fn new_data(&self) -> FdnReverbData {
+        // Without a pre-delay there's no buffer, as the shortest a buffer can
+        // delay by is one sample
+        let pre_delay = match self.t2n(self.pre_delay) {
+            0 => None,
+            length => Some([DelayBuffer::new(length), DelayBuffer::new(length)]),
+        };
+        let diffusers: Vec<_> = DIFFUSER_DELAYS.iter()
+            .map(|delay| {
+                let length = self.t2n(*delay);
+                Diffuser { delay: DelayBuffer::new(length), length: length }
+            })
+            .collect();
+        let line_delays = self.line_delays();
+        // -60 dB over rt60 seconds is a gain of 10^(-3 / rt60) per second
+        let gains = line_delays.map(|delay| 10.0_f32.powf(-3.0 * delay as f32 / (self.rt60 * self.sample_rate as f32)));
+        FdnReverbData {
+            input_data: self.input.init_state(),
+            next_n: 0,
+            pre_delay: pre_delay,
+            // The right channel's diffusers go in the opposite order so the
+            // two sides smear differently
+            diffusers: [diffusers.clone(), diffusers.into_iter().rev().collect()],
+            lines: line_delays.iter().map(|delay| DelayBuffer::new(*delay)).collect(),
+            line_delays: line_delays,
+            gains: gains,
+            damping: [0.0; NUM_LINES],
+        }
+    }
+}
+
+struct FdnReverbData {
+    input_data: SoundData,
+    next_n: SampleTime,
+    pre_delay: Option<[DelayBuffer; 2]>,
+    diffusers: [Vec<Diffuser>; 2],
+    lines: Vec<DelayBuffer>,
+    line_delays: [usize; NUM_LINES],
+    // Each line's feedback gain and the state of its damping filter
+    gains: [f32; NUM_LINES],
+    damping: [f32; NUM_LINES],
+}
+
+impl SoundSource for FdnReverb {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<FdnReverbData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start, so forget the old tail
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let dry = self.input.next_value(n, &mut data.input_data);
+        let coefficient = self.diffusion * MAX_DIFFUSION;
+        let mut diffused = [dry.0, dry.1];
+        for (ch, x) in diffused.iter_mut().enumerate() {
+            let pre_delayed = match &mut data.pre_delay {
+                Some(pre_delay) => {
+                    let pre_delayed = pre_delay[ch].tap(self.t2n(self.pre_delay));
+                    pre_delay[ch].push(*x);
+                    pre_delayed
+                },
+                None => *x,
+            };
+            *x = data.diffusers[ch].iter_mut().fold(pre_delayed, |x, diffuser| diffuser.process(x, coefficient));
+        }
+
+        let outputs: [f32; NUM_LINES] = std::array::from_fn(|i| data.lines[i].tap(data.line_delays[i]));
+        let mut feedback = [0.0; NUM_LINES];
+        for i in 0..NUM_LINES {
+            data.damping[i] = outputs[i] * (1.0 - self.damping) + data.damping[i] * self.damping;
+            feedback[i] = data.damping[i] * data.gains[i];
+        }
+        hadamard(&mut feedback);
+        for (i, line) in data.lines.iter_mut().enumerate() {
+            line.push(feedback[i] + diffused[i % 2]);
+        }
+
+        // Half the lines go to each side, scaled so that together they are as
+        // loud as one line
+        let scale = 1.0 / (NUM_LINES as f32 / 2.0).sqrt();
+        let left = outputs.iter().step_by(2).sum::<f32>() * scale;
+        let right = outputs.iter().skip(1).step_by(2).sum::<f32>() * scale;
+        let wet_1 = (1.0 + self.width) / 2.0;
+        let wet_2 = (1.0 - self.width) / 2.0;
+        let wet = (left * wet_1 + right * wet_2, right * wet_1 + left * wet_2);
+        (dry.0 * (1.0 - self.mix) + wet.0 * self.mix, dry.1 * (1.0 - self.mix) + wet.1 * self.mix)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(self.tail())
+    }
+
+    // params: input, room size, RT60 in seconds, damping, pre-delay in
+    // seconds, diffusion, width, mix
+    // Room size, damping, diffusion, width and mix run from 0 to 1.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let param = |i: usize| params[i].parse::<f32>().unwrap();
+        Box::new(FdnReverb::new(input, reader.sample_rate)
+            .room_size(param(1))
+            .rt60(param(2))
+            .damping(param(3))
+            .pre_delay(param(4))
+            .diffusion(param(5))
+            .width(param(6))
+            .mix(param(7)))
+    }
+}
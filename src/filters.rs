@@ -1,11 +1,15 @@
 pub mod allpass_filter;
 pub mod band_pass_filter;
+pub mod biquad_filter;
 pub mod butterworth_bandpass_filter;
 pub mod butterworth_filter;
 pub mod elementary_non_recirculating_filter;
 pub mod elementary_non_recirculating_filter_2nd_form;
 pub mod elementary_recirculating_filter;
+pub mod equaliser;
 pub mod high_pass_filter;
+pub mod ladder_filter;
 pub mod low_pass_filter;
 pub mod pole_zero_filter;
-pub mod real_to_complex;
\ No newline at end of file
+pub mod real_to_complex;
+pub mod state_variable_filter;
\ No newline at end of file
@@ -1,10 +1,8 @@
-pub mod allpass_filter {
-
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-use crate::read_song::read_song::SongReader;
-use crate::knob::knob::ComplexKnob;
-use crate::dc::dc::DC;
-use crate::filters::pole_zero_filter::pole_zero_filter::PoleZeroFilter;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::knob::ComplexKnob;
+use crate::dc::DC;
+use crate::filters::pole_zero_filter::PoleZeroFilter;
 
 #[derive(Clone)]
 pub struct AllpassFilter {
@@ -39,12 +37,12 @@ impl SoundSource for AllpassFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<AllpassFilterData>().unwrap();
         self.filter.next_value(n, &mut data.filter_data)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.filter.duration()
     }
 
@@ -54,5 +52,3 @@ impl SoundSource for AllpassFilter {
         Box::new(AllpassFilter::new(input, gain))
     }
 }
-
-}
\ No newline at end of file
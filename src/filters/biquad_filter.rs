@@ -0,0 +1,194 @@
+// Two pole, two zero filters with the coefficients from Robert
+// Bristow-Johnson's Audio EQ Cookbook. One structure covers low pass, high
+// pass, band pass, notch and all pass filters plus the peaking and shelving
+// bands used in equalisers, each set by a frequency in Hz, a Q and (for the
+// peaking and shelving types) a gain in dB.
+//
+// The settings are knobs. The coefficients are only worked out again when one
+// of them changes, so fixed filters cost no more than a handful of multiplies.
+// The filter runs in direct form I, which copes best with its coefficients
+// changing under it.
+
+use std::f64::consts::PI;
+
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::knob::Knob;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum BiquadType {
+    LowPass,
+    HighPass,
+    // 0 dB at the centre frequency
+    BandPass,
+    Notch,
+    AllPass,
+    Peaking,
+    LowShelf,
+    HighShelf,
+}
+
+impl BiquadType {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "lowpass" | "lp" => BiquadType::LowPass,
+            "highpass" | "hp" => BiquadType::HighPass,
+            "bandpass" | "bp" => BiquadType::BandPass,
+            "notch" => BiquadType::Notch,
+            "allpass" | "ap" => BiquadType::AllPass,
+            "peaking" | "peak" => BiquadType::Peaking,
+            "lowshelf" => BiquadType::LowShelf,
+            "highshelf" => BiquadType::HighShelf,
+            _ => panic!("Biquad type must be lowpass, highpass, bandpass, notch, allpass, peaking, lowshelf or highshelf, not \"{}\"", param)
+        }
+    }
+
+    // b0, b1, b2, a1, a2 normalised so that a0 is 1
+    pub fn coefficients(&self, freq: f64, q: f64, gain_db: f64, sample_rate: i32) -> [f64; 5] {
+        let w0 = 2.0 * PI * freq.clamp(1.0, sample_rate as f64 * 0.49) / sample_rate as f64;
+        let (sin, cos) = w0.sin_cos();
+        let alpha = sin / (2.0 * q.max(1e-3));
+        let a = 10.0_f64.powf(gain_db / 40.0);
+        let shelf = 2.0 * a.sqrt() * alpha;
+        let (b0, b1, b2, a0, a1, a2) = match self {
+            BiquadType::LowPass => ((1.0 - cos) / 2.0, 1.0 - cos, (1.0 - cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadType::HighPass => ((1.0 + cos) / 2.0, -(1.0 + cos), (1.0 + cos) / 2.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadType::BandPass => (alpha, 0.0, -alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadType::Notch => (1.0, -2.0 * cos, 1.0, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadType::AllPass => (1.0 - alpha, -2.0 * cos, 1.0 + alpha, 1.0 + alpha, -2.0 * cos, 1.0 - alpha),
+            BiquadType::Peaking => (1.0 + alpha * a, -2.0 * cos, 1.0 - alpha * a, 1.0 + alpha / a, -2.0 * cos, 1.0 - alpha / a),
+            BiquadType::LowShelf => (
+                a * ((a + 1.0) - (a - 1.0) * cos + shelf),
+                2.0 * a * ((a - 1.0) - (a + 1.0) * cos),
+                a * ((a + 1.0) - (a - 1.0) * cos - shelf),
+                (a + 1.0) + (a - 1.0) * cos + shelf,
+                -2.0 * ((a - 1.0) + (a + 1.0) * cos),
+                (a + 1.0) + (a - 1.0) * cos - shelf,
+            ),
+            BiquadType::HighShelf => (
+                a * ((a + 1.0) + (a - 1.0) * cos + shelf),
+                -2.0 * a * ((a - 1.0) + (a + 1.0) * cos),
+                a * ((a + 1.0) + (a - 1.0) * cos - shelf),
+                (a + 1.0) - (a - 1.0) * cos + shelf,
+                2.0 * ((a - 1.0) - (a + 1.0) * cos),
+                (a + 1.0) - (a - 1.0) * cos - shelf,
+            ),
+        };
+        [b0 / a0, b1 / a0, b2 / a0, a1 / a0, a2 / a0]
+    }
+}
+
+// One filter's settings, for use on its own or as a band of an equaliser
+#[derive(Clone)]
+pub struct BiquadBand {
+    filter_type: BiquadType,
+    freq: Knob, // Hz
+    q: Knob,
+    gain: Knob, // dB
+    sample_rate: i32,
+}
+
+pub struct BiquadBandData {
+    freq_data: SoundData,
+    q_data: SoundData,
+    gain_data: SoundData,
+    settings: Option<(f32, f32, f32)>,
+    coefficients: [f64; 5],
+    // x[n-1], x[n-2], y[n-1], y[n-2] for each channel
+    history: [[f64; 4]; 2],
+}
+
+impl BiquadBand {
+    pub fn new(filter_type: BiquadType, freq: Knob, q: Knob, gain: Knob, sample_rate: i32) -> Self {
+        BiquadBand { filter_type: filter_type, freq: freq, q: q, gain: gain, sample_rate: sample_rate }
+    }
+
+    // "type freq q [gain]" with the frequency in Hz and the gain in dB. Each
+    // setting can also be a sound name.
+    pub fn from_param(param: &str, reader: &mut SongReader) -> Self {
+        let parts: Vec<_> = param.split_whitespace().collect();
+        if parts.len() < 3 {
+            panic!("Filter band should be \"type freq q [gain]\", not \"{}\"", param);
+        }
+        let filter_type = BiquadType::from_param(parts[0]);
+        let freq = reader.get_value_knob(parts[1], 1.0);
+        let q = reader.get_value_knob(parts[2], 1.0);
+        let gain = reader.get_value_knob(parts.get(3).unwrap_or(&"0.0"), 1.0);
+        BiquadBand::new(filter_type, freq, q, gain, reader.sample_rate)
+    }
+
+    pub fn init_state(&self) -> BiquadBandData {
+        BiquadBandData {
+            freq_data: self.freq.init_state(),
+            q_data: self.q.init_state(),
+            gain_data: self.gain.init_state(),
+            settings: None,
+            coefficients: [0.0; 5],
+            history: [[0.0; 4]; 2],
+        }
+    }
+
+    pub fn process(&self, n: SampleTime, input: (f32, f32), data: &mut BiquadBandData) -> (f32, f32) {
+        let settings = (
+            self.freq.next_value(n, &mut data.freq_data),
+            self.q.next_value(n, &mut data.q_data),
+            self.gain.next_value(n, &mut data.gain_data),
+        );
+        if data.settings != Some(settings) {
+            data.coefficients = self.filter_type.coefficients(settings.0 as f64, settings.1 as f64, settings.2 as f64, self.sample_rate);
+            data.settings = Some(settings);
+        }
+        let [b0, b1, b2, a1, a2] = data.coefficients;
+        let mut output = [0.0; 2];
+        for (ch, x) in [input.0 as f64, input.1 as f64].into_iter().enumerate() {
+            let [x1, x2, y1, y2] = data.history[ch];
+            let y = b0 * x + b1 * x1 + b2 * x2 - a1 * y1 - a2 * y2;
+            data.history[ch] = [x, x1, y, y1];
+            output[ch] = y as f32;
+        }
+        (output[0], output[1])
+    }
+}
+
+#[derive(Clone)]
+pub struct BiquadFilter {
+    input: DynSoundSource,
+    band: BiquadBand,
+}
+
+impl BiquadFilter {
+    pub fn new(input: DynSoundSource, band: BiquadBand) -> Self {
+        BiquadFilter { input: input, band: band }
+    }
+}
+
+struct BiquadFilterData {
+    input_data: SoundData,
+    band_data: BiquadBandData,
+}
+
+impl SoundSource for BiquadFilter {
+    fn init_state(&self) -> SoundData {
+        Box::new(BiquadFilterData {
+            input_data: self.input.init_state(),
+            band_data: self.band.init_state(),
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<BiquadFilterData>().unwrap();
+        let input = self.input.next_value(n, &mut data.input_data);
+        self.band.process(n, input, &mut data.band_data)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration()
+    }
+
+    // params: input, type, frequency in Hz, Q, [gain in dB]
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let band = BiquadBand::from_param(&params[1..].join(" "), reader);
+        Box::new(BiquadFilter::new(input, band))
+    }
+}
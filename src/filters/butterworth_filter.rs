@@ -1,12 +1,11 @@
-pub mod butterworth_filter {
-
 use num::complex::Complex;
 
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-use crate::read_song::read_song::SongReader;
-use crate::knob::knob::ComplexKnob;
-use crate::dc::dc::DC;
-use crate::filters::pole_zero_filter::pole_zero_filter::PoleZeroFilter;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::frequency_response::TransferFunction;
+use crate::knob::ComplexKnob;
+use crate::dc::DC;
+use crate::filters::pole_zero_filter::PoleZeroFilter;
 
 #[derive(Clone)]
 pub struct ButterworthFilter {
@@ -44,11 +43,11 @@ impl ButterworthFilter {
         // Create dc knobs
         let mut pole_knobs = Vec::<ComplexKnob>::new();
         for pole in &poles {
-            pole_knobs.push(ComplexKnob::dc(pole.clone()));
+            pole_knobs.push(ComplexKnob::dc(*pole));
         }
         let mut zero_knobs = Vec::<ComplexKnob>::new();
         for zero in &zeros {
-            zero_knobs.push(ComplexKnob::dc(zero.clone()));
+            zero_knobs.push(ComplexKnob::dc(*zero));
         }
         let zero2_knobs = Vec::<ComplexKnob>::new();
         let duration = input.duration();
@@ -72,15 +71,19 @@ impl SoundSource for ButterworthFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<ButterworthFilterData>().unwrap();
         self.filter.next_value(n, &mut data.filter_data)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.filter.duration()
     }
 
+    fn transfer_function(&self) -> Option<TransferFunction> {
+        self.filter.transfer_function()
+    }
+
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
         let normalize = params[1].parse::<f32>().unwrap();
@@ -90,5 +93,3 @@ impl SoundSource for ButterworthFilter {
         Box::new(ButterworthFilter::new(input, num_points, pole_r, zero_r, normalize))
     }
 }
-
-}
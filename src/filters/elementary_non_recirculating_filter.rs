@@ -1,13 +1,12 @@
-pub mod elementary_non_recirculating_filter {
-
 use num::complex::Complex;
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData,
-    ComplexSoundSource, DynComplexSoundSource};
-use crate::dc::dc::DC;
-use crate::knob::knob::ComplexKnob;
-use crate::filters::real_to_complex::real_to_complex::RealToComplex;
+use crate::read_song::SongReader;
+use crate::frequency_response::TransferFunction;
+use crate::traits::{SoundSource, DynSoundSource, SoundData,
+    ComplexSoundSource, DynComplexSoundSource, SampleTime};
+use crate::dc::DC;
+use crate::knob::ComplexKnob;
+use crate::filters::real_to_complex::RealToComplex;
 
 
 #[derive(Clone)]
@@ -42,7 +41,7 @@ impl ComplexSoundSource for ComplexElementaryNonRecirculatingFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
         let data = &mut state.downcast_mut::<ComplexElementaryNonRecirculatingFilterData>().unwrap();
         let input_value = self.input.next_value(n, &mut data.input_data);
         if n > 0 {
@@ -56,7 +55,7 @@ impl ComplexSoundSource for ComplexElementaryNonRecirculatingFilter {
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.input.duration()
     }
 }
@@ -64,14 +63,18 @@ impl ComplexSoundSource for ComplexElementaryNonRecirculatingFilter {
 
 #[derive(Clone)]
 pub struct ElementaryNonRecirculatingFilter {
+    // Kept to describe the filter's response
+    input_transfer_function: Option<TransferFunction>,
     complex_filter: ComplexElementaryNonRecirculatingFilter,
 }
 
 impl ElementaryNonRecirculatingFilter {
     pub fn new(input: DynSoundSource, complex_gain: ComplexKnob) -> Self {
         let duration = input.duration();
+        let input_transfer_function = input.transfer_function();
         let complex_input = Box::new(RealToComplex::new(input, Box::new(DC::new(0.0, duration))));
         ElementaryNonRecirculatingFilter {
+            input_transfer_function: input_transfer_function,
             complex_filter: ComplexElementaryNonRecirculatingFilter::new(complex_input, complex_gain)
         }
     }
@@ -90,21 +93,25 @@ impl SoundSource for ElementaryNonRecirculatingFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<ElementaryNonRecirculatingFilterData>().unwrap();
         let output = self.complex_filter.next_value(n, &mut data.complex_filter_data);
         (output.0.re, output.1.re)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.complex_filter.duration()
     }
 
+    // With the gain at its starting setting
+    fn transfer_function(&self) -> Option<TransferFunction> {
+        let gain = self.complex_filter.gain.initial_value();
+        TransferFunction::new(1.0, vec![], vec![gain], vec![]).after(self.input_transfer_function.clone())
+    }
+
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
         let complex_gain = reader.get_complex_knob(&params[1]);
         Box::new(ElementaryNonRecirculatingFilter::new(input, complex_gain))
     }
 }
-
-}
\ No newline at end of file
@@ -1,13 +1,12 @@
-pub mod elementary_recirculating_filter {
-
 use num::complex::Complex;
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData,
-    ComplexSoundSource, DynComplexSoundSource};
-use crate::dc::dc::DC;
-use crate::knob::knob::ComplexKnob;
-use crate::filters::real_to_complex::real_to_complex::RealToComplex;
+use crate::read_song::SongReader;
+use crate::frequency_response::TransferFunction;
+use crate::traits::{SoundSource, DynSoundSource, SoundData,
+    ComplexSoundSource, DynComplexSoundSource, SampleTime};
+use crate::dc::DC;
+use crate::knob::ComplexKnob;
+use crate::filters::real_to_complex::RealToComplex;
 
 #[derive(Clone)]
 pub struct ComplexElementaryRecirculatingFilter {
@@ -27,7 +26,7 @@ impl ComplexElementaryRecirculatingFilter {
 struct ComplexElementaryRecirculatingFilterData {
     input_data: SoundData,
     prev_sample: (Complex<f32>, Complex<f32>),
-    prev_sample_number: i32,
+    prev_sample_number: SampleTime,
     gain_data: SoundData,
 }
 
@@ -43,7 +42,7 @@ impl ComplexSoundSource for ComplexElementaryRecirculatingFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
         let data = state.downcast_mut::<ComplexElementaryRecirculatingFilterData>().unwrap();
         let input_value = self.input.next_value(n, &mut data.input_data);
         if n > 0 {
@@ -86,7 +85,7 @@ impl ComplexSoundSource for ComplexElementaryRecirculatingFilter {
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.input.duration()
     }
 
@@ -95,6 +94,8 @@ impl ComplexSoundSource for ComplexElementaryRecirculatingFilter {
 
 #[derive(Clone)]
 pub struct ElementaryRecirculatingFilter {
+    // Kept to describe the filter's response
+    input_transfer_function: Option<TransferFunction>,
     complex_filter: ComplexElementaryRecirculatingFilter,
 }
 
@@ -102,8 +103,10 @@ pub struct ElementaryRecirculatingFilter {
 impl ElementaryRecirculatingFilter {
     pub fn new(input: DynSoundSource, complex_gain: ComplexKnob) -> Self {
         let duration = input.duration();
+        let input_transfer_function = input.transfer_function();
         let complex_input = Box::new(RealToComplex::new(input, Box::new(DC::new(0.0, duration))));
         ElementaryRecirculatingFilter {
+            input_transfer_function: input_transfer_function,
             complex_filter: ComplexElementaryRecirculatingFilter::new(complex_input, complex_gain),
         }
     }
@@ -122,21 +125,25 @@ impl SoundSource for ElementaryRecirculatingFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<ElementaryRecirculatingFilterData>().unwrap();
         let output = self.complex_filter.next_value(n, &mut data.complex_filter_data);
         (output.0.re, output.1.re)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.complex_filter.duration()
     }
 
+    // With the gain at its starting setting
+    fn transfer_function(&self) -> Option<TransferFunction> {
+        let gain = self.complex_filter.gain.initial_value();
+        TransferFunction::new(1.0, vec![gain], vec![], vec![]).after(self.input_transfer_function.clone())
+    }
+
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
         let complex_gain = reader.get_complex_knob(&params[1]);
         Box::new(ElementaryRecirculatingFilter::new(input, complex_gain))
     }
 }
-
-}
\ No newline at end of file
@@ -0,0 +1,54 @@
+// A parametric equaliser: any number of cookbook biquad bands, one after the
+// other. Each band is written "type freq q [gain]", like
+//   "lowshelf 120 0.7 3.0"
+//   "peaking 2500 1.4 -4.5"
+//   "highpass 40 0.707"
+// with the frequency in Hz and the gain in dB.
+
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::filters::biquad_filter::{BiquadBand, BiquadBandData};
+
+#[derive(Clone)]
+pub struct Equaliser {
+    input: DynSoundSource,
+    bands: Vec<BiquadBand>,
+}
+
+impl Equaliser {
+    pub fn new(input: DynSoundSource, bands: Vec<BiquadBand>) -> Self {
+        Equaliser { input: input, bands: bands }
+    }
+}
+
+struct EqualiserData {
+    input_data: SoundData,
+    band_data: Vec<BiquadBandData>,
+}
+
+impl SoundSource for Equaliser {
+    fn init_state(&self) -> SoundData {
+        Box::new(EqualiserData {
+            input_data: self.input.init_state(),
+            band_data: self.bands.iter().map(|band| band.init_state()).collect(),
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<EqualiserData>().unwrap();
+        let input = self.input.next_value(n, &mut data.input_data);
+        self.bands.iter().zip(data.band_data.iter_mut())
+            .fold(input, |signal, (band, band_data)| band.process(n, signal, band_data))
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration()
+    }
+
+    // params: input, then one param per band
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let bands = params[1..].iter().map(|param| BiquadBand::from_param(param, reader)).collect();
+        Box::new(Equaliser::new(input, bands))
+    }
+}
@@ -1,17 +1,15 @@
-pub mod high_pass_filter {
-
 use num::complex::Complex;
 
-use crate::traits::traits::{SoundSource, DynSoundSource, DynComplexSoundSource, SoundData};
-use crate::read_song::read_song::SongReader;
-use crate::dc::dc::DC;
-use crate::knob::knob::ComplexKnob;
-use crate::mix::mix::Mix;
-use crate::multiply::multiply::Multiply;
-use crate::time_box::time_box::TimeBox;
-use crate::filters::elementary_non_recirculating_filter::elementary_non_recirculating_filter::ComplexElementaryNonRecirculatingFilter;
-use crate::filters::elementary_recirculating_filter::elementary_recirculating_filter::ComplexElementaryRecirculatingFilter;
-use crate::filters::real_to_complex::real_to_complex::RealToComplex;
+use crate::traits::{SoundSource, DynSoundSource, DynComplexSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::dc::DC;
+use crate::knob::ComplexKnob;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::time_box::TimeBox;
+use crate::filters::elementary_non_recirculating_filter::ComplexElementaryNonRecirculatingFilter;
+use crate::filters::elementary_recirculating_filter::ComplexElementaryRecirculatingFilter;
+use crate::filters::real_to_complex::RealToComplex;
 
 #[derive(Clone)]
 pub struct HighPassFilter {
@@ -65,13 +63,13 @@ impl SoundSource for HighPassFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<HighPassFilterData>().unwrap();
         let output = self.filter.next_value(n, &mut data.filter_data);
         (output.0.re, output.1.re)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.filter.duration()
     }
 
@@ -81,5 +79,3 @@ impl SoundSource for HighPassFilter {
         Box::new(HighPassFilter::new(input, cutoff))
     }
 }
-
-}
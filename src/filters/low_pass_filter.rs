@@ -1,14 +1,12 @@
-pub mod low_pass_filter {
-
-use crate::traits::traits::{SoundSource, DynSoundSource, DynComplexSoundSource, SoundData};
-use crate::read_song::read_song::SongReader;
-use crate::dc::dc::DC;
-use crate::knob::knob::ComplexKnob;
-use crate::mix::mix::Mix;
-use crate::multiply::multiply::Multiply;
-use crate::time_box::time_box::TimeBox;
-use crate::filters::elementary_recirculating_filter::elementary_recirculating_filter::ComplexElementaryRecirculatingFilter;
-use crate::filters::real_to_complex::real_to_complex::RealToComplex;
+use crate::traits::{SoundSource, DynSoundSource, DynComplexSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::dc::DC;
+use crate::knob::ComplexKnob;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::time_box::TimeBox;
+use crate::filters::elementary_recirculating_filter::ComplexElementaryRecirculatingFilter;
+use crate::filters::real_to_complex::RealToComplex;
 
 #[derive(Clone)]
 pub struct LowPassFilter {
@@ -57,13 +55,13 @@ impl SoundSource for LowPassFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<LowPassFilterData>().unwrap();
         let output = self.filter.next_value(n, &mut data.filter_data);
         (output.0.re, output.1.re)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.filter.duration()
     }
 
@@ -73,5 +71,3 @@ impl SoundSource for LowPassFilter {
         Box::new(LowPassFilter::new(input, cutoff))
     }
 }
-
-}
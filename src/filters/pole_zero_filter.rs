@@ -1,18 +1,23 @@
-pub mod pole_zero_filter {
-
-use crate::traits::traits::{SoundSource, DynSoundSource, DynComplexSoundSource, SoundData};
-use crate::read_song::read_song::SongReader;
-use crate::dc::dc::DC;
-use crate::knob::knob::ComplexKnob;
-use crate::multiply::multiply::Multiply;
-use crate::filters::elementary_non_recirculating_filter::elementary_non_recirculating_filter::ComplexElementaryNonRecirculatingFilter;
-use crate::filters::elementary_non_recirculating_filter_2nd_form::elementary_non_recirculating_filter_2nd_form::ComplexElementaryNonRecirculatingFilter2;
-use crate::filters::elementary_recirculating_filter::elementary_recirculating_filter::ComplexElementaryRecirculatingFilter;
-use crate::filters::real_to_complex::real_to_complex::RealToComplex;
+use crate::traits::{SoundSource, DynSoundSource, DynComplexSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::frequency_response::TransferFunction;
+use crate::dc::DC;
+use crate::knob::ComplexKnob;
+use crate::multiply::Multiply;
+use crate::filters::elementary_non_recirculating_filter::ComplexElementaryNonRecirculatingFilter;
+use crate::filters::elementary_non_recirculating_filter_2nd_form::ComplexElementaryNonRecirculatingFilter2;
+use crate::filters::elementary_recirculating_filter::ComplexElementaryRecirculatingFilter;
+use crate::filters::real_to_complex::RealToComplex;
 
 #[derive(Clone)]
 pub struct PoleZeroFilter {
     filter: DynComplexSoundSource,
+    // Kept to describe the filter's response
+    input_transfer_function: Option<TransferFunction>,
+    normalize: DynSoundSource,
+    poles: Vec<ComplexKnob>,
+    zeros: Vec<ComplexKnob>,
+    zero2s: Vec<ComplexKnob>,
 }
 
 impl PoleZeroFilter {
@@ -20,9 +25,10 @@ impl PoleZeroFilter {
         zero2s: Vec<ComplexKnob>) -> Self
     {
         let duration = input.duration();
+        let input_transfer_function = input.transfer_function();
         let mut normalize_input = Multiply::new();
         normalize_input.add(input, 0.0);
-        normalize_input.add(normalize, 0.0);
+        normalize_input.add(normalize.clone(), 0.0);
         let mut filter: DynComplexSoundSource = Box::new(RealToComplex::new(Box::new(normalize_input),
             Box::new(DC::new(0.0, duration))));
         for pole in &poles {
@@ -36,6 +42,11 @@ impl PoleZeroFilter {
         }
         PoleZeroFilter {
             filter: filter,
+            input_transfer_function: input_transfer_function,
+            normalize: normalize,
+            poles: poles,
+            zeros: zeros,
+            zero2s: zero2s,
         }
     }
 }
@@ -53,16 +64,27 @@ impl SoundSource for PoleZeroFilter {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<PoleZeroFilterData>().unwrap();
         let output = self.filter.next_value(n, &mut data.filter_data);
         (output.0.re, output.1.re)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.filter.duration()
     }
 
+    // With the knobs at their starting settings
+    fn transfer_function(&self) -> Option<TransferFunction> {
+        let mut normalize_data = self.normalize.init_state();
+        TransferFunction::new(
+            self.normalize.next_value(0, &mut normalize_data).0,
+            self.poles.iter().map(|pole| pole.initial_value()).collect(),
+            self.zeros.iter().map(|zero| zero.initial_value()).collect(),
+            self.zero2s.iter().map(|zero2| zero2.initial_value()).collect(),
+        ).after(self.input_transfer_function.clone())
+    }
+
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
         let normalize: DynSoundSource;
@@ -74,8 +96,7 @@ impl SoundSource for PoleZeroFilter {
         let mut poles = Vec::<ComplexKnob>::new();
         let mut zeros = Vec::<ComplexKnob>::new();
         let mut zero2s = Vec::<ComplexKnob>::new();
-        for idx in 2..params.len() {
-            let param = &params[idx];
+        for param in &params[2..] {
             let mut split = param.split(",");
             let param_type = split.next().unwrap();
             let point = reader.get_complex_knob(&(split.next().unwrap().to_owned()+","+split.next().unwrap()));
@@ -89,5 +110,3 @@ impl SoundSource for PoleZeroFilter {
         Box::new(PoleZeroFilter::new(input, normalize, poles, zeros, zero2s))
     }
 }
-
-}
\ No newline at end of file
@@ -1,8 +1,6 @@
-pub mod real_to_complex {
-
 use num::complex::Complex;
 
-use crate::traits::traits::{DynSoundSource, SoundData, ComplexSoundSource};
+use crate::traits::{DynSoundSource, SoundData, ComplexSoundSource, SampleTime};
 
 #[derive(Clone)]
 pub struct RealToComplex {
@@ -34,17 +32,14 @@ impl ComplexSoundSource for RealToComplex {
         )
     }
 
-    fn next_value(&self, n:i32, state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (Complex<f32>, Complex<f32>) {
         let data = &mut state.downcast_mut::<RealToComplexData>().unwrap();
         let magnitude = self.magnitude.next_value(n, &mut data.magnitude_data);
         let angle = self.angle.next_value(n, &mut data.angle_data);
         (Complex::from_polar(magnitude.0, angle.0), Complex::from_polar(magnitude.1, angle.1))
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.magnitude.duration()
     }
 }
-
-
-}
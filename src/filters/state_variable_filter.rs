@@ -0,0 +1,141 @@
+// A state variable filter in the topology preserving transform (zero delay
+// feedback) form from Vadim Zavalishin's The Art of VA Filter Design, as
+// arranged by Andrew Simper. It gives low pass, high pass, band pass and notch
+// outputs from the same two integrators, and because the integrators' state
+// is kept as it would be in an analogue circuit the cutoff and resonance can
+// be swept at audio rate without clicks or blowing up.
+//
+// Resonance runs from 0 (a Q of 0.5, no peak) to 1 (a Q of 50, ringing for a
+// long time but still losing energy so it can't run away).
+
+use std::f32::consts::PI;
+
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::read_song::SongReader;
+use crate::knob::Knob;
+
+// 1 / Q at full resonance
+const MIN_DAMPING: f32 = 0.02;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum FilterMode {
+    LowPass,
+    HighPass,
+    BandPass,
+    Notch,
+}
+
+impl FilterMode {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "lp" | "low_pass" => FilterMode::LowPass,
+            "hp" | "high_pass" => FilterMode::HighPass,
+            "bp" | "band_pass" => FilterMode::BandPass,
+            "notch" => FilterMode::Notch,
+            _ => panic!("Filter mode must be lp, hp, bp or notch, not \"{}\"", param)
+        }
+    }
+}
+
+// g for a cutoff in Hz, kept below nyquist where tan blows up
+pub fn prewarp(cutoff: f32, sample_rate: i32) -> f32 {
+    let cutoff = cutoff.clamp(1.0, sample_rate as f32 * 0.49);
+    (PI * cutoff / sample_rate as f32).tan()
+}
+
+// k (1 / Q) for a resonance from 0 to 1
+pub fn damping(resonance: f32) -> f32 {
+    2.0 - (2.0 - MIN_DAMPING) * resonance.clamp(0.0, 1.0)
+}
+
+// The two integrators' state for one channel, for nodes that filter inside
+// their own loops
+#[derive(Clone, Copy, Default)]
+pub struct SvfChannel {
+    ic1eq: f32,
+    ic2eq: f32,
+}
+
+impl SvfChannel {
+    // g from prewarp and k from damping
+    pub fn process(&mut self, v0: f32, g: f32, k: f32, mode: FilterMode) -> f32 {
+        let a1 = 1.0 / (1.0 + g * (g + k));
+        let a2 = g * a1;
+        let a3 = g * a2;
+        let v3 = v0 - self.ic2eq;
+        let v1 = a1 * self.ic1eq + a2 * v3;
+        let v2 = self.ic2eq + a2 * self.ic1eq + a3 * v3;
+        self.ic1eq = 2.0 * v1 - self.ic1eq;
+        self.ic2eq = 2.0 * v2 - self.ic2eq;
+        let (low, band) = (v2, v1);
+        let high = v0 - k * band - low;
+        match mode {
+            FilterMode::LowPass => low,
+            FilterMode::HighPass => high,
+            FilterMode::BandPass => band,
+            FilterMode::Notch => low + high,
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct StateVariableFilter {
+    input: DynSoundSource,
+    mode: FilterMode,
+    cutoff: Knob, // Hz
+    resonance: Knob,
+    sample_rate: i32,
+}
+
+impl StateVariableFilter {
+    pub fn new(input: DynSoundSource, mode: FilterMode, cutoff: Knob, resonance: Knob, sample_rate: i32) -> Self {
+        StateVariableFilter {
+            input: input,
+            mode: mode,
+            cutoff: cutoff,
+            resonance: resonance,
+            sample_rate: sample_rate,
+        }
+    }
+}
+
+struct StateVariableFilterData {
+    input_data: SoundData,
+    cutoff_data: SoundData,
+    resonance_data: SoundData,
+    channels: [SvfChannel; 2],
+}
+
+impl SoundSource for StateVariableFilter {
+    fn init_state(&self) -> SoundData {
+        Box::new(StateVariableFilterData {
+            input_data: self.input.init_state(),
+            cutoff_data: self.cutoff.init_state(),
+            resonance_data: self.resonance.init_state(),
+            channels: [SvfChannel::default(); 2],
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<StateVariableFilterData>().unwrap();
+        let input = self.input.next_value(n, &mut data.input_data);
+        let g = prewarp(self.cutoff.next_value(n, &mut data.cutoff_data), self.sample_rate);
+        let k = damping(self.resonance.next_value(n, &mut data.resonance_data));
+        let left = data.channels[0].process(input.0, g, k, self.mode);
+        let right = data.channels[1].process(input.1, g, k, self.mode);
+        (left, right)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration()
+    }
+
+    // params: input, mode (lp, hp, bp or notch), cutoff in Hz, resonance
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let mode = FilterMode::from_param(&params[1]);
+        let cutoff = reader.get_value_knob(&params[2], 1.0);
+        let resonance = reader.get_value_knob(&params[3], 1.0);
+        Box::new(StateVariableFilter::new(input, mode, cutoff, resonance, reader.sample_rate))
+    }
+}
@@ -0,0 +1,152 @@
+// A flanger: the input mixed with a copy of itself whose short delay sweeps up
+// and down, so the comb of notches this makes sweeps up and down too. Feeding
+// the delayed copy back into the delay deepens the notches and brings out
+// ringing peaks between them (negative feedback moves the peaks to where the
+// notches were).
+//
+// Through zero flanging delays the dry sound as well, by the middle of the
+// sweep, so the swept copy passes through it and the notches sweep all the
+// way up through the top of the spectrum, like two tape machines being slowed
+// in turn.
+
+use std::f64::consts::PI;
+
+use crate::delay_buffer::DelayBuffer;
+use crate::knob::Knob;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+// Any more and the ringing builds up without end
+pub const MAX_FEEDBACK: f32 = 0.95;
+
+#[derive(Clone)]
+pub struct Flanger {
+    input: DynSoundSource,
+    rate: Knob, // Cycles per sample
+    depth: Knob, // Samples either side of the delay
+    feedback: Knob,
+    mix: Knob,
+    delay: f32, // Samples
+    through_zero: bool,
+}
+
+impl Flanger {
+    // Sweeping around 2.5 ms, not through zero
+    pub fn new(input: DynSoundSource, rate: Knob, depth: Knob, feedback: Knob, mix: Knob, sample_rate: i32) -> Self {
+        Flanger {
+            input: input,
+            rate: rate,
+            depth: depth,
+            feedback: feedback,
+            mix: mix,
+            delay: 0.0025 * sample_rate as f32,
+            through_zero: false,
+        }
+    }
+
+    // The delay in samples the sweep goes either side of. The depth is kept
+    // below it.
+    pub fn delay(mut self, delay: f32) -> Self {
+        self.delay = delay.max(1.0);
+        self
+    }
+
+    pub fn through_zero(mut self, through_zero: bool) -> Self {
+        self.through_zero = through_zero;
+        self
+    }
+
+    fn new_data(&self) -> FlangerData {
+        let buffer = DelayBuffer::new(2 * self.delay.ceil() as usize);
+        FlangerData {
+            input_data: self.input.init_state(),
+            rate_data: self.rate.init_state(),
+            depth_data: self.depth.init_state(),
+            feedback_data: self.feedback.init_state(),
+            mix_data: self.mix.init_state(),
+            next_n: 0,
+            buffers: [buffer.clone(), buffer],
+            dry_buffers: [DelayBuffer::new(self.delay.ceil() as usize), DelayBuffer::new(self.delay.ceil() as usize)],
+            phase: 0.0,
+        }
+    }
+}
+
+struct FlangerData {
+    input_data: SoundData,
+    rate_data: SoundData,
+    depth_data: SoundData,
+    feedback_data: SoundData,
+    mix_data: SoundData,
+    next_n: SampleTime,
+    buffers: [DelayBuffer; 2],
+    // To delay the dry sound for through zero flanging
+    dry_buffers: [DelayBuffer; 2],
+    phase: f64, // Fraction of a cycle
+}
+
+impl SoundSource for Flanger {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<FlangerData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let input = if n < self.input.duration() { self.input.next_value(n, &mut data.input_data) } else { (0.0, 0.0) };
+        let rate = self.rate.next_value(n, &mut data.rate_data) as f64;
+        let depth = self.depth.next_value(n, &mut data.depth_data).clamp(0.0, self.delay - 1.0);
+        let feedback = self.feedback.next_value(n, &mut data.feedback_data).clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+        let mix = self.mix.next_value(n, &mut data.mix_data).clamp(0.0, 1.0);
+
+        let delay = self.delay + depth * (2.0 * PI * data.phase).sin() as f32;
+        data.phase = (data.phase + rate).rem_euclid(1.0);
+        let mut output = [0.0; 2];
+        for (ch, x) in [input.0, input.1].into_iter().enumerate() {
+            let wet = data.buffers[ch].read(delay);
+            data.buffers[ch].push(x + feedback * wet);
+            let dry = if self.through_zero {
+                let dry = data.dry_buffers[ch].read(self.delay);
+                data.dry_buffers[ch].push(x);
+                dry
+            } else {
+                x
+            };
+            output[ch] = dry * (1.0 - mix) + wet * mix;
+        }
+        (output[0], output[1])
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(2 * self.delay.ceil() as SampleTime)
+    }
+
+    // params: input, rate, depth in ms, delay in ms, feedback, through zero,
+    // mix
+    // The rate is in Hz or a note value like 1/4 at the song's tempo. Rate,
+    // depth, feedback and mix can be sounds. The delay sweeps by up to the
+    // depth either side of the delay. Feedback runs from -0.95 to 0.95 and mix
+    // from 0 to 1, with the deepest notches at 0.5. Through zero is
+    // through_zero or normal.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let sample_rate = reader.sample_rate as f32;
+        let rate = reader.get_rate_knob(&params[1], 1.0 / sample_rate);
+        let depth = reader.get_value_knob(&params[2], sample_rate / 1000.0);
+        let delay = params[3].parse::<f32>().unwrap() * sample_rate / 1000.0;
+        let feedback = reader.get_value_knob(&params[4], 1.0);
+        let through_zero = match params[5].as_str() {
+            "through_zero" => true,
+            "normal" => false,
+            _ => panic!("Flanger must be through_zero or normal, not \"{}\"", params[5]),
+        };
+        let mix = reader.get_value_knob(&params[6], 1.0);
+        Box::new(Flanger::new(input, rate, depth, feedback, mix, reader.sample_rate)
+            .delay(delay)
+            .through_zero(through_zero))
+    }
+}
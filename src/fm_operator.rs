@@ -0,0 +1,133 @@
+// An FM operator is a sine oscillator whose phase can be pushed around by
+// other operators (what synths call FM is really phase modulation). Its
+// frequency is either a ratio of the note frequency or fixed in Hz, and its
+// level is a knob so that an envelope can shape it. For a carrier the level is
+// the output amplitude. For a modulator it is the modulation index in radians,
+// so levels of a few radians give bright, busy spectra.
+//
+// Self-feedback feeds the operator's own output back into its phase. Like the
+// DX7 it uses the average of the last two outputs, which stops the loop from
+// flipping between two values at high feedback. At around 1.5 radians a sine
+// turns into something close to a saw, and much above 2 it turns into noise.
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum OperatorFrequency {
+    Ratio(f32), // Multiple of the note frequency
+    Fixed(f32), // Fraction of the sample rate, whatever note is played
+}
+
+impl OperatorFrequency {
+    // A plain number is a ratio, a number followed by Hz is a fixed frequency
+    pub fn from_param(param: &str, sample_rate: i32) -> Self {
+        let param = param.trim();
+        let lower = param.to_lowercase();
+        match lower.strip_suffix("hz") {
+            Some(hz) => OperatorFrequency::Fixed(hz.trim().parse::<f32>()
+                .expect("Fixed operator frequency must be a number of Hz, e.g. 440Hz") / sample_rate as f32),
+            None => OperatorFrequency::Ratio(param.parse::<f32>()
+                .expect("Operator frequency must be a ratio like 2.0 or a fixed frequency like 440Hz")),
+        }
+    }
+
+    pub fn freq(&self, note_freq: f32) -> f32 {
+        match self {
+            OperatorFrequency::Ratio(ratio) => note_freq * ratio,
+            OperatorFrequency::Fixed(freq) => *freq,
+        }
+    }
+}
+
+// The running state of one operator, shared with FmVoice which runs several of
+// them together
+pub struct OperatorState {
+    phase: f64,
+    prev: [f32; 2],
+}
+
+impl OperatorState {
+    pub fn new() -> Self {
+        OperatorState { phase: 0.0, prev: [0.0; 2] }
+    }
+
+    // The average of the last two outputs, for feeding back into a phase
+    pub fn feedback(&self) -> f32 {
+        (self.prev[0] + self.prev[1]) * 0.5
+    }
+
+    // `modulation` is in radians
+    pub fn next_value(&mut self, freq: f32, level: f32, modulation: f32) -> f32 {
+        let val = level * ((self.phase * std::f64::consts::TAU) as f32 + modulation).sin();
+        self.phase = (self.phase + freq as f64).fract();
+        self.prev = [val, self.prev[0]];
+        val
+    }
+}
+
+#[derive(Clone)]
+pub struct FmOperator {
+    freq: Knob, // Note frequency as fraction of sample rate
+    frequency: OperatorFrequency,
+    level: Knob,
+    feedback: f32, // Radians of phase per unit of output
+    modulator: Option<DynSoundSource>, // Radians of phase
+    duration: SampleTime,
+}
+
+impl FmOperator {
+    pub fn new(freq: Knob, frequency: OperatorFrequency, level: Knob, feedback: f32, modulator: Option<DynSoundSource>, duration: SampleTime) -> Self {
+        FmOperator { freq: freq, frequency: frequency, level: level, feedback: feedback, modulator: modulator, duration: duration }
+    }
+}
+
+struct FmOperatorData {
+    freq_data: SoundData,
+    level_data: SoundData,
+    modulator_data: Option<SoundData>,
+    operator: OperatorState,
+}
+
+impl SoundSource for FmOperator {
+    fn init_state(&self) -> SoundData {
+        Box::new(FmOperatorData {
+            freq_data: self.freq.init_state(),
+            level_data: self.level.init_state(),
+            modulator_data: self.modulator.as_ref().map(|modulator| modulator.init_state()),
+            operator: OperatorState::new(),
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<FmOperatorData>().unwrap();
+        let freq = self.frequency.freq(self.freq.next_value(n, &mut data.freq_data));
+        let level = self.level.next_value(n, &mut data.level_data);
+        let mut modulation = self.feedback * data.operator.feedback();
+        if let (Some(modulator), Some(modulator_data)) = (&self.modulator, &mut data.modulator_data) {
+            modulation += modulator.next_value(n, modulator_data).0;
+        }
+        let val = data.operator.next_value(freq, level, modulation);
+        (val, val)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let freq = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let frequency = OperatorFrequency::from_param(&params[1], reader.sample_rate);
+        let level = reader.get_knob(&params[2], 1.0);
+        let feedback = params[3].parse::<f32>().unwrap();
+        let duration = reader.get_duration(&params[4]);
+        // An optional sixth parameter names the sound modulating this one
+        let modulator = params.get(5).map(|modulator| reader.get_sound(modulator));
+        Box::new(Self::new(freq, frequency, level, feedback, modulator, duration))
+    }
+}
@@ -0,0 +1,266 @@
+// An FM voice runs a set of FM operators wired together by an algorithm
+// number, the same way FM synths do. Operators are numbered from 1 and, as on
+// the hardware, higher numbered operators modulate lower numbered ones. The
+// operators that aren't modulating anything are carriers and are mixed to make
+// the output.
+//
+// The algorithms are those of the classic synths:
+//   2 operators: 1-2
+//   4 operators: 1-8 as on the DX21/DX27/DX100/TX81Z, with feedback on op 4
+//   6 operators: 1-32 as on the DX7
+// The feedback amount applies to the algorithm's feedback loop. Usually this
+// is an operator modulating itself, but DX7 algorithms 4 and 6 loop back
+// through two or three operators. Anything fed back uses the average of the
+// last two samples, just like FmOperator's self-feedback.
+//
+// FmInstrument plays an FmVoice for each note, giving every operator its own
+// attack-decay-sustain-release envelope.
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, Instrument, SampleTime};
+
+use crate::envelope::Envelope;
+use crate::fm_operator::{OperatorFrequency, OperatorState};
+use crate::knob::Knob;
+
+// (modulations as (from, to), carriers), with operators numbered from 1
+type AlgorithmTable = [(&'static [(usize, usize)], &'static [usize])];
+
+const TWO_OP_ALGORITHMS: &AlgorithmTable = &[
+    (&[(2, 1), (2, 2)], &[1]),
+    (&[(2, 2)], &[1, 2]),
+];
+
+const FOUR_OP_ALGORITHMS: &AlgorithmTable = &[
+    (&[(4, 3), (3, 2), (2, 1), (4, 4)], &[1]),
+    (&[(4, 2), (3, 2), (2, 1), (4, 4)], &[1]),
+    (&[(4, 1), (3, 2), (2, 1), (4, 4)], &[1]),
+    (&[(4, 3), (3, 1), (2, 1), (4, 4)], &[1]),
+    (&[(4, 3), (2, 1), (4, 4)], &[1, 3]),
+    (&[(4, 3), (4, 2), (4, 1), (4, 4)], &[1, 2, 3]),
+    (&[(4, 3), (4, 4)], &[1, 2, 3]),
+    (&[(4, 4)], &[1, 2, 3, 4]),
+];
+
+const SIX_OP_ALGORITHMS: &AlgorithmTable = &[
+    (&[(2, 1), (6, 5), (5, 4), (4, 3), (6, 6)], &[1, 3]),
+    (&[(2, 1), (6, 5), (5, 4), (4, 3), (2, 2)], &[1, 3]),
+    (&[(3, 2), (2, 1), (6, 5), (5, 4), (6, 6)], &[1, 4]),
+    (&[(3, 2), (2, 1), (6, 5), (5, 4), (4, 6)], &[1, 4]),
+    (&[(2, 1), (4, 3), (6, 5), (6, 6)], &[1, 3, 5]),
+    (&[(2, 1), (4, 3), (6, 5), (5, 6)], &[1, 3, 5]),
+    (&[(2, 1), (4, 3), (6, 5), (5, 3), (6, 6)], &[1, 3]),
+    (&[(2, 1), (4, 3), (6, 5), (5, 3), (4, 4)], &[1, 3]),
+    (&[(2, 1), (4, 3), (6, 5), (5, 3), (2, 2)], &[1, 3]),
+    (&[(3, 2), (2, 1), (5, 4), (6, 4), (3, 3)], &[1, 4]),
+    (&[(3, 2), (2, 1), (5, 4), (6, 4), (6, 6)], &[1, 4]),
+    (&[(2, 1), (4, 3), (5, 3), (6, 3), (2, 2)], &[1, 3]),
+    (&[(2, 1), (4, 3), (5, 3), (6, 3), (6, 6)], &[1, 3]),
+    (&[(2, 1), (4, 3), (5, 4), (6, 4), (6, 6)], &[1, 3]),
+    (&[(2, 1), (4, 3), (5, 4), (6, 4), (2, 2)], &[1, 3]),
+    (&[(2, 1), (3, 1), (4, 3), (5, 1), (6, 5), (6, 6)], &[1]),
+    (&[(2, 1), (3, 1), (4, 3), (5, 1), (6, 5), (2, 2)], &[1]),
+    (&[(2, 1), (3, 1), (4, 1), (5, 4), (6, 5), (3, 3)], &[1]),
+    (&[(3, 2), (2, 1), (6, 4), (6, 5), (6, 6)], &[1, 4, 5]),
+    (&[(3, 1), (3, 2), (5, 4), (6, 4), (3, 3)], &[1, 2, 4]),
+    (&[(3, 1), (3, 2), (6, 4), (6, 5), (3, 3)], &[1, 2, 4, 5]),
+    (&[(2, 1), (6, 3), (6, 4), (6, 5), (6, 6)], &[1, 3, 4, 5]),
+    (&[(3, 2), (6, 4), (6, 5), (6, 6)], &[1, 2, 4, 5]),
+    (&[(6, 3), (6, 4), (6, 5), (6, 6)], &[1, 2, 3, 4, 5]),
+    (&[(6, 4), (6, 5), (6, 6)], &[1, 2, 3, 4, 5]),
+    (&[(3, 2), (5, 4), (6, 4), (6, 6)], &[1, 2, 4]),
+    (&[(3, 2), (5, 4), (6, 4), (3, 3)], &[1, 2, 4]),
+    (&[(2, 1), (5, 4), (4, 3), (5, 5)], &[1, 3, 6]),
+    (&[(4, 3), (6, 5), (6, 6)], &[1, 2, 3, 5]),
+    (&[(5, 4), (4, 3), (5, 5)], &[1, 2, 3, 6]),
+    (&[(6, 5), (6, 6)], &[1, 2, 3, 4, 5]),
+    (&[(6, 6)], &[1, 2, 3, 4, 5, 6]),
+];
+
+#[derive(Clone, Debug)]
+pub struct FmAlgorithm {
+    // For each operator, the operators modulating it (counting from 0)
+    modulators: Vec<Vec<usize>>,
+    carriers: Vec<usize>,
+}
+
+impl FmAlgorithm {
+    pub fn new(operators: usize, number: usize) -> Self {
+        let table = match operators {
+            2 => TWO_OP_ALGORITHMS,
+            4 => FOUR_OP_ALGORITHMS,
+            6 => SIX_OP_ALGORITHMS,
+            _ => panic!("FM voices must have 2, 4 or 6 operators, not {}", operators)
+        };
+        if !(1..=table.len()).contains(&number) {
+            panic!("Algorithm for {} operators must be from 1 to {}, not {}", operators, table.len(), number);
+        }
+        let (modulations, carriers) = table[number - 1];
+        let mut modulators = vec![Vec::new(); operators];
+        for (from, to) in modulations {
+            modulators[to - 1].push(from - 1);
+        }
+        FmAlgorithm {
+            modulators: modulators,
+            carriers: carriers.iter().map(|carrier| carrier - 1).collect(),
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct FmVoice {
+    freq: Knob, // Note frequency as fraction of sample rate
+    gain: Knob,
+    operators: Vec<(OperatorFrequency, Knob)>, // Frequency and level of each operator
+    algorithm: FmAlgorithm,
+    feedback: f32, // Radians of phase per unit of output
+    duration: SampleTime,
+}
+
+impl FmVoice {
+    pub fn new(freq: Knob, gain: Knob, operators: Vec<(OperatorFrequency, Knob)>, algorithm: FmAlgorithm, feedback: f32, duration: SampleTime) -> Self {
+        if operators.len() != algorithm.modulators.len() {
+            panic!("FM voice has {} operators but its algorithm is for {}", operators.len(), algorithm.modulators.len());
+        }
+        FmVoice { freq: freq, gain: gain, operators: operators, algorithm: algorithm, feedback: feedback, duration: duration }
+    }
+}
+
+struct FmVoiceData {
+    freq_data: SoundData,
+    gain_data: SoundData,
+    level_data: Vec<SoundData>,
+    operators: Vec<OperatorState>,
+    outputs: Vec<f32>,
+}
+
+impl SoundSource for FmVoice {
+    fn init_state(&self) -> SoundData {
+        Box::new(FmVoiceData {
+            freq_data: self.freq.init_state(),
+            gain_data: self.gain.init_state(),
+            level_data: self.operators.iter().map(|(_, level)| level.init_state()).collect(),
+            operators: self.operators.iter().map(|_| OperatorState::new()).collect(),
+            outputs: vec![0.0; self.operators.len()],
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<FmVoiceData>().unwrap();
+        let freq = self.freq.next_value(n, &mut data.freq_data);
+        let gain = self.gain.next_value(n, &mut data.gain_data);
+        // Higher numbered operators go first so their output is ready for the
+        // operators they modulate. Anything modulating an operator that went
+        // before it is feedback and uses previous samples.
+        for i in (0..self.operators.len()).rev() {
+            let (frequency, level) = &self.operators[i];
+            let level = level.next_value(n, &mut data.level_data[i]);
+            let mut modulation = 0.0;
+            for &modulator in &self.algorithm.modulators[i] {
+                if modulator > i {
+                    modulation += data.outputs[modulator];
+                } else {
+                    modulation += self.feedback * data.operators[modulator].feedback();
+                }
+            }
+            data.outputs[i] = data.operators[i].next_value(frequency.freq(freq), level, modulation);
+        }
+        let mut val = 0.0;
+        for &carrier in &self.algorithm.carriers {
+            val += data.outputs[carrier];
+        }
+        val *= gain / self.algorithm.carriers.len() as f32;
+        (val, val)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let freq = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let gain = reader.get_knob(&params[1], 1.0);
+        let note_duration = reader.get_duration(&params[2]);
+        let algorithm = params[3].parse::<usize>().unwrap();
+        let feedback = params[4].parse::<f32>().unwrap();
+        // Then one parameter per operator, starting with operator 1
+        let settings: Vec<_> = params[5..].iter()
+            .map(|param| FmOperatorSettings::from_param(param, reader.sample_rate))
+            .collect();
+        let algorithm = FmAlgorithm::new(settings.len(), algorithm);
+        let instrument = FmInstrument::new(reader.sample_rate, settings, algorithm, feedback);
+        Box::new(instrument.voice(freq, gain, note_duration))
+    }
+}
+
+// How to play one operator of an FmInstrument. Times are in seconds. The
+// envelope rises to `level` over the attack, falls to `level * sustain` over
+// the decay, holds until the note ends then falls to zero over the release.
+#[derive(Clone, Debug)]
+pub struct FmOperatorSettings {
+    pub frequency: OperatorFrequency,
+    pub level: f32,
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl FmOperatorSettings {
+    pub fn new(frequency: OperatorFrequency, level: f32, attack: f32, decay: f32, sustain: f32, release: f32) -> Self {
+        FmOperatorSettings { frequency: frequency, level: level, attack: attack, decay: decay, sustain: sustain, release: release }
+    }
+
+    // "frequency level attack decay sustain release", where frequency is a
+    // ratio like 2.0 or a fixed frequency like 440Hz
+    pub fn from_param(param: &str, sample_rate: i32) -> Self {
+        let parts: Vec<_> = param.split_whitespace().collect();
+        if parts.len() != 6 {
+            panic!("FM operator settings must be \"frequency level attack decay sustain release\", not \"{}\"", param);
+        }
+        let number = |part: &str| part.parse::<f32>().unwrap();
+        Self::new(OperatorFrequency::from_param(parts[0], sample_rate),
+            number(parts[1]), number(parts[2]), number(parts[3]), number(parts[4]), number(parts[5]))
+    }
+}
+
+#[derive(Clone)]
+pub struct FmInstrument {
+    sample_rate: i32,
+    operators: Vec<FmOperatorSettings>,
+    algorithm: FmAlgorithm,
+    feedback: f32,
+}
+
+impl FmInstrument {
+    pub fn new(sample_rate: i32, operators: Vec<FmOperatorSettings>, algorithm: FmAlgorithm, feedback: f32) -> Self {
+        FmInstrument { sample_rate: sample_rate, operators: operators, algorithm: algorithm, feedback: feedback }
+    }
+
+    fn t2n(&self, t: f32) -> SampleTime {
+        (t * self.sample_rate as f32).round() as SampleTime
+    }
+
+    // A voice for a note lasting `duration` samples. The voice lasts until
+    // the longest release has finished.
+    pub fn voice(&self, freq: Knob, gain: Knob, duration: SampleTime) -> FmVoice {
+        let mut release = 0;
+        let mut operators = Vec::new();
+        for settings in &self.operators {
+            let envelope = Envelope::adsr(settings.level, self.t2n(settings.attack), self.t2n(settings.decay),
+                settings.sustain, self.t2n(settings.release), duration);
+            release = release.max(envelope.duration() - duration);
+            operators.push((settings.frequency, Knob::new(Box::new(envelope))));
+        }
+        FmVoice::new(freq, gain, operators, self.algorithm.clone(), self.feedback, duration + release)
+    }
+}
+
+impl Instrument for FmInstrument {
+    fn play(&self, freq: f32, duration: SampleTime, strength: f32) -> DynSoundSource {
+        Box::new(self.voice(Knob::dc(freq), Knob::dc(strength), duration))
+    }
+}
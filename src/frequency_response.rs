@@ -0,0 +1,257 @@
+// Frequency responses of filters, for checking that a filter really does what
+// its settings ask for. Filters built from poles and zeros give their transfer
+// function so their response can be worked out exactly. Anything else is
+// measured by feeding it an impulse and taking the spectrum of what comes out,
+// which only shows the whole story for filters that are linear and don't
+// change over time.
+//
+// Responses are a magnitude in dB and a phase in degrees at each of a list of
+// frequencies, and can be written out as CSV or drawn as an SVG plot.
+
+use std::f64::consts::PI;
+use std::fs::File;
+use std::io::{BufWriter, Write};
+use num::complex::Complex;
+
+use crate::traits::{DynSoundSource, SampleTime};
+
+// The poles and zeros of one of the elementary filters or a chain of them. A
+// pole p contributes 1 / (1 - p z^-1), a zero q contributes 1 - q z^-1 and a
+// second form zero q contributes q* - z^-1, as in Miller Puckette's The Theory
+// and Technique of Electronic Music. A filter fed by another filter includes
+// the input filter's transfer function too. A filter fed by anything else
+// (other than the impulse standing in for its input) has no transfer function,
+// so its response has to be measured.
+#[derive(Clone, Debug)]
+pub struct TransferFunction {
+    pub normalize: f32,
+    pub poles: Vec<Complex<f32>>,
+    pub zeros: Vec<Complex<f32>>,
+    pub zero2s: Vec<Complex<f32>>,
+    pub input: Option<Box<TransferFunction>>,
+}
+
+impl TransferFunction {
+    pub fn new(normalize: f32, poles: Vec<Complex<f32>>, zeros: Vec<Complex<f32>>, zero2s: Vec<Complex<f32>>) -> Self {
+        TransferFunction { normalize: normalize, poles: poles, zeros: zeros, zero2s: zero2s, input: None }
+    }
+
+    // Passes everything through unchanged
+    pub fn identity() -> Self {
+        TransferFunction::new(1.0, vec![], vec![], vec![])
+    }
+
+    fn is_identity(&self) -> bool {
+        self.normalize == 1.0 && self.poles.is_empty() && self.zeros.is_empty() && self.zero2s.is_empty() && self.input.is_none()
+    }
+
+    // This filter fed by its input's transfer function, or None if the input
+    // doesn't have one
+    pub fn after(mut self, input: Option<TransferFunction>) -> Option<Self> {
+        let input = input?;
+        if !input.is_identity() {
+            self.input = Some(Box::new(input));
+        }
+        Some(self)
+    }
+
+    // The response of the complex filter at w radians per sample
+    fn complex_response(&self, w: f64) -> Complex<f64> {
+        let one = Complex::new(1.0, 0.0);
+        let delay = Complex::from_polar(1.0, -w);
+        let point = |p: &Complex<f32>| Complex::new(p.re as f64, p.im as f64);
+        let mut res = Complex::new(self.normalize as f64, 0.0);
+        for pole in &self.poles {
+            res /= one - point(pole) * delay;
+        }
+        for zero in &self.zeros {
+            res *= one - point(zero) * delay;
+        }
+        for zero2 in &self.zero2s {
+            res *= point(zero2).conj() - delay;
+        }
+        res
+    }
+
+    // The filters feed a real signal through complex poles and zeros and keep
+    // the real part of the result. That averages the response at w with the
+    // conjugate of the response at -w, which only matters when the poles and
+    // zeros don't come in conjugate pairs.
+    pub fn response(&self, w: f64) -> Complex<f64> {
+        let res = (self.complex_response(w) + self.complex_response(-w).conj()) / 2.0;
+        match &self.input {
+            Some(input) => res * input.response(w),
+            None => res,
+        }
+    }
+}
+
+pub struct ResponsePoint {
+    pub freq: f64, // Hz
+    pub magnitude: f64, // dB
+    pub phase: f64, // degrees
+}
+
+pub struct FrequencyResponse {
+    pub points: Vec<ResponsePoint>,
+}
+
+// count frequencies from lowest to highest, evenly spaced on a log scale
+pub fn log_frequencies(lowest: f64, highest: f64, count: usize) -> Vec<f64> {
+    let steps = (count.max(2) - 1) as f64;
+    (0..count.max(2)).map(|i| lowest * (highest / lowest).powf(i as f64 / steps)).collect()
+}
+
+impl FrequencyResponse {
+    fn from_fn(freqs: &[f64], sample_rate: i32, response: impl Fn(f64) -> Complex<f64>) -> Self {
+        let points = freqs.iter().map(|&freq| {
+            let h = response(2.0 * PI * freq / sample_rate as f64);
+            ResponsePoint {
+                freq: freq,
+                magnitude: 20.0 * h.norm().max(1e-20).log10(),
+                phase: h.arg().to_degrees(),
+            }
+        }).collect();
+        FrequencyResponse { points: points }
+    }
+
+    pub fn from_transfer_function(transfer_function: &TransferFunction, freqs: &[f64], sample_rate: i32) -> Self {
+        FrequencyResponse::from_fn(freqs, sample_rate, |w| transfer_function.response(w))
+    }
+
+    // The response from an impulse response, where the impulse was level high
+    pub fn from_impulse_response(impulse_response: &[f32], level: f32, freqs: &[f64], sample_rate: i32) -> Self {
+        FrequencyResponse::from_fn(freqs, sample_rate, |w| {
+            impulse_response.iter().enumerate()
+                .map(|(n, &x)| Complex::from_polar(x as f64, -w * n as f64))
+                .sum::<Complex<f64>>() / level as f64
+        })
+    }
+
+    // Play length samples of a filter whose input is an impulse of the given
+    // level and work out the response from its left channel
+    pub fn measure(filter: &DynSoundSource, level: f32, length: SampleTime, freqs: &[f64], sample_rate: i32) -> Self {
+        let mut state = filter.init_state();
+        let impulse_response: Vec<_> = (0..length).map(|n| filter.next_value(n, &mut state).0).collect();
+        FrequencyResponse::from_impulse_response(&impulse_response, level, freqs, sample_rate)
+    }
+
+    pub fn peak(&self) -> &ResponsePoint {
+        self.points.iter().max_by(|a, b| a.magnitude.total_cmp(&b.magnitude)).unwrap()
+    }
+
+    // The frequencies where the magnitude passes through level dB, found by
+    // interpolating between points on a log frequency scale
+    pub fn crossings(&self, level: f64) -> Vec<f64> {
+        self.points.windows(2).filter_map(|pair| {
+            let (a, b) = (&pair[0], &pair[1]);
+            if (a.magnitude - level) * (b.magnitude - level) <= 0.0 && a.magnitude != b.magnitude {
+                let x = (level - a.magnitude) / (b.magnitude - a.magnitude);
+                Some(a.freq * (b.freq / a.freq).powf(x))
+            } else {
+                None
+            }
+        }).collect()
+    }
+
+    pub fn write_csv(&self, filename: &str) -> std::io::Result<()> {
+        let mut file = BufWriter::new(File::create(filename)?);
+        writeln!(file, "frequency_hz,magnitude_db,phase_degrees")?;
+        for point in &self.points {
+            writeln!(file, "{},{},{}", point.freq, point.magnitude, point.phase)?;
+        }
+        Ok(())
+    }
+
+    // Magnitude and phase plots, one above the other, against frequency on a
+    // log scale
+    pub fn write_svg(&self, filename: &str, title: &str) -> std::io::Result<()> {
+        const WIDTH: f64 = 800.0;
+        const LEFT: f64 = 60.0;
+        const RIGHT: f64 = 780.0;
+        const MAGNITUDE_TOP: f64 = 40.0;
+        const MAGNITUDE_BOTTOM: f64 = 320.0;
+        const PHASE_TOP: f64 = 370.0;
+        const PHASE_BOTTOM: f64 = 550.0;
+        const HEIGHT: f64 = 590.0;
+
+        let lowest = self.points.first().unwrap().freq.log10();
+        let highest = self.points.last().unwrap().freq.log10();
+        let x = |freq: f64| LEFT + (freq.log10() - lowest) / (highest - lowest) * (RIGHT - LEFT);
+        // Keep deep notches from squashing the rest of the plot
+        let max_db = self.peak().magnitude;
+        let min_db = self.points.iter().map(|p| p.magnitude).fold(f64::INFINITY, f64::min);
+        // Adding 0 turns -0 into 0 for the labels
+        let top_db = (max_db / 10.0).ceil() * 10.0 + 0.0;
+        let bottom_db = ((min_db / 10.0).floor() * 10.0).clamp(top_db - 120.0, top_db - 20.0);
+        let db_step = if top_db - bottom_db > 60.0 { 20.0 } else { 10.0 };
+        let magnitude_y = |db: f64| MAGNITUDE_TOP + (top_db - db.clamp(bottom_db, top_db)) / (top_db - bottom_db) * (MAGNITUDE_BOTTOM - MAGNITUDE_TOP);
+        let phase_y = |degrees: f64| PHASE_TOP + (180.0 - degrees) / 360.0 * (PHASE_BOTTOM - PHASE_TOP);
+
+        let mut file = BufWriter::new(File::create(filename)?);
+        writeln!(file, "<svg xmlns=\"http://www.w3.org/2000/svg\" width=\"{}\" height=\"{}\" font-family=\"sans-serif\" font-size=\"11\">", WIDTH, HEIGHT)?;
+        writeln!(file, "<rect width=\"100%\" height=\"100%\" fill=\"white\"/>")?;
+        writeln!(file, "<text x=\"{}\" y=\"20\" font-size=\"14\">{}</text>", LEFT, title)?;
+        writeln!(file, "<g stroke=\"#ddd\">")?;
+        // Frequency grid lines at 1 to 9 times each power of ten
+        let mut labels = Vec::new();
+        for decade in (lowest.floor() as i32)..=(highest.ceil() as i32) {
+            for multiple in 1..10 {
+                let freq = multiple as f64 * 10.0_f64.powi(decade);
+                if freq.log10() < lowest || freq.log10() > highest {
+                    continue;
+                }
+                for (top, bottom) in [(MAGNITUDE_TOP, MAGNITUDE_BOTTOM), (PHASE_TOP, PHASE_BOTTOM)] {
+                    writeln!(file, "<line x1=\"{0:.1}\" y1=\"{1}\" x2=\"{0:.1}\" y2=\"{2}\"/>", x(freq), top, bottom)?;
+                }
+                if multiple == 1 {
+                    labels.push(freq);
+                }
+            }
+        }
+        let mut db = top_db;
+        while db >= bottom_db {
+            writeln!(file, "<line x1=\"{0}\" y1=\"{1:.1}\" x2=\"{2}\" y2=\"{1:.1}\"/>", LEFT, magnitude_y(db), RIGHT)?;
+            db -= db_step;
+        }
+        for degrees in [-180.0, -90.0, 0.0, 90.0, 180.0] {
+            writeln!(file, "<line x1=\"{0}\" y1=\"{1:.1}\" x2=\"{2}\" y2=\"{1:.1}\"/>", LEFT, phase_y(degrees), RIGHT)?;
+        }
+        writeln!(file, "</g>")?;
+
+        for freq in labels {
+            let label = if freq >= 1000.0 { format!("{}k", freq / 1000.0) } else { format!("{}", freq) };
+            writeln!(file, "<text x=\"{:.1}\" y=\"{}\" text-anchor=\"middle\">{}</text>", x(freq), PHASE_BOTTOM + 15.0, label)?;
+        }
+        let mut db = top_db;
+        while db >= bottom_db {
+            writeln!(file, "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>", LEFT - 5.0, magnitude_y(db) + 4.0, db)?;
+            db -= db_step;
+        }
+        for degrees in [-180.0, -90.0, 0.0, 90.0, 180.0] {
+            writeln!(file, "<text x=\"{}\" y=\"{:.1}\" text-anchor=\"end\">{}</text>", LEFT - 5.0, phase_y(degrees) + 4.0, degrees)?;
+        }
+        writeln!(file, "<text x=\"{}\" y=\"{}\">Magnitude (dB)</text>", LEFT, MAGNITUDE_TOP - 5.0)?;
+        writeln!(file, "<text x=\"{}\" y=\"{}\">Phase (degrees)</text>", LEFT, PHASE_TOP - 5.0)?;
+        writeln!(file, "<text x=\"{}\" y=\"{}\" text-anchor=\"middle\">Frequency (Hz)</text>", (LEFT + RIGHT) / 2.0, PHASE_BOTTOM + 32.0)?;
+
+        let magnitudes: Vec<_> = self.points.iter().map(|p| format!("{:.1},{:.1}", x(p.freq), magnitude_y(p.magnitude))).collect();
+        writeln!(file, "<polyline fill=\"none\" stroke=\"#1f5fa8\" stroke-width=\"1.5\" points=\"{}\"/>", magnitudes.join(" "))?;
+        // Start a new line wherever the phase wraps round
+        let mut phases = vec![Vec::new()];
+        for (i, p) in self.points.iter().enumerate() {
+            if i > 0 && (p.phase - self.points[i - 1].phase).abs() > 180.0 {
+                phases.push(Vec::new());
+            }
+            phases.last_mut().unwrap().push(format!("{:.1},{:.1}", x(p.freq), phase_y(p.phase)));
+        }
+        for phase in phases {
+            writeln!(file, "<polyline fill=\"none\" stroke=\"#a8421f\" stroke-width=\"1.5\" points=\"{}\"/>", phase.join(" "))?;
+        }
+        for (top, bottom) in [(MAGNITUDE_TOP, MAGNITUDE_BOTTOM), (PHASE_TOP, PHASE_BOTTOM)] {
+            writeln!(file, "<rect x=\"{}\" y=\"{}\" width=\"{}\" height=\"{}\" fill=\"none\" stroke=\"black\"/>", LEFT, top, RIGHT - LEFT, bottom - top)?;
+        }
+        writeln!(file, "</svg>")?;
+        Ok(())
+    }
+}
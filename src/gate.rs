@@ -0,0 +1,181 @@
+// A noise gate and downward expander. Below the threshold the sound is turned
+// down, each dB under coming out as ratio dB under, down to at most the range.
+// A ratio of "inf" shuts the gate completely (to the range) as soon as the
+// level drops below the threshold, while a ratio of 2 or so gently pushes the
+// quiet parts down, like noise between notes, without cutting them off.
+//
+// The gain opens at the attack rate and closes at the release rate, but only
+// after the level has been below the threshold for the hold time, so the gate
+// doesn't chatter on a sound that hovers around the threshold.
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::dynamics::{Detection, LevelDetector, db_to_gain, smooth, time_coefficient};
+
+#[derive(Clone)]
+pub struct Gate {
+    input: DynSoundSource,
+    sidechain: Option<DynSoundSource>,
+    threshold: f32, // dB
+    ratio: f32,
+    range: f32, // dB, zero or below
+    attack: f32, // Seconds
+    hold: SampleTime,
+    release: f32, // Seconds
+    detection: Detection,
+    sample_rate: i32,
+}
+
+impl Gate {
+    // A gate that shuts completely, with a 1 ms attack, 50 ms hold, 100 ms
+    // release and peak detection
+    pub fn new(input: DynSoundSource, threshold: f32, sample_rate: i32) -> Self {
+        Gate {
+            input: input,
+            sidechain: None,
+            threshold: threshold,
+            ratio: f32::INFINITY,
+            range: -150.0,
+            attack: 0.001,
+            hold: (0.05 * sample_rate as f32).round() as SampleTime,
+            release: 0.1,
+            detection: Detection::Peak,
+            sample_rate: sample_rate,
+        }
+    }
+
+    // From 1 (no change) up, with inf for a gate
+    pub fn ratio(mut self, ratio: f32) -> Self {
+        self.ratio = ratio.max(1.0);
+        self
+    }
+
+    // The most the sound is turned down by, in dB
+    pub fn range(mut self, range: f32) -> Self {
+        self.range = -range.abs();
+        self
+    }
+
+    pub fn attack(mut self, attack: f32) -> Self {
+        self.attack = attack.max(0.0);
+        self
+    }
+
+    // In samples
+    pub fn hold(mut self, hold: SampleTime) -> Self {
+        self.hold = hold.max(0);
+        self
+    }
+
+    pub fn release(mut self, release: f32) -> Self {
+        self.release = release.max(0.0);
+        self
+    }
+
+    pub fn detection(mut self, detection: Detection) -> Self {
+        self.detection = detection;
+        self
+    }
+
+    // Open the gate on another sound's level rather than the input's
+    pub fn sidechain(mut self, sidechain: DynSoundSource) -> Self {
+        self.sidechain = Some(sidechain);
+        self
+    }
+
+    // The change in dB (zero or below) for a level in dB
+    fn gain_reduction(&self, level: f32) -> f32 {
+        let under = level - self.threshold;
+        if under >= 0.0 {
+            0.0
+        } else {
+            // Written so an infinite ratio gives the range rather than NaN
+            (under * (self.ratio - 1.0)).max(self.range)
+        }
+    }
+
+    fn new_data(&self) -> GateData {
+        GateData {
+            input_data: self.input.init_state(),
+            sidechain_data: self.sidechain.as_ref().map(|sidechain| sidechain.init_state()),
+            next_n: 0,
+            detector: LevelDetector::new(self.detection, self.sample_rate),
+            attack: time_coefficient(self.attack, self.sample_rate),
+            release: time_coefficient(self.release, self.sample_rate),
+            held: 0,
+            gain_reduction: self.range,
+        }
+    }
+}
+
+struct GateData {
+    input_data: SoundData,
+    sidechain_data: Option<SoundData>,
+    next_n: SampleTime,
+    detector: LevelDetector,
+    // Smoothing coefficients
+    attack: f32,
+    release: f32,
+    // Samples since the gain last needed to open
+    held: SampleTime,
+    gain_reduction: f32, // dB
+}
+
+impl SoundSource for Gate {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<GateData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let input = self.input.next_value(n, &mut data.input_data);
+        let detected = match (&self.sidechain, &mut data.sidechain_data) {
+            (Some(sidechain), Some(sidechain_data)) if n < sidechain.duration() => sidechain.next_value(n, sidechain_data),
+            (Some(_), _) => (0.0, 0.0),
+            _ => input,
+        };
+        let target = self.gain_reduction(data.detector.level(detected));
+        if target >= data.gain_reduction {
+            data.held = 0;
+            smooth(&mut data.gain_reduction, target, data.attack);
+        } else if data.held < self.hold {
+            data.held += 1;
+        } else {
+            smooth(&mut data.gain_reduction, target, data.release);
+        }
+        let gain = db_to_gain(data.gain_reduction);
+        (input.0 * gain, input.1 * gain)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration()
+    }
+
+    // params: input, threshold in dB, ratio, range in dB, attack in seconds,
+    // hold in seconds, release in seconds, detection (peak or rms),
+    // [sidechain]
+    // The ratio is inf for a gate or from 1 up for an expander. The range is
+    // the most the sound is turned down by. The sidechain is a sound to open
+    // the gate on instead of the input.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let param = |i: usize| params[i].parse::<f32>().unwrap();
+        let mut gate = Gate::new(input, param(1), reader.sample_rate)
+            .ratio(param(2))
+            .range(param(3))
+            .attack(param(4))
+            .hold(reader.get_duration(&params[5]))
+            .release(param(6))
+            .detection(Detection::from_param(&params[7]));
+        if let Some(sidechain) = params.get(8) {
+            gate = gate.sidechain(reader.get_sound(sidechain));
+        }
+        Box::new(gate)
+    }
+}
@@ -1,7 +1,5 @@
-pub mod gaussian_transfer {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub struct GaussianTransfer {
@@ -31,13 +29,13 @@ impl SoundSource for GaussianTransfer {
         Box::new(GaussianTransferData { source_data: self.source.init_state() })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<GaussianTransferData>().unwrap();
         let val = self.source.next_value(n, &mut data.source_data);
         (eminusxsq(val.0), eminusxsq(val.1))
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.source.duration()
     }
 
@@ -46,5 +44,3 @@ impl SoundSource for GaussianTransfer {
         Box::new(Self::new(source))
     }
 }
-
-}
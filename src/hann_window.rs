@@ -1,7 +1,5 @@
-pub mod hann_window {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub struct HannWindow {
@@ -20,7 +18,7 @@ impl HannWindow {
 
 fn window(x:f32) -> f32 {
     // wrap x to lie between -pi and pi
-    if x > std::f32::consts::PI || x < -std::f32::consts::PI {
+    if !(-std::f32::consts::PI..=std::f32::consts::PI).contains(&x) {
         0.0
     } else {
         (x.cos() + 1.0) / 2.0
@@ -35,13 +33,13 @@ impl SoundSource for HannWindow {
     fn init_state(&self) -> SoundData {
         Box::new(HannWindowData { source_data: self.source.init_state() })
     }
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<HannWindowData>().unwrap();
         let val = self.source.next_value(n, &mut data.source_data);
         (window(val.0), window(val.1))
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.source.duration()
     }
 
@@ -50,5 +48,3 @@ impl SoundSource for HannWindow {
         Box::new(Self::new(source))
     }
 }
-
-}
@@ -1,132 +1,291 @@
-pub mod import_wav {
+// Imports a sound file. WAV files are read with hound, and FLAC, Ogg Vorbis and
+// MP3 files with symphonia. Files with more than two channels are either
+// downmixed to stereo or have the channels to use picked out. A section of the
+// file can be imported by giving a start time and length in seconds.
+//
+// Files at a different sample rate are converted when they are loaded, using
+// the given resampler quality. The sinc qualities (fast, medium and best)
+// don't alias when downsampling, so use one of those for high rate files.
 
 use hound;
 use std::io::BufReader;
 use std::fs::File;
+use std::path::Path;
+use std::sync::Arc;
+use symphonia::core::audio::SampleBuffer;
+use symphonia::core::codecs::DecoderOptions;
+use symphonia::core::errors::Error;
+use symphonia::core::formats::{FormatOptions, FormatReader};
+use symphonia::core::io::MediaSourceStream;
+use symphonia::core::meta::MetadataOptions;
+use symphonia::core::probe::Hint;
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::knob::knob::Knob;
-use crate::ramp::ramp::Ramp;
-use crate::wavetable::wavetable::{Wavetable, Interpolation};
+use crate::resampler::{resample, Quality};
 
 
-#[derive(Clone)]
-pub struct ImportWav {
-    wavetable: Box<Wavetable>,
+// Which channels of a file to use
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Channels {
+    // Mono files go to both sides, stereo files are kept as they are and
+    // surround files are downmixed to stereo
+    Auto,
+    // Everything mixed down to mono
+    Mono,
+    // One channel (counting from 0) on both sides
+    Channel(usize),
+    // Two channels as left and right
+    Pair(usize, usize),
 }
 
-fn do_read_i32(reader: &mut hound::WavReader<BufReader<File>>, channels: u16, bits_per_sample: u16) -> Vec<(f32, f32)> {
-    println!("do_read_i32");
-    let mut samples = Vec::<(f32, f32)>::new();
-    let mut ch = 0;
-    let mut val: (f32, f32) = (0.0, 0.0);
-    for s in reader.samples::<i32>() {
-        if ch == 0 {
-            let sval:i32 = s.unwrap();
-            val.0 = sval as f32 / (1_u32 << (bits_per_sample-1)) as f32;
-            if channels == 1 {
-                val.1 = val.0;
+impl Channels {
+    // auto, mono, a channel number like 2 or a pair like 4,5
+    pub fn from_param(param: &str) -> Self {
+        let parse = |channel: &str| channel.trim().parse::<usize>()
+            .unwrap_or_else(|_| panic!("Channels must be auto, mono, a channel number or two channel numbers like 0,1, not \"{}\"", param));
+        match param.to_lowercase().as_str() {
+            "auto" => Channels::Auto,
+            "mono" => Channels::Mono,
+            _ => match param.split_once(',') {
+                Some((left, right)) => Channels::Pair(parse(left), parse(right)),
+                None => Channels::Channel(parse(param)),
             }
-        } else if ch == 1 {
-            val.1 = s.unwrap() as f32  / (1_u32 << (bits_per_sample-1)) as f32;
-        }
-        ch += 1;
-        if ch >= channels {
-            ch = 0;
-            samples.push(val.clone());
-            val = (0.0, 0.0);
         }
     }
-    println!("Read {} samples", samples.len());
-    samples
 }
 
-fn do_read_f32(reader: &mut hound::WavReader<BufReader<File>>, channels: u16) -> Vec<(f32, f32)> {
-    println!("do_read_f32");
-    let mut samples = Vec::<(f32, f32)>::new();
-    let mut ch = 0;
-    let mut val: (f32, f32) = (0.0, 0.0);
-    for s in reader.samples::<f32>() {
-        if ch == 0 {
-            val.0 = s.unwrap();
-            if channels == 1 {
-                val.1 = val.0;
-            }
-        } else if ch == 1 {
-            val.1 = s.unwrap();
+// How much of each channel goes to the left and right when downmixing. The
+// channels are in the usual WAV order: front left, front right, centre, LFE,
+// back left, back right, side left, side right. The LFE channel is dropped.
+fn downmix_gains(channels: usize) -> Vec<(f32, f32)> {
+    let h = std::f32::consts::FRAC_1_SQRT_2;
+    let gains = match channels {
+        1 => vec![(1.0, 1.0)],
+        2 => vec![(1.0, 0.0), (0.0, 1.0)],
+        3 => vec![(1.0, 0.0), (0.0, 1.0), (h, h)],
+        4 => vec![(1.0, 0.0), (0.0, 1.0), (h, 0.0), (0.0, h)],
+        5 => vec![(1.0, 0.0), (0.0, 1.0), (h, h), (h, 0.0), (0.0, h)],
+        6 => vec![(1.0, 0.0), (0.0, 1.0), (h, h), (0.0, 0.0), (h, 0.0), (0.0, h)],
+        8 => vec![(1.0, 0.0), (0.0, 1.0), (h, h), (0.0, 0.0), (h, 0.0), (0.0, h), (h, 0.0), (0.0, h)],
+        // Anything else alternates left and right
+        _ => (0..channels).map(|ch| if ch % 2 == 0 { (1.0, 0.0) } else { (0.0, 1.0) }).collect(),
+    };
+    // Scale each side so that it can't get louder than its loudest channel
+    let left: f32 = gains.iter().map(|g| g.0).sum();
+    let right: f32 = gains.iter().map(|g| g.1).sum();
+    gains.iter().map(|(l, r)| (l / left, r / right)).collect()
+}
+
+// Pick or mix the selected channels out of interleaved samples
+pub fn select_channels(interleaved: &[f32], channels: usize, selection: Channels) -> Vec<(f32, f32)> {
+    let check = |channel: usize| if channel >= channels {
+        panic!("Channel {} was asked for but the file only has {} channels", channel, channels);
+    };
+    let gains = match selection {
+        Channels::Auto => downmix_gains(channels),
+        Channels::Mono => vec![(1.0 / channels as f32, 1.0 / channels as f32); channels],
+        Channels::Channel(channel) => {
+            check(channel);
+            (0..channels).map(|ch| if ch == channel { (1.0, 1.0) } else { (0.0, 0.0) }).collect()
+        },
+        Channels::Pair(left, right) => {
+            check(left);
+            check(right);
+            (0..channels).map(|ch| (if ch == left { 1.0 } else { 0.0 }, if ch == right { 1.0 } else { 0.0 })).collect()
+        },
+    };
+    interleaved.chunks_exact(channels).map(|frame| {
+        frame.iter().zip(&gains).fold((0.0, 0.0), |(l, r), (x, (gl, gr))| (l + x * gl, r + x * gr))
+    }).collect()
+}
+
+// All channels, interleaved
+fn read_hound(filename: &str) -> (Vec<f32>, usize, i32) {
+    let mut reader: hound::WavReader<BufReader<File>> = hound::WavReader::open(filename).unwrap();
+    let spec = reader.spec();
+    println!("Importing {} {} bit channels, at {} Hz", spec.channels, spec.bits_per_sample, spec.sample_rate);
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().map(|s| s.unwrap()).collect(),
+        hound::SampleFormat::Int => {
+            // hound gives us signed values whatever the bit depth (8 bit WAVs
+            // are stored unsigned)
+            let scale = 1.0 / 2.0_f64.powi(spec.bits_per_sample as i32 - 1);
+            reader.samples::<i32>().map(|s| (s.unwrap() as f64 * scale) as f32).collect()
+        },
+    };
+    (samples, spec.channels as usize, spec.sample_rate as i32)
+}
+
+// The file's container, ready to read packets from
+fn open_symphonia(filename: &str) -> Box<dyn FormatReader> {
+    let file = File::open(filename).unwrap();
+    let stream = MediaSourceStream::new(Box::new(file), Default::default());
+    let mut hint = Hint::new();
+    if let Some(extension) = Path::new(filename).extension().and_then(|ext| ext.to_str()) {
+        hint.with_extension(extension);
+    }
+    let probed = symphonia::default::get_probe()
+        .format(&hint, stream, &FormatOptions::default(), &MetadataOptions::default())
+        .unwrap_or_else(|e| panic!("Couldn't read {}: {}", filename, e));
+    probed.format
+}
+
+// All channels, interleaved
+fn read_symphonia(filename: &str) -> (Vec<f32>, usize, i32) {
+    let mut format = open_symphonia(filename);
+    let track = format.default_track().unwrap_or_else(|| panic!("{} has no audio", filename));
+    let track_id = track.id;
+    let mut decoder = symphonia::default::get_codecs()
+        .make(&track.codec_params, &DecoderOptions::default())
+        .unwrap_or_else(|e| panic!("Couldn't decode {}: {}", filename, e));
+    let mut channels = track.codec_params.channels.map(|channels| channels.count()).unwrap_or(1);
+    let mut sample_rate = track.codec_params.sample_rate.unwrap_or(0) as i32;
+    let mut samples = Vec::new();
+    loop {
+        let packet = match format.next_packet() {
+            Ok(packet) => packet,
+            // The end of the file
+            Err(Error::IoError(e)) if e.kind() == std::io::ErrorKind::UnexpectedEof => break,
+            Err(e) => panic!("Error reading {}: {}", filename, e),
+        };
+        if packet.track_id() != track_id {
+            continue;
+        }
+        match decoder.decode(&packet) {
+            Ok(decoded) => {
+                let spec = *decoded.spec();
+                channels = spec.channels.count();
+                sample_rate = spec.rate as i32;
+                let mut buffer = SampleBuffer::<f32>::new(decoded.capacity() as u64, spec);
+                buffer.copy_interleaved_ref(decoded);
+                samples.extend_from_slice(buffer.samples());
+            },
+            // A damaged packet only loses a little audio
+            Err(Error::DecodeError(e)) => println!("Skipping bad packet in {}: {}", filename, e),
+            Err(e) => panic!("Error decoding {}: {}", filename, e),
         }
-        ch += 1;
-        if ch >= channels {
-            ch = 0;
-            samples.push(val.clone());
-            val = (0.0, 0.0);
+    }
+    println!("Importing {} channels at {} Hz", channels, sample_rate);
+    (samples, channels, sample_rate)
+}
+
+// Whether a name looks like a file we can import rather than a sound name
+pub fn is_sound_file(name: &str) -> bool {
+    let name = name.to_lowercase();
+    [".wav", ".flac", ".ogg", ".mp3"].iter().any(|extension| name.ends_with(extension))
+}
+
+// The number of channels in a sound file, from its header. A file whose
+// header doesn't say has to be decoded to find out.
+pub fn channel_count(filename: &str) -> usize {
+    if filename.to_lowercase().ends_with(".wav") {
+        hound::WavReader::open(filename).unwrap().spec().channels as usize
+    } else {
+        let format = open_symphonia(filename);
+        let track = format.default_track().unwrap_or_else(|| panic!("{} has no audio", filename));
+        match track.codec_params.channels {
+            Some(channels) => channels.count(),
+            None => read_symphonia(filename).1,
         }
     }
+}
+
+// Read a whole sound file as interleaved samples, with its channel count and
+// sample rate
+pub fn read_interleaved(filename: &str) -> (Vec<f32>, usize, i32) {
+    println!("Reading file: {}", filename);
+    if filename.to_lowercase().ends_with(".wav") {
+        read_hound(filename)
+    } else {
+        read_symphonia(filename)
+    }
+}
+
+// Read a whole sound file as stereo samples and its sample rate
+pub fn read_audio(filename: &str, channels: Channels) -> (Vec<(f32, f32)>, i32) {
+    let (interleaved, file_channels, sample_rate) = read_interleaved(filename);
+    let samples = select_channels(&interleaved, file_channels, channels);
     println!("Read {} samples", samples.len());
-    samples
+    (samples, sample_rate)
+}
+
+#[derive(Clone)]
+pub struct ImportWav {
+    samples: Arc<Vec<(f32, f32)>>,
 }
 
 impl ImportWav {
-    pub fn new(filename: &str, sample_rate: i32, interpolation: Interpolation) -> Self {
-        println!("Reading file: {}", filename);
-        let mut reader = hound::WavReader::open(filename).unwrap();
-        let samples: Vec<(f32, f32)>;
-        let spec = reader.spec();
-        println!("Importing {} {} bit channels, at {} Hz", spec.channels, spec.bits_per_sample, spec.sample_rate);
-        match spec.sample_format {
-            hound::SampleFormat::Float => samples = do_read_f32(&mut reader, spec.channels),
-            hound::SampleFormat::Int => samples = do_read_i32(&mut reader, spec.channels, spec.bits_per_sample),
+    pub fn new(filename: &str, sample_rate: i32, quality: Quality) -> Self {
+        Self::with_range(filename, sample_rate, quality, Channels::Auto, 0.0, None)
+    }
+
+    // Import `length` seconds (or the rest of the file) from `start` seconds in
+    pub fn with_range(
+        filename: &str,
+        sample_rate: i32,
+        quality: Quality,
+        channels: Channels,
+        start: f32,
+        length: Option<f32>,
+    ) -> Self {
+        let (samples, file_sample_rate) = read_audio(filename, channels);
+        Self::from_samples(samples, file_sample_rate, sample_rate, quality, start, length)
+    }
+
+    // Samples already read from a file at file_sample_rate, trimmed and
+    // resampled as with_range does
+    pub fn from_samples(
+        mut samples: Vec<(f32, f32)>,
+        file_sample_rate: i32,
+        sample_rate: i32,
+        quality: Quality,
+        start: f32,
+        length: Option<f32>,
+    ) -> Self {
+        let start = ((start * file_sample_rate as f32).round().max(0.0) as usize).min(samples.len());
+        let end = match length {
+            Some(length) => (start + (length * file_sample_rate as f32).round().max(0.0) as usize).min(samples.len()),
+            None => samples.len(),
+        };
+        samples.truncate(end);
+        samples.drain(..start);
+        if file_sample_rate != sample_rate {
+            println!("Resampling from {} Hz to {} Hz", file_sample_rate, sample_rate);
         }
-        println!("Creating wavetable");
-        // We will store the file in a wavetable. Wavetables support reading parts of samples at arbitrary sample rates.
-        // This function keeps it simple and supplies a sweep function that reads the whole table starting at sample 0
-        // and in "real time".
-
-        // This ramp is the sweep function used to convert sample rates.
-        // The period of the ramp is taken from the time it takes to play the file in our sample rate.
-        let period = samples.len() as f32 / spec.sample_rate as f32 * sample_rate as f32;
-        let sweep = Ramp::new(
-            Knob::dc(period),
-            // The amplitude is set to the number of samples in the file so that one ramp will read the whole file.
-            Knob::dc(samples.len() as f32),
-            // The duration is set to one ramp time (same as period)
-            period.round() as i32);
-        let wavetable = Wavetable::from_buffer(samples, Box::new(sweep), interpolation, period.round() as i32);
-        ImportWav { wavetable: Box::new(wavetable) }
+        ImportWav { samples: Arc::new(resample(&samples, file_sample_rate, sample_rate, quality)) }
     }
-}
 
-struct ImportWavData {
-    wavetable_data: SoundData,
+    pub fn samples(&self) -> &[(f32, f32)] {
+        &self.samples
+    }
 }
 
 impl SoundSource for ImportWav {
     fn init_state(&self) -> SoundData {
-        Box::new(ImportWavData { wavetable_data: self.wavetable.init_state() })
+        Box::new(0)
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
-        let data = &mut state.downcast_mut::<ImportWavData>().unwrap();
-        self.wavetable.next_value(n, &mut data.wavetable_data)
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (f32, f32) {
+        if n >= 0 && (n as usize) < self.samples.len() {
+            self.samples[n as usize]
+        } else {
+            (0.0, 0.0)
+        }
     }
 
-    fn duration(&self) -> i32 {
-        self.wavetable.duration()
+    fn duration(&self) -> SampleTime {
+        self.samples.len() as SampleTime
     }
 
+    // params: filename, quality, [channels], [start], [length]
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let filename = &params[0];
-        let interpolation: Interpolation;
-        match params[1].to_lowercase().as_str() {
-            "rounding" => interpolation = Interpolation::Rounding,
-            "linear" => interpolation = Interpolation::Linear,
-            "cubic" => interpolation = Interpolation::Cubic,
-            _ => panic!("Value must be rounding, linear or cubic")
-        }
-        Box::new(ImportWav::new(filename, reader.sample_rate, interpolation))
+        let quality = Quality::from_param(&params[1]);
+        let channels = params.get(2).map(|channels| Channels::from_param(channels)).unwrap_or(Channels::Auto);
+        let start = params.get(3).map(|start| start.parse::<f32>().unwrap()).unwrap_or(0.0);
+        let length = params.get(4).map(|length| length.parse::<f32>().unwrap());
+        Box::new(ImportWav::with_range(filename, reader.sample_rate, quality, channels, start, length))
     }
 }
-
-}
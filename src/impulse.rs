@@ -0,0 +1,49 @@
+// A single sample at the given level followed by silence. Feeding one into a
+// filter gives its impulse response.
+
+use crate::frequency_response::TransferFunction;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+#[derive(Clone)]
+pub struct Impulse {
+    level: f32,
+    duration: SampleTime,
+}
+
+impl Impulse {
+    pub fn new(level: f32, duration: SampleTime) -> Self {
+        Impulse { level: level, duration: duration }
+    }
+}
+
+impl SoundSource for Impulse {
+    fn init_state(&self) -> SoundData {
+        Box::new(0)
+    }
+
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (f32, f32) {
+        if n == 0 {
+            (self.level, self.level)
+        } else {
+            (0.0, 0.0)
+        }
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    // Standing in for a filter's input, the impulse is what goes into the
+    // filter rather than a stage of it
+    fn transfer_function(&self) -> Option<TransferFunction> {
+        Some(TransferFunction::identity())
+    }
+
+    // params: level, duration
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let level = params[0].parse::<f32>().unwrap();
+        let duration = reader.get_duration(&params[1]);
+        Box::new(Impulse::new(level, duration))
+    }
+}
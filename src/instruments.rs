@@ -5,3 +5,4 @@ pub mod triangle_ding;
 pub mod saw_ding;
 pub mod experiment;
 pub mod uphonium;
+pub mod fm_epiano;
@@ -0,0 +1,31 @@
+use crate::traits::{DynSoundSource, Instrument, SampleTime};
+use crate::fm_operator::OperatorFrequency;
+use crate::fm_voice::{FmAlgorithm, FmInstrument, FmOperatorSettings};
+
+
+// A DX7 style electric piano. Algorithm 5 gives three pairs of operators: a
+// warm body, a bell-like tine that dies away quickly and a fixed frequency
+// click for the hammer.
+pub struct FmEPiano {
+    instrument: FmInstrument,
+}
+
+impl FmEPiano {
+    pub fn new(sample_rate: i32) -> Self {
+        let operators = vec![
+            FmOperatorSettings::new(OperatorFrequency::Ratio(1.0), 1.0, 0.002, 1.5, 0.3, 0.4),
+            FmOperatorSettings::new(OperatorFrequency::Ratio(1.0), 1.2, 0.002, 1.0, 0.2, 0.4),
+            FmOperatorSettings::new(OperatorFrequency::Ratio(1.0), 0.6, 0.002, 0.6, 0.0, 0.3),
+            FmOperatorSettings::new(OperatorFrequency::Ratio(14.0), 1.5, 0.001, 0.3, 0.0, 0.3),
+            FmOperatorSettings::new(OperatorFrequency::Ratio(1.0), 0.2, 0.002, 0.1, 0.0, 0.1),
+            FmOperatorSettings::new(OperatorFrequency::Fixed(1800.0 / sample_rate as f32), 2.0, 0.001, 0.02, 0.0, 0.02),
+        ];
+        FmEPiano { instrument: FmInstrument::new(sample_rate, operators, FmAlgorithm::new(6, 5), 0.0) }
+    }
+}
+
+impl Instrument for FmEPiano {
+    fn play(&self, freq: f32, duration: SampleTime, strength: f32) -> DynSoundSource {
+        self.instrument.play(freq, duration, strength)
+    }
+}
@@ -1,13 +1,11 @@
-pub mod saw_ding {
-
-use crate::traits::traits::{DynSoundSource, Instrument} ;
-use crate::knob::knob::Knob;
-use crate::saw::saw::Saw;
-use crate::envelope::envelope::{Envelope, EnvelopePoint};
-use crate::sine::sine::Sine;
-use crate::dc::dc::DC;
-use crate::time_box::time_box::TimeBox;
-use crate::multiply::multiply::Multiply;
+use crate::traits::{DynSoundSource, Instrument, SampleTime};
+use crate::knob::Knob;
+use crate::saw::Saw;
+use crate::envelope::{Envelope, EnvelopePoint};
+use crate::sine::Sine;
+use crate::dc::DC;
+use crate::time_box::{TimeBox, DECLICK_TIME};
+use crate::multiply::Multiply;
 
 pub struct SawDing {
     sample_rate: i32,
@@ -17,13 +15,13 @@ impl SawDing {
     pub fn new(sample_rate: i32) -> Self {
         SawDing { sample_rate: sample_rate }
     }
-    fn t2n(&self, t: f32) -> i32 {
-        (t * self.sample_rate as f32).round() as i32
+    fn t2n(&self, t: f32) -> SampleTime {
+        (t * self.sample_rate as f32).round() as SampleTime
     }
 }
 
 impl Instrument for SawDing {
-    fn play(&self, freq: f32, duration: i32, strength: f32) -> DynSoundSource {
+    fn play(&self, freq: f32, duration: SampleTime, strength: f32) -> DynSoundSource {
         let mut points = Vec::<EnvelopePoint>::new();
         points.push(EnvelopePoint::new( self.t2n(0.005),  1.0 ));
         points.push(EnvelopePoint::new( self.t2n(0.1),  0.5 ));
@@ -40,8 +38,6 @@ impl Instrument for SawDing {
         multiply.add(Box::new(DC::new(strength, duration)), 0.0);
         multiply.add(Box::new(tremolo_wave), 1.0);
 
-        Box::new(TimeBox::new(duration, 88, Box::new(multiply)))
+        Box::new(TimeBox::new(duration, self.t2n(DECLICK_TIME), Box::new(multiply)))
     }
 }
-
-}
\ No newline at end of file
@@ -1,9 +1,7 @@
-pub mod knob {
-
 use num::Complex;
 
-use crate::traits::traits::{DynSoundSource, SoundData, DynComplexSoundSource};
-use crate::dc::dc::{DC, ComplexDC};
+use crate::traits::{DynSoundSource, SoundData, DynComplexSoundSource, SampleTime, FOREVER};
+use crate::dc::{DC, ComplexDC};
 
 #[derive(Clone)]
 pub struct Knob {
@@ -24,10 +22,10 @@ impl Knob {
     }
 
     pub fn dc(value: f32) -> Self {
-        Knob::new(Box::new(DC::new(value, core::i32::MAX)))
+        Knob::new(Box::new(DC::new(value, FOREVER)))
     }
 
-    pub fn next_value(&self, n: i32, state: &mut SoundData) -> f32 {
+    pub fn next_value(&self, n: SampleTime, state: &mut SoundData) -> f32 {
         let data = &mut state.downcast_mut::<KnobData>().unwrap();
         self.input.next_value(n, &mut data.input_data).0
     }
@@ -52,13 +50,17 @@ impl ComplexKnob {
     }
 
     pub fn dc(value: Complex<f32>) -> Self {
-        ComplexKnob::new(Box::new(ComplexDC::new(value, core::i32::MAX)))
+        ComplexKnob::new(Box::new(ComplexDC::new(value, FOREVER)))
     }
 
-    pub fn next_value(&self, n: i32, state: &mut SoundData) -> Complex<f32> {
+    pub fn next_value(&self, n: SampleTime, state: &mut SoundData) -> Complex<f32> {
         let data = &mut state.downcast_mut::<ComplexKnobData>().unwrap();
         self.input.next_value(n, &mut data.input_data).0
     }
-}
 
-}
\ No newline at end of file
+    // The knob's setting at the first sample
+    pub fn initial_value(&self) -> Complex<f32> {
+        let mut state = self.init_state();
+        self.next_value(0, &mut state)
+    }
+}
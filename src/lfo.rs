@@ -0,0 +1,229 @@
+// A low frequency oscillator for modulating other nodes. The rate is in Hz or
+// a note value like 1/4 or 1/8T at the song's tempo. The output is bipolar
+// (-1 to 1), unipolar (0 to 1) or scaled to any range, and the modulation can
+// fade in from the start.
+//
+// Inside a patch every note gets its own LFO, so each note starts at the phase
+// offset. A trigger sound restarts it whenever the trigger goes from zero or
+// below to above zero, for retriggering from a gate.
+
+use std::f64::consts::PI;
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LfoShape {
+    Sine,
+    Triangle,
+    SawUp,
+    SawDown,
+    Square,
+    // A new random level every cycle
+    SampleAndHold,
+    // Glides from one random level to the next every cycle
+    SmoothRandom,
+}
+
+impl LfoShape {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "sine" => LfoShape::Sine,
+            "triangle" | "tri" => LfoShape::Triangle,
+            "saw_up" | "saw" => LfoShape::SawUp,
+            "saw_down" => LfoShape::SawDown,
+            "square" => LfoShape::Square,
+            "sample_and_hold" | "sample_hold" => LfoShape::SampleAndHold,
+            "smooth_random" => LfoShape::SmoothRandom,
+            _ => panic!("LFO shape must be sine, triangle, saw_up, saw_down, square, sample_and_hold or smooth_random, not \"{}\"", param)
+        }
+    }
+}
+
+#[derive(Clone)]
+pub struct Lfo {
+    shape: LfoShape,
+    freq: Knob, // Cycles per sample
+    // The output runs from min to max. Fading in starts at rest.
+    min: f32,
+    max: f32,
+    rest: f32,
+    phase: f64, // Fraction of a cycle
+    fade_in: SampleTime,
+    trigger: Option<DynSoundSource>,
+    seed: u64,
+    duration: SampleTime,
+}
+
+impl Lfo {
+    // A bipolar LFO starting at phase 0 with no fade in
+    pub fn new(shape: LfoShape, freq: Knob, seed: u64, duration: SampleTime) -> Self {
+        Lfo {
+            shape: shape,
+            freq: freq,
+            min: -1.0,
+            max: 1.0,
+            rest: 0.0,
+            phase: 0.0,
+            fade_in: 0,
+            trigger: None,
+            seed: seed,
+            duration: duration,
+        }
+    }
+
+    // Run from 0 to 1 rather than -1 to 1
+    pub fn unipolar(self) -> Self {
+        self.range(0.0, 1.0)
+    }
+
+    // Run from min to max, fading in from min
+    pub fn range(mut self, min: f32, max: f32) -> Self {
+        self.min = min;
+        self.max = max;
+        self.rest = min;
+        self
+    }
+
+    // Start this fraction of a cycle in
+    pub fn phase(mut self, phase: f64) -> Self {
+        self.phase = phase.rem_euclid(1.0);
+        self
+    }
+
+    pub fn fade_in(mut self, fade_in: SampleTime) -> Self {
+        self.fade_in = fade_in;
+        self
+    }
+
+    pub fn trigger(mut self, trigger: DynSoundSource) -> Self {
+        self.trigger = Some(trigger);
+        self
+    }
+
+    // The shape from -1 to 1 at a point in the cycle
+    fn bipolar_value(&self, phase: f64, data: &LfoData) -> f64 {
+        match self.shape {
+            LfoShape::Sine => (2.0 * PI * phase).sin(),
+            LfoShape::Triangle => if phase < 0.25 {
+                4.0 * phase
+            } else if phase < 0.75 {
+                2.0 - 4.0 * phase
+            } else {
+                4.0 * phase - 4.0
+            },
+            LfoShape::SawUp => 2.0 * phase - 1.0,
+            LfoShape::SawDown => 1.0 - 2.0 * phase,
+            LfoShape::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            LfoShape::SampleAndHold => data.next_level,
+            LfoShape::SmoothRandom => {
+                let x = 0.5 - 0.5 * (PI * phase).cos();
+                data.level + (data.next_level - data.level) * x
+            },
+        }
+    }
+
+    fn restart(&self, n: SampleTime, data: &mut LfoData) {
+        data.phase = self.phase;
+        data.start = n;
+        data.level = data.next_level;
+        data.next_level = data.rng.gen_range(-1.0..=1.0);
+    }
+}
+
+struct LfoData {
+    freq_data: SoundData,
+    trigger_data: Option<SoundData>,
+    rng: StdRng,
+    phase: f64,
+    // When the LFO last started, for the fade in
+    start: SampleTime,
+    // Random levels for this cycle and the next
+    level: f64,
+    next_level: f64,
+    triggered: bool,
+}
+
+impl SoundSource for Lfo {
+    fn init_state(&self) -> SoundData {
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let level = rng.gen_range(-1.0..=1.0);
+        let next_level = rng.gen_range(-1.0..=1.0);
+        Box::new(LfoData {
+            freq_data: self.freq.init_state(),
+            trigger_data: self.trigger.as_ref().map(|trigger| trigger.init_state()),
+            rng: rng,
+            phase: self.phase,
+            start: 0,
+            level: level,
+            next_level: next_level,
+            triggered: false,
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<LfoData>().unwrap();
+        if let (Some(trigger), Some(trigger_data)) = (&self.trigger, &mut data.trigger_data) {
+            let triggered = trigger.next_value(n, trigger_data).0 > 0.0;
+            if triggered && !data.triggered {
+                self.restart(n, data);
+            }
+            data.triggered = triggered;
+        }
+        let freq = self.freq.next_value(n, &mut data.freq_data) as f64;
+        let value = (self.bipolar_value(data.phase, data) + 1.0) as f32 / 2.0;
+        let mut res = self.min + (self.max - self.min) * value;
+        if n - data.start < self.fade_in {
+            res = self.rest + (res - self.rest) * (n - data.start) as f32 / self.fade_in as f32;
+        }
+        data.phase += freq;
+        // A negative rate runs the cycle backwards, so the random levels move
+        // the other way to keep the smooth random shape joined up
+        if data.phase >= 1.0 {
+            data.level = data.next_level;
+            data.next_level = data.rng.gen_range(-1.0..=1.0);
+        } else if data.phase < 0.0 {
+            data.next_level = data.level;
+            data.level = data.rng.gen_range(-1.0..=1.0);
+        }
+        data.phase = data.phase.rem_euclid(1.0);
+        (res, res)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    // params: shape, rate, range, phase, fade in, trigger, duration
+    // The rate is in Hz, a note value like 1/4, 1/8T (triplet) or 1/8.
+    // (dotted), or a sound giving cycles per sample. The range is bipolar,
+    // unipolar or "min max". The phase is a fraction of a cycle and the fade
+    // in is in seconds. The trigger is a sound name or "none".
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let shape = LfoShape::from_param(&params[0]);
+        let sample_rate = reader.sample_rate as f32;
+        // Plain numbers are Hz rather than midi notes
+        let freq = reader.get_rate_knob(&params[1], 1.0 / sample_rate);
+        let phase = params[3].parse::<f64>().unwrap();
+        let fade_in = reader.get_duration(&params[4]);
+        let duration = reader.get_duration(&params[6]);
+        let mut lfo = Lfo::new(shape, freq, reader.next_seed(), duration).phase(phase).fade_in(fade_in);
+        let range: Vec<_> = params[2].split_whitespace().collect();
+        lfo = match range[..] {
+            ["bipolar"] => lfo,
+            ["unipolar"] => lfo.unipolar(),
+            [min, max] => lfo.range(min.parse::<f32>().unwrap(), max.parse::<f32>().unwrap()),
+            _ => panic!("LFO range must be bipolar, unipolar or \"min max\", not \"{}\"", params[2]),
+        };
+        if params[5] != "none" {
+            lfo = lfo.trigger(reader.get_sound(&params[5]));
+        }
+        Box::new(lfo)
+    }
+}
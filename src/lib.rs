@@ -0,0 +1,108 @@
+//! Mattmusic - a code driven synthesiser
+//!
+//! Every sound is a `SoundSource`: a node that is asked for one stereo sample
+//! at a time and keeps whatever it needs between samples in a separate state
+//! object created by `init_state()`. Nodes take other nodes as inputs, so a
+//! song is just a tree of sources. Trees can be read from YAML files with
+//! `read_song`, put together by hand from the node modules, or built with the
+//! chaining API in `builder`:
+//!
+//! ```no_run
+//! use mattmusic::builder::Builder;
+//!
+//! let b = Builder::new(48000);
+//! let env = b.envelope(&[(0.01, 1.0), (0.5, 0.3), (1.0, 0.0)]);
+//! let note = b.sine(440.0, 1.51).mul(env).lowpass(1000.0);
+//! note.export_wav("note.wav").unwrap();
+//! ```
+
+// The code base prefers explicit `field: field` initialisers and late
+// initialised `let` bindings, so don't let clippy fight that style.
+#![allow(clippy::redundant_field_names)]
+#![allow(clippy::needless_late_init)]
+#![allow(clippy::vec_init_then_push)]
+#![allow(clippy::ptr_arg)]
+#![allow(clippy::new_without_default)]
+
+pub mod band_limited_random;
+pub mod blep_pulse;
+pub mod blep_saw;
+pub mod blep_square;
+pub mod blep_triangle;
+pub mod buffer_reader;
+pub mod buffer_writer;
+pub mod builder;
+pub mod cauchy_transfer;
+pub mod chorus;
+pub mod clip;
+pub mod coloured_noise;
+pub mod compressor;
+pub mod convolve;
+pub mod cos_transfer;
+pub mod db2amp;
+pub mod dc;
+pub mod delay_buffer;
+pub mod delay_line;
+pub mod dynamics;
+pub mod envelope;
+pub mod export_wav;
+pub mod fdn_reverb;
+pub mod flanger;
+pub mod fm_operator;
+pub mod fm_voice;
+pub mod frequency_response;
+pub mod gate;
+pub mod gaussian_transfer;
+pub mod generative_waveform;
+pub mod hann_window;
+pub mod import_wav;
+pub mod impulse;
+pub mod knob;
+pub mod lfo;
+pub mod limiter;
+pub mod markov_chain;
+pub mod midi_notes;
+pub mod midi2freq;
+pub mod mix;
+pub mod multiply;
+pub mod noise;
+pub mod note_player;
+pub mod oscillator;
+pub mod pitch_shift;
+pub mod poly_blep;
+pub mod pre_render;
+pub mod pulse_train;
+pub mod ramp;
+pub mod random_walk;
+pub mod read_song;
+pub mod recirculating_delay;
+pub mod render;
+pub mod resampler;
+pub mod reverberator;
+pub mod rotation_transfer;
+pub mod sampler;
+pub mod saw;
+pub mod sequence;
+pub mod sf2;
+pub mod sfz;
+pub mod sine;
+pub mod square;
+pub mod stereo_delay;
+pub mod time_box;
+pub mod traits;
+pub mod triangle;
+pub mod uneven_delay;
+pub mod varispeed;
+pub mod velvet_noise;
+pub mod vibrato;
+pub mod wavetable;
+pub mod wavetable_oscillator;
+
+pub mod filters;
+pub mod instruments;
+pub mod songs;
+
+pub use builder::{Builder, Param, Sound};
+pub use read_song::read_song;
+pub use render::{render, render_to_wav, write_wav};
+pub use traits::{SoundSource, DynSoundSource, SoundData, Instrument, DynInstrument};
@@ -0,0 +1,141 @@
+// A brickwall limiter for the end of a song: no sample comes out louder than
+// the ceiling. The sound is delayed by the look ahead time so the gain can
+// start coming down before a peak arrives, and gets there in a smooth ramp
+// rather than clipping the peak.
+//
+// For each sample the gain that would keep it under the ceiling is worked
+// out, then the lowest over the look ahead window is taken so the gain is
+// already down when the peak comes out of the delay. That lets the gain back
+// up at the release rate, and a moving average over the window turns the
+// steps down into ramps. Every value in the average is at most the gain the
+// peak needs, so the average is too.
+
+use std::collections::VecDeque;
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::delay_buffer::DelayBuffer;
+use crate::dynamics::{db_to_gain, smooth, time_coefficient};
+
+#[derive(Clone)]
+pub struct Limiter {
+    input: DynSoundSource,
+    ceiling: f32, // Gain
+    look_ahead: usize, // Samples
+    release: f32, // Seconds
+    sample_rate: i32,
+}
+
+impl Limiter {
+    // Ceiling in dB relative to full scale, with a 5 ms look ahead and a
+    // 100 ms release
+    pub fn new(input: DynSoundSource, ceiling: f32, sample_rate: i32) -> Self {
+        Limiter {
+            input: input,
+            ceiling: db_to_gain(ceiling),
+            look_ahead: (0.005 * sample_rate as f32).round() as usize,
+            release: 0.1,
+            sample_rate: sample_rate,
+        }
+    }
+
+    // In samples
+    pub fn look_ahead(mut self, look_ahead: usize) -> Self {
+        self.look_ahead = look_ahead.max(1);
+        self
+    }
+
+    pub fn release(mut self, release: f32) -> Self {
+        self.release = release.max(0.0);
+        self
+    }
+
+    fn new_data(&self) -> LimiterData {
+        LimiterData {
+            input_data: self.input.init_state(),
+            next_n: 0,
+            delays: [DelayBuffer::new(self.look_ahead), DelayBuffer::new(self.look_ahead)],
+            minimum: VecDeque::new(),
+            release: time_coefficient(self.release, self.sample_rate),
+            released: 1.0,
+            window: VecDeque::from(vec![1.0; self.look_ahead + 1]),
+            sum: (self.look_ahead + 1) as f64,
+        }
+    }
+}
+
+struct LimiterData {
+    input_data: SoundData,
+    next_n: SampleTime,
+    delays: [DelayBuffer; 2],
+    // The gains that could still be the lowest in the window, with when
+    // they're from. Each is lower than the one before.
+    minimum: VecDeque<(SampleTime, f32)>,
+    release: f32, // Smoothing coefficient
+    released: f32,
+    // The last look ahead + 1 released gains and their sum
+    window: VecDeque<f32>,
+    sum: f64,
+}
+
+impl SoundSource for Limiter {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<LimiterData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let input = if n < self.input.duration() { self.input.next_value(n, &mut data.input_data) } else { (0.0, 0.0) };
+        let peak = input.0.abs().max(input.1.abs());
+        let needed = if peak > self.ceiling { self.ceiling / peak } else { 1.0 };
+
+        while data.minimum.back().is_some_and(|(_, gain)| *gain >= needed) {
+            data.minimum.pop_back();
+        }
+        data.minimum.push_back((n, needed));
+        while data.minimum.front().is_some_and(|(m, _)| *m < n - self.look_ahead as SampleTime) {
+            data.minimum.pop_front();
+        }
+        let lowest = data.minimum.front().unwrap().1;
+        if lowest < data.released {
+            data.released = lowest;
+        } else {
+            smooth(&mut data.released, lowest, data.release);
+        }
+
+        data.window.push_back(data.released);
+        data.sum += data.released as f64 - data.window.pop_front().unwrap() as f64;
+        let gain = (data.sum / data.window.len() as f64) as f32;
+
+        let output = (data.delays[0].tap(self.look_ahead), data.delays[1].tap(self.look_ahead));
+        data.delays[0].push(input.0);
+        data.delays[1].push(input.1);
+        // The sum drifts a little from rounding, so make sure
+        (
+            (output.0 * gain).clamp(-self.ceiling, self.ceiling),
+            (output.1 * gain).clamp(-self.ceiling, self.ceiling),
+        )
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(self.look_ahead as SampleTime)
+    }
+
+    // params: input, ceiling in dB, look ahead in seconds, release in seconds
+    // The ceiling is relative to full scale, so -0.3 keeps the peaks just
+    // under 1.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let param = |i: usize| params[i].parse::<f32>().unwrap();
+        let look_ahead = (param(2) * reader.sample_rate as f32).round() as usize;
+        Box::new(Limiter::new(input, param(1), reader.sample_rate)
+            .look_ahead(look_ahead)
+            .release(param(3)))
+    }
+}
@@ -1,90 +1,60 @@
-use anyhow;
+#![allow(clippy::needless_late_init)]
+
 use std::sync::{Arc, Mutex, Condvar};
+use std::sync::mpsc::sync_channel;
+use std::thread;
 use clap::{Parser, ValueEnum};
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use cpal::{FromSample, Sample, SampleFormat, SizedSample};
 
-mod buffer_reader;
-mod buffer_writer;
-mod cauchy_transfer;
-mod clip;
-mod cos_transfer;
-mod db2amp;
-mod dc;
-mod delay_line;
-mod envelope;
-mod export_wav;
-mod gaussian_transfer;
-mod generative_waveform;
-mod hann_window;
-mod import_wav;
-mod knob;
-mod midi_notes;
-mod midi2freq;
-mod mix;
-mod multiply;
-mod noise;
-mod oscillator;
-mod pitch_shift;
-mod pre_render;
-mod pulse_train;
-mod ramp;
-mod read_song;
-mod recirculating_delay;
-mod reverberator;
-mod rotation_transfer;
-mod saw;
-mod sequence;
-mod sine;
-mod square;
-mod time_box;
-mod traits;
-mod triangle;
-mod uneven_delay;
-mod wavetable;
-
-mod filters;
-mod instruments;
-mod songs;
-
-
-use traits::traits::{DynSoundSource, DynInstrument};
-use read_song::read_song::read_song;
-use wavetable::wavetable::Interpolation;
-use import_wav::import_wav::ImportWav;
+use mattmusic::{instruments, songs};
+use mattmusic::traits::{DynSoundSource, DynInstrument, SampleTime, is_forever};
+use mattmusic::read_song::read_song;
+use mattmusic::sf2::read_sf2;
+use mattmusic::sfz::read_sfz;
+use mattmusic::resampler::Quality;
+use mattmusic::import_wav::ImportWav;
 
 // todo make command line args select the song to play
-fn get_song(songname: &Option<Song>, instrument_name: &Option<InstrumentName>, sample_rate: i32) -> DynSoundSource {
+fn get_song(args: &Args, sample_rate: i32, seed: u64) -> DynSoundSource {
     let instrument: DynInstrument;
-    match instrument_name {
-    Some(InstrumentName::Vibraphone) => {
-        instrument = Box::new(instruments::vibraphone::vibraphone::Vibraphone::new(sample_rate)); }
-    Some(InstrumentName::Kick) => {
-        instrument = Box::new(instruments::kick::kick::Kick::new(sample_rate)); }
-    Some(InstrumentName::SquareDing) => {
-        instrument = Box::new(instruments::square_ding::square_ding::SquareDing::new(sample_rate)); }
-    Some(InstrumentName::TriangleDing) => {
-        instrument = Box::new(instruments::triangle_ding::triangle_ding::TriangleDing::new(sample_rate)); }
-    Some(InstrumentName::SawDing) => {
-        instrument = Box::new(instruments::saw_ding::saw_ding::SawDing::new(sample_rate)); }
-    Some(InstrumentName::Experiment) => {
-        instrument = Box::new(instruments::experiment::experiment::Experiment::new(sample_rate)); }
-    Some(InstrumentName::Uphonium) => {
-        instrument = Box::new(instruments::uphonium::uphonium::Uphonium::new(sample_rate)); },
-    &None => todo!()
+    if let Some(filename) = &args.sfz {
+        instrument = Box::new(read_sfz(filename, sample_rate));
+    } else if let Some(filename) = &args.sf2 {
+        instrument = Box::new(read_sf2(filename, &args.preset, sample_rate));
+    } else {
+        match args.instrument {
+        Some(InstrumentName::Vibraphone) => {
+            instrument = Box::new(instruments::vibraphone::Vibraphone::new(sample_rate)); }
+        Some(InstrumentName::Kick) => {
+            instrument = Box::new(instruments::kick::Kick::new(sample_rate, seed)); }
+        Some(InstrumentName::SquareDing) => {
+            instrument = Box::new(instruments::square_ding::SquareDing::new(sample_rate)); }
+        Some(InstrumentName::TriangleDing) => {
+            instrument = Box::new(instruments::triangle_ding::TriangleDing::new(sample_rate)); }
+        Some(InstrumentName::SawDing) => {
+            instrument = Box::new(instruments::saw_ding::SawDing::new(sample_rate)); }
+        Some(InstrumentName::Experiment) => {
+            instrument = Box::new(instruments::experiment::Experiment::new(sample_rate)); }
+        Some(InstrumentName::Uphonium) => {
+            instrument = Box::new(instruments::uphonium::Uphonium::new(sample_rate)); },
+        Some(InstrumentName::FmEPiano) => {
+            instrument = Box::new(instruments::fm_epiano::FmEPiano::new(sample_rate)); },
+        None => todo!()
+        }
     }
-    match songname {
+    match args.song {
     Some(Song::Arpeggios) => {
-        songs::arpeggios::arpeggios::arpeggios(sample_rate, instrument) }
+        songs::arpeggios::arpeggios(sample_rate, instrument) }
     Some(Song::LongNote) => {
-        songs::long_note::long_note::long_note(sample_rate, instrument) }
+        songs::long_note::long_note(sample_rate, instrument) }
     Some(Song::Beats) => {
-        songs::beats::beats::beats(sample_rate, instrument) }
+        songs::beats::beats(sample_rate, instrument) }
     Some(Song::TwoNotes) => {
-        songs::two_notes::two_notes::two_notes(sample_rate, instrument) }
+        songs::two_notes::two_notes(sample_rate, instrument) }
     Some(Song::ManyNotes) => {
-        songs::many_notes::many_notes::many_notes(sample_rate, instrument) },
-    &None => todo!()
+        songs::many_notes::many_notes(sample_rate, instrument) },
+    None => todo!()
     }
 }
 
@@ -105,7 +75,8 @@ enum InstrumentName {
     TriangleDing,
     SawDing,
     Experiment,
-    Uphonium
+    Uphonium,
+    FmEPiano
 }
 
 
@@ -114,10 +85,19 @@ enum InstrumentName {
 #[command(author, version, about, long_about = None)]
 struct Args {
     /// Play using built-in instrument
-    #[arg(value_enum, short, long, requires="song")]
+    #[arg(value_enum, short, long, requires="song", group="voice")]
     instrument: Option<InstrumentName>,
+    /// Play using an SFZ sample instrument
+    #[arg(long, requires="song", group="voice")]
+    sfz: Option<String>,
+    /// Play using a preset from an SF2 soundfont
+    #[arg(long, requires="song", group="voice")]
+    sf2: Option<String>,
+    /// SF2 preset as bank:program or just program
+    #[arg(long, default_value="0:0", requires="sf2")]
+    preset: String,
     /// Play built-in song
-    #[arg(value_enum, short, long, requires="instrument")]
+    #[arg(value_enum, short, long, requires="voice")]
     song: Option<Song>,
     /// Read song and instrument from file (will ignore --song and --instrument)
     #[arg(short, long)]
@@ -125,6 +105,12 @@ struct Args {
     /// Play a wav file
     #[arg(short, long)]
     wavfile: Option<String>,
+    /// Seed for random sources such as noise (overrides any seed in the song file)
+    #[arg(long)]
+    seed: Option<u64>,
+    /// Start the song again from the beginning each time it ends
+    #[arg(long = "loop")]
+    loop_song: bool,
 }
 
 fn main() -> anyhow::Result<()> {
@@ -148,27 +134,58 @@ where
     println!("Output sample rate is {} Hz", sample_rate);
     let song;
     if let Some(filename) = &args.file {
-        song = read_song(&filename, sample_rate);
+        song = read_song(filename, sample_rate, args.seed);
     } else if let Some(filename) = &args.wavfile {
-        song = Box::new(ImportWav::new(filename, sample_rate, Interpolation::Cubic));
+        song = Box::new(ImportWav::new(filename, sample_rate, Quality::Best));
     } else {
-        song = get_song(&args.song, &args.instrument, sample_rate);
+        song = get_song(args, sample_rate, args.seed.unwrap_or(0));
     }
     let mut song_state = song.init_state();
     let channels = config.channels as usize;
-    let mut sample_clock = 0i32;
+    let duration = song.duration();
+    let mut sample_clock: SampleTime = 0;
+    // Setting up a song's state allocates its buffers, which is too slow for
+    // the audio thread. So when looping, a helper thread keeps the state for
+    // the next pass ready and frees the state of the pass before.
+    let passes = if args.loop_song && duration > 0 && !is_forever(duration) {
+        let (ready_tx, ready_rx) = sync_channel(1);
+        let (used_tx, used_rx) = sync_channel(1);
+        let song = song.clone();
+        thread::spawn(move || {
+            while ready_tx.send(song.init_state()).is_ok() {
+                match used_rx.recv() {
+                    Ok(used) => drop(used),
+                    Err(_) => break,
+                }
+            }
+        });
+        Some((ready_rx, used_tx))
+    } else {
+        None
+    };
     let pair = Arc::new((Mutex::new(false), Condvar::new()));
     let pair2 = Arc::clone(&pair);
     let mut next_value = move || -> (f32, f32) {
         let (lock, cvar) = &*pair2;
-        sample_clock = sample_clock + 1;
-        if sample_clock > song.duration() {
+        if let (true, Some((ready_rx, used_tx))) = (sample_clock >= duration, &passes) {
+            // Go straight back to the start with fresh state so the first
+            // sample of the next pass follows the last sample of this one.
+            // If the helper hasn't finished, which only a very short song
+            // could cause, make the state here.
+            sample_clock = 0;
+            let next_state = ready_rx.try_recv().unwrap_or_else(|_| song.init_state());
+            let used = std::mem::replace(&mut song_state, next_state);
+            let _ = used_tx.try_send(used);
+        }
+        if sample_clock >= duration {
             let mut done = lock.lock().unwrap();
             *done = true;
             cvar.notify_one();
             (0.0, 0.0)
         } else {
-            song.next_value(sample_clock, &mut song_state)
+            let val = song.next_value(sample_clock, &mut song_state);
+            sample_clock += 1;
+            val
         }
     };
 
@@ -196,7 +213,13 @@ where
         }
     }
 
-    println!("Playing");
+    if is_forever(duration) {
+        println!("Playing until interrupted");
+    } else if args.loop_song {
+        println!("Playing in a loop until interrupted");
+    } else {
+        println!("Playing");
+    }
     stream.play()?;
 
     let (lock, cvar) = &*pair;
@@ -0,0 +1,120 @@
+// A Markov chain over short note patterns. Each state plays its pattern, one
+// note per step, then moves on to one of its next states at random, weighted
+// by the given weights. The chain starts in the first state.
+//
+// States are written as "name: notes -> next weight, next weight", e.g.
+//   "verse: C4 E4 G4 r -> verse 3, chorus 1"
+// where notes are names or midi note numbers and "r" is a rest. A state with
+// no next states goes back to the first one.
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{DynSoundSource, SoundData};
+
+use crate::note_player::{NoteChooser, NotePlayer, parse_midi_note};
+
+#[derive(Clone)]
+pub struct MarkovState {
+    pattern: Vec<Option<u8>>,
+    // Index of each next state and its weight
+    next: Vec<(usize, f32)>,
+}
+
+impl MarkovState {
+    pub fn new(pattern: Vec<Option<u8>>, next: Vec<(usize, f32)>) -> Self {
+        MarkovState { pattern: pattern, next: next }
+    }
+}
+
+#[derive(Clone)]
+pub struct MarkovChain {
+    states: Vec<MarkovState>,
+}
+
+impl MarkovChain {
+    pub fn new(states: Vec<MarkovState>) -> Self {
+        if states.is_empty() || states.iter().any(|state| state.pattern.is_empty()) {
+            panic!("A Markov chain needs at least one state and every state needs at least one note");
+        }
+        MarkovChain { states: states }
+    }
+
+    fn choose_next(&self, state: usize, rng: &mut StdRng) -> usize {
+        let next = &self.states[state].next;
+        let total: f32 = next.iter().map(|(_, weight)| weight).sum();
+        if total <= 0.0 {
+            return 0;
+        }
+        let mut choice = rng.gen_range(0.0..total);
+        for (idx, weight) in next {
+            if choice < *weight {
+                return *idx;
+            }
+            choice -= weight;
+        }
+        next[next.len() - 1].0
+    }
+
+    // params: patch, step time, note length, duration, then one param per state
+    pub fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let patch = &params[0];
+        let step = reader.get_duration(&params[1]);
+        let note_length = &params[2];
+        let duration = reader.get_duration(&params[3]);
+        let definitions: Vec<_> = params[4..].iter().map(|param| {
+            let (name, rest) = param.split_once(':')
+                .unwrap_or_else(|| panic!("Markov state should look like \"name: notes -> next weight\", not \"{}\"", param));
+            let (notes, next) = rest.split_once("->").unwrap_or((rest, ""));
+            (name.trim(), notes, next)
+        }).collect();
+        let index = |name: &str| definitions.iter().position(|(state_name, _, _)| *state_name == name)
+            .unwrap_or_else(|| panic!("Couldn't find Markov state \"{}\"", name));
+        let mut states = Vec::<MarkovState>::new();
+        for (_, notes, next) in &definitions {
+            let pattern = notes.split_whitespace()
+                .map(|note| if note == "r" { None } else { Some(parse_midi_note(note, reader)) })
+                .collect();
+            let next = next.split(',')
+                .filter(|transition| !transition.trim().is_empty())
+                .map(|transition| {
+                    let parts: Vec<_> = transition.split_whitespace().collect();
+                    let weight = parts.get(1).map(|weight| weight.parse::<f32>().unwrap()).unwrap_or(1.0);
+                    (index(parts[0]), weight)
+                })
+                .collect();
+            states.push(MarkovState::new(pattern, next));
+        }
+        let chain = MarkovChain::new(states);
+        Box::new(NotePlayer::from_patch(Box::new(chain), patch, note_length, step, duration, reader))
+    }
+}
+
+struct MarkovChainData {
+    state: usize,
+    position: usize,
+}
+
+impl NoteChooser for MarkovChain {
+    fn init_state(&self) -> SoundData {
+        Box::new(MarkovChainData { state: 0, position: 0 })
+    }
+
+    fn next_note(&self, state: &mut SoundData, rng: &mut StdRng) -> Option<u8> {
+        let data = state.downcast_mut::<MarkovChainData>().unwrap();
+        if data.position >= self.states[data.state].pattern.len() {
+            data.state = self.choose_next(data.state, rng);
+            data.position = 0;
+        }
+        let note = self.states[data.state].pattern[data.position];
+        data.position += 1;
+        note
+    }
+
+    fn notes(&self) -> Vec<u8> {
+        let mut notes: Vec<u8> = self.states.iter().flat_map(|state| state.pattern.iter().flatten().copied()).collect();
+        notes.sort();
+        notes.dedup();
+        notes
+    }
+}
@@ -1,16 +1,15 @@
-pub mod midi2freq {
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-use crate::midi_notes::midi_notes::{midistr2freq, midi2freq};
-use crate::read_song::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::midi_notes::{midistr2freq, midi2freq};
+use crate::read_song::SongReader;
 
 #[derive(Clone)]
 pub struct Midi2Freq {
     freq: f32,
-    duration: i32
+    duration: SampleTime
 }
 
 impl Midi2Freq {
-    pub fn new(freq: f32, duration: i32) -> Self {
+    pub fn new(freq: f32, duration: SampleTime) -> Self {
         Midi2Freq { freq: freq, duration: duration }
     }
 }
@@ -20,7 +19,7 @@ impl SoundSource for Midi2Freq {
         Box::new(0)
     }
 
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (f32, f32) {
         if n < self.duration {
             (self.freq, self.freq)
         } else {
@@ -28,7 +27,7 @@ impl SoundSource for Midi2Freq {
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
@@ -41,10 +40,7 @@ impl SoundSource for Midi2Freq {
         } else {
             value = midi2freq(params[0].parse::<i8>().unwrap()) / reader.sample_rate as f32;
         }
-        let duration = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Self::new(value, duration.round() as i32))
+        let duration = reader.get_duration(&params[1]);
+        Box::new(Self::new(value, duration))
     }
 }
-
-
-}
\ No newline at end of file
@@ -1,6 +1,4 @@
-pub mod midi_notes {
-
-const MIDI_NOTES : [(u8, &'static str, u8, f32, f32); 99] = [
+const MIDI_NOTES : [(u8, &str, u8, f32, f32); 99] = [
     (21,"A",0,27.5,27.00),
     (22,"A#",0,29.1353,28.61),
     (23,"B",0,30.8677,30.31),
@@ -138,6 +136,11 @@ pub fn midi2freq(midi: i8) -> f32 {
     440.0 * 2.0_f32.powf((midi - 69) as f32 / 12.0)
 }
 
+// The (fractional) midi note number of a frequency in Hz
+pub fn freq2midi(freq: f32) -> f32 {
+    69.0 + 12.0 * (freq / 440.0).log2()
+}
+
 pub fn midistr2freq(midi: &str) -> f32 {
     if midi.len() > 3 {
         panic!("Couldn't parse \"{}\" as a midi note", midi);
@@ -165,5 +168,3 @@ pub fn midistr2freq(midi: &str) -> f32 {
         _ => todo!()
     }
 }
-
-}
\ No newline at end of file
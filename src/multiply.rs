@@ -1,8 +1,6 @@
-pub mod multiply {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-use crate::dc::dc::DC;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+use crate::dc::DC;
 
 #[derive(Clone)]
 pub struct MultiplyInput {
@@ -39,21 +37,19 @@ impl SoundSource for Multiply {
         Box::new(data)
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<MultiplyState>().unwrap();
         let mut res1: f32 = 1.0;
         let mut res2: f32 = 1.0;
-        let mut idx = 0;
-        for minput in &self.inputs {
+        for (idx, minput) in self.inputs.iter().enumerate() {
             let (v1, v2) = minput.source.next_value(n, &mut data.inputs[idx]);
             res1 *= v1 + minput.offset;
             res2 *= v2 + minput.offset;
-            idx += 1;
         }
         (res1, res2)
     }
-    fn duration(&self) -> i32 {
-        let mut duration: i32 = 0;
+    fn duration(&self) -> SampleTime {
+        let mut duration: SampleTime = 0;
         for minput in self.inputs.iter() {
             duration = duration.max(minput.source.duration());
         }
@@ -70,27 +66,25 @@ impl SoundSource for Multiply {
             // Otherwise we expect to see a dc offset and a source name
             if parts[0] == "dc" {
                 let val = parts[1].parse::<f32>().unwrap();
-                let duration: f32;
+                let duration: f64;
                 // If the dc component duration token is "max" then we use the
                 // running maximum duration of any previous sources.
                 if parts[2] == "max" {
                     duration = max_duration;
                     println!("max: duration = {}", duration);
                 } else {
-                    duration = parts[2].parse::<f32>().unwrap() * reader.sample_rate as f32;
+                    duration = reader.get_duration(parts[2]) as f64;
                 }
-                let source = Box::new(DC::new(val, duration.round() as i32));
+                let source = Box::new(DC::new(val, duration.round() as SampleTime));
                 multiply.add(source, 0.0);
             } else {
                 let dc_offset = parts[0].parse::<f32>().unwrap();
                 let source_name = parts[1];
                 let source = reader.get_sound(source_name);
-                max_duration = max_duration.max(source.duration() as f32);
+                max_duration = max_duration.max(source.duration() as f64);
                 multiply.add(source, dc_offset);
             }
         }
         Box::new(multiply)
     }
 }
-
-}
\ No newline at end of file
@@ -1,46 +1,67 @@
-pub mod noise {
-
-use rand::Rng;
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
+// Mix a base seed with an index to get a seed for one node. This is the
+// splitmix64 finaliser, which spreads consecutive indexes across the whole
+// 64 bit range so neighbouring nodes don't get correlated sequences.
+pub fn derive_seed(seed: u64, index: u64) -> u64 {
+    let mut z = seed.wrapping_add(index.wrapping_add(1).wrapping_mul(0x9E3779B97F4A7C15));
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58476D1CE4E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D049BB133111EB);
+    z ^ (z >> 31)
+}
 
 #[derive(Clone)]
 pub struct Noise {
-    duration: i32,
+    seed: u64,
+    duration: SampleTime,
 }
 
 impl Noise {
     pub fn new(
-        duration: i32
+        seed: u64,
+        duration: SampleTime
     ) -> Self {
         Noise{
+            seed: seed,
             duration: duration,
         }
     }
 }
 
+struct NoiseData {
+    rng: StdRng,
+}
+
 impl SoundSource for Noise {
     fn init_state(&self) -> SoundData {
-        Box::new(0)
+        // Every new state starts the sequence again so that rendering the same
+        // node twice gives the same result
+        Box::new(NoiseData { rng: StdRng::seed_from_u64(self.seed) })
     }
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         if n > self.duration {
             (0.0, 0.0)
         } else {
-            let mut rng = rand::thread_rng();
-            let val: f32 = rng.gen_range(-1.0..1.0);
+            let data = state.downcast_mut::<NoiseData>().unwrap();
+            let val: f32 = data.rng.gen_range(-1.0..1.0);
             (val, val)
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
-        let duration = params[0].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Self::new(duration.round() as i32))
+        let duration = reader.get_duration(&params[0]);
+        // An optional second parameter fixes the seed for this node
+        let seed = match params.get(1) {
+            Some(seed) => seed.parse::<u64>().unwrap(),
+            None => reader.next_seed(),
+        };
+        Box::new(Self::new(seed, duration))
     }
 }
-}
\ No newline at end of file
@@ -0,0 +1,140 @@
+// Plays notes that are chosen while the song runs rather than written out in
+// a sequence. Every step a NoteChooser picks the next midi note (or a rest)
+// and it is played through a patch, which gets the midi note number as
+// INPUT(0) and the note length in seconds as INPUT(1), the same as the patches
+// used in sequences. Notes can ring on over the following steps.
+//
+// The patch is read once for each note the chooser could pick when the song
+// is loaded, so nothing has to be parsed while playing. Given a duration of
+// "forever" the notes keep coming until playback is interrupted.
+
+use std::sync::Arc;
+use dyn_clone::DynClone;
+use rand::SeedableRng;
+use rand::rngs::StdRng;
+use crate::midi_notes::freq2midi;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime, FOREVER};
+
+pub trait NoteChooser: DynClone {
+    fn init_state(&self) -> SoundData;
+    // The next midi note to play, or None to rest for a step
+    fn next_note(&self, state: &mut SoundData, rng: &mut StdRng) -> Option<u8>;
+    // Every note that next_note might return
+    fn notes(&self) -> Vec<u8>;
+}
+
+dyn_clone::clone_trait_object!(NoteChooser);
+
+pub type DynNoteChooser = Box<dyn NoteChooser + Send + Sync>;
+
+// A note name like C4 or a midi note number as a midi note number
+pub fn parse_midi_note(note: &str, reader: &SongReader) -> u8 {
+    freq2midi(reader.get_note(note)).round().clamp(0.0, 127.0) as u8
+}
+
+#[derive(Clone)]
+pub struct NotePlayer {
+    chooser: DynNoteChooser,
+    // The patch played at each midi note, for the notes the chooser can pick
+    voices: Arc<Vec<Option<DynSoundSource>>>,
+    step: SampleTime,
+    seed: u64,
+    duration: SampleTime,
+}
+
+impl NotePlayer {
+    pub fn new(
+        chooser: DynNoteChooser,
+        voices: Vec<Option<DynSoundSource>>,
+        step: SampleTime,
+        seed: u64,
+        duration: SampleTime,
+    ) -> Self {
+        NotePlayer {
+            chooser: chooser,
+            voices: Arc::new(voices),
+            step: step.max(1),
+            seed: seed,
+            duration: duration,
+        }
+    }
+
+    // Read the patch for every note the chooser can pick
+    pub fn from_patch(
+        chooser: DynNoteChooser,
+        patch: &str,
+        note_length: &str,
+        step: SampleTime,
+        duration: SampleTime,
+        reader: &mut SongReader,
+    ) -> Self {
+        let mut voices: Vec<Option<DynSoundSource>> = vec![None; 128];
+        for note in chooser.notes() {
+            let params = vec![note.to_string(), note_length.to_string()];
+            voices[note as usize] = Some(reader.get_patch(patch, &params));
+        }
+        let seed = reader.next_seed();
+        NotePlayer::new(chooser, voices, step, seed, duration)
+    }
+
+    fn longest_voice(&self) -> SampleTime {
+        self.voices.iter().flatten().map(|voice| voice.duration()).max().unwrap_or(0)
+    }
+}
+
+struct PlayingNote {
+    note: u8,
+    start_time: SampleTime,
+    voice_data: SoundData,
+}
+
+struct NotePlayerData {
+    rng: StdRng,
+    chooser_data: SoundData,
+    next_step: SampleTime,
+    playing: Vec<PlayingNote>,
+}
+
+impl SoundSource for NotePlayer {
+    fn init_state(&self) -> SoundData {
+        Box::new(NotePlayerData {
+            rng: StdRng::seed_from_u64(self.seed),
+            chooser_data: self.chooser.init_state(),
+            next_step: 0,
+            playing: Vec::new(),
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<NotePlayerData>().unwrap();
+        if n >= data.next_step && n < self.duration {
+            if let Some(note) = self.chooser.next_note(&mut data.chooser_data, &mut data.rng) {
+                if let Some(voice) = &self.voices[note as usize] {
+                    data.playing.push(PlayingNote { note: note, start_time: n, voice_data: voice.init_state() });
+                }
+            }
+            data.next_step = n + self.step;
+        }
+        let mut res1: f32 = 0.0;
+        let mut res2: f32 = 0.0;
+        for playing in data.playing.iter_mut() {
+            let voice = self.voices[playing.note as usize].as_ref().unwrap();
+            let (v1, v2) = voice.next_value(n - playing.start_time, &mut playing.voice_data);
+            res1 += v1;
+            res2 += v2;
+        }
+        let voices = &self.voices;
+        data.playing.retain(|playing| n + 1 - playing.start_time < voices[playing.note as usize].as_ref().unwrap().duration());
+        (res1, res2)
+    }
+
+    fn duration(&self) -> SampleTime {
+        // The last note can ring on after the last step
+        self.duration.saturating_add(self.longest_voice()).min(FOREVER)
+    }
+
+    fn from_yaml(_params: &Vec::<String>, _reader: &mut SongReader) -> DynSoundSource {
+        panic!("NotePlayer is made by the random_walk and markov_chain sound types");
+    }
+}
@@ -1,22 +1,20 @@
-pub mod oscillator {
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-
-use crate::knob::knob::Knob;
+use crate::knob::Knob;
 
 #[derive(Clone)]
 pub struct Oscillator {
     freq: Knob,
     phase: Knob,
-    duration: i32,
+    duration: SampleTime,
 }
 
 impl Oscillator {
     pub fn new(
         freq: Knob,
         phase: Knob,
-        duration: i32,
+        duration: SampleTime,
         ) -> Self {
         Oscillator{
             freq: freq,
@@ -29,6 +27,8 @@ impl Oscillator {
 struct OscillatorData {
     freq_data: SoundData,
     phase_data: SoundData,
+    // Running phase in cycles, so frequency changes don't make it jump
+    phase: f64,
 }
 
 impl SoundSource for Oscillator {
@@ -36,30 +36,30 @@ impl SoundSource for Oscillator {
         Box::new(OscillatorData {
             freq_data: self.freq.init_state(),
             phase_data: self.phase.init_state(),
+            phase: 0.0,
         })
     }
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         if n > self.duration {
             (0.0, 0.0)
         } else {
             let data = &mut state.downcast_mut::<OscillatorData>().unwrap();
-            let val = ((n as f32 * self.freq.next_value(n, &mut data.freq_data)
-                + self.phase.next_value(n, &mut data.phase_data))
-                * 2.0 * std::f32::consts::PI).sin();
+            let freq = self.freq.next_value(n, &mut data.freq_data);
+            let phase = data.phase + self.phase.next_value(n, &mut data.phase_data) as f64;
+            let val = (phase.rem_euclid(1.0) * 2.0 * std::f64::consts::PI).sin() as f32;
+            data.phase = (data.phase + freq as f64).rem_euclid(1.0);
             (val, val)
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let freq = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
         let phase = reader.get_knob(&params[1], 1.0 / reader.sample_rate as f32);
-        let duration = params[2].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Self::new(freq, phase, duration.round() as i32))
+        let duration = reader.get_duration(&params[2]);
+        Box::new(Self::new(freq, phase, duration))
     }
 }
-
-}
@@ -1,17 +1,15 @@
-pub mod pitch_shift {
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::knob::Knob;
 
-use crate::knob::knob::Knob;
-
-use crate::cos_transfer::cos_transfer::CosTransfer;
-use crate::dc::dc::DC;
-use crate::delay_line::delay_line::DelayLine;
-use crate::mix::mix::Mix;
-use crate::multiply::multiply::Multiply;
-use crate::ramp::ramp::Ramp;
-use crate::sequence::sequence::Sequence;
+use crate::cos_transfer::CosTransfer;
+use crate::dc::DC;
+use crate::delay_line::DelayLine;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::ramp::Ramp;
+use crate::sequence::Sequence;
 
 #[derive(Clone)]
 pub struct PitchShift {
@@ -58,11 +56,11 @@ pub struct PitchShift {
 //       |
 //       V
 //      OUT
-fn new_circuit(input: DynSoundSource, base_delay: i32, window_size: i32, freq: f32, phase: f32) -> DynSoundSource {
+fn new_circuit(input: DynSoundSource, base_delay: SampleTime, window_size: SampleTime, freq: f32, phase: f32) -> DynSoundSource {
     let duration = input.duration() + base_delay + window_size;
     let direction = freq.signum();
-    let period = (1.0 / freq.abs()).round() as i32;
-    let ramp_delay = (phase / (2.0 * std::f32::consts::PI) / freq.abs()).round() as i32;
+    let period = (1.0 / freq.abs()).round() as SampleTime;
+    let ramp_delay = (phase / (2.0 * std::f32::consts::PI) / freq.abs()).round() as SampleTime;
 
     // Delay line
     let mut ramp_sequence1 = Sequence::new();
@@ -83,7 +81,7 @@ fn new_circuit(input: DynSoundSource, base_delay: i32, window_size: i32, freq: f
     let mut delay_adder = Mix::new();
     delay_adder.add(Box::new(DC::new(base_delay as f32, duration)));
     delay_adder.add(Box::new(delay_window_multiplier));
-    let delay_line = DelayLine::new(input, Knob::new(Box::new(delay_adder)));
+    let delay_line = DelayLine::new(input, Knob::new(Box::new(delay_adder)), base_delay + window_size);
 
     // Window envelope
     let ramp2 = Ramp::new(Knob::dc(period as f32), Knob::dc(1.0), duration);
@@ -103,7 +101,7 @@ fn new_circuit(input: DynSoundSource, base_delay: i32, window_size: i32, freq: f
 }
 
 impl PitchShift {
-    pub fn new(input: DynSoundSource, base_delay: i32, window_size: i32, freq: f32) -> Self {
+    pub fn new(input: DynSoundSource, base_delay: SampleTime, window_size: SampleTime, freq: f32) -> Self {
         // We need to use the input twice so prerender it and clone.
         let input0 = input;
         let input1 = dyn_clone::clone_box(&*input0);
@@ -125,22 +123,20 @@ impl SoundSource for PitchShift {
         Box::new(PitchShiftData { output_data: self.output.init_state() })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<PitchShiftData>().unwrap();
         self.output.next_value(n, &mut data.output_data)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.output.duration()
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
-        let base_delay = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        let window_size = params[2].parse::<f32>().unwrap() * reader.sample_rate as f32;
+        let base_delay = params[1].parse::<f64>().unwrap() * reader.sample_rate as f64;
+        let window_size = params[2].parse::<f64>().unwrap() * reader.sample_rate as f64;
         let freq = params[3].parse::<f32>().unwrap() / reader.sample_rate as f32;
-        Box::new(PitchShift::new(input, base_delay.round() as i32, window_size.round() as i32, freq))
+        Box::new(PitchShift::new(input, base_delay.round() as SampleTime, window_size.round() as SampleTime, freq))
     }
 }
-
-}
@@ -0,0 +1,209 @@
+// PolyBlep is an anti-aliased oscillator for the classic analogue shapes.
+// A naive saw or square jumps instantly from one value to another, and that
+// step has harmonics all the way up that fold back below nyquist as aliasing.
+// PolyBLEP (polynomial band limited step) smooths each step by adding a small
+// two sample polynomial correction either side of it. Triangles have no steps
+// but do have corners, and these get the integrated version of the correction
+// (PolyBLAMP). This is much cheaper than summing sines like
+// GenerativeWaveform and sounds nearly as clean.
+//
+// To correct the sample before each step we need to know about the step when
+// we calculate the sample after it, so the output is one sample late.
+//
+// The sync input resets the phase every time it goes from zero or less to
+// above zero (hard sync). The reset is corrected the same way as the steps in
+// the waveform itself.
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Shape {
+    Saw,
+    Square,
+    Triangle,
+    Pulse,
+}
+
+#[derive(Clone)]
+pub struct PolyBlep {
+    shape: Shape,
+    freq: Knob, // Frequency as fraction of sample rate
+    duty: Knob, // Fraction of each cycle the pulse is high. Only used for Pulse
+    gain: Knob,
+    sync: Option<Knob>,
+    duration: SampleTime,
+}
+
+// A step of `height` in value and `slope` in value per sample, `offset`
+// samples before the current sample.
+struct Discontinuity {
+    offset: f32,
+    height: f32,
+    slope: f32,
+}
+
+impl PolyBlep {
+    pub fn new(shape: Shape, freq: Knob, duty: Knob, gain: Knob, sync: Option<Knob>, duration: SampleTime) -> Self {
+        PolyBlep { shape: shape, freq: freq, duty: duty, gain: gain, sync: sync, duration: duration }
+    }
+
+    fn value(&self, phase: f64, duty: f64) -> f32 {
+        let phase = phase as f32;
+        match self.shape {
+            Shape::Saw => 2.0 * phase - 1.0,
+            Shape::Square => if phase < 0.5 { 1.0 } else { -1.0 },
+            Shape::Pulse => if phase < duty as f32 { 1.0 } else { -1.0 },
+            Shape::Triangle => if phase < 0.5 { 4.0 * phase - 1.0 } else { 3.0 - 4.0 * phase },
+        }
+    }
+
+    // Change in value per sample
+    fn slope(&self, phase: f64, dt: f64) -> f32 {
+        match self.shape {
+            Shape::Saw => 2.0 * dt as f32,
+            Shape::Square | Shape::Pulse => 0.0,
+            Shape::Triangle => if phase < 0.5 { 4.0 * dt as f32 } else { -4.0 * dt as f32 },
+        }
+    }
+
+    // Find the steps and corners of the waveform between phase `from` and
+    // phase `to`, where `to` may have gone past 1.0 but not past 2.0. `end` is
+    // the time of `to` in samples before the current sample.
+    fn find_discontinuities(&self, from: f64, to: f64, dt: f64, duty: f64, end: f32, found: &mut Vec<Discontinuity>) {
+        // (phase, height, slope) of each point in one cycle
+        let points: [(f64, f32, f32); 2] = match self.shape {
+            Shape::Saw => [(1.0, -2.0, 0.0), (f64::MAX, 0.0, 0.0)],
+            Shape::Square => [(0.5, -2.0, 0.0), (1.0, 2.0, 0.0)],
+            Shape::Pulse => [(duty, -2.0, 0.0), (1.0, 2.0, 0.0)],
+            Shape::Triangle => [(0.5, 0.0, -8.0 * dt as f32), (1.0, 0.0, 8.0 * dt as f32)],
+        };
+        for cycle in [0.0, 1.0] {
+            for (point, height, slope) in points {
+                let point = point + cycle;
+                if point > from && point <= to && dt > 0.0 {
+                    found.push(Discontinuity {
+                        offset: end + ((to - point) / dt) as f32,
+                        height: height,
+                        slope: slope,
+                    });
+                }
+            }
+        }
+    }
+}
+
+struct PolyBlepData {
+    freq_data: SoundData,
+    duty_data: SoundData,
+    gain_data: SoundData,
+    sync_data: Option<SoundData>,
+    phase: f64,
+    prev_sync: f32,
+    // The previous sample, waiting for any correction from steps that happen
+    // just after it
+    prev_value: f32,
+    prev_gain: f32,
+    discontinuities: Vec<Discontinuity>,
+}
+
+impl SoundSource for PolyBlep {
+    fn init_state(&self) -> SoundData {
+        Box::new(PolyBlepData {
+            freq_data: self.freq.init_state(),
+            duty_data: self.duty.init_state(),
+            gain_data: self.gain.init_state(),
+            sync_data: self.sync.as_ref().map(|sync| sync.init_state()),
+            phase: 0.0,
+            prev_sync: 0.0,
+            prev_value: 0.0,
+            prev_gain: 0.0,
+            discontinuities: Vec::new(),
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<PolyBlepData>().unwrap();
+        let dt = (self.freq.next_value(n, &mut data.freq_data) as f64).clamp(0.0, 0.5);
+        let duty = (self.duty.next_value(n, &mut data.duty_data) as f64).clamp(0.0, 1.0);
+        let gain = self.gain.next_value(n, &mut data.gain_data);
+        data.discontinuities.clear();
+
+        // Work out when (if at all) the sync input crossed zero going up
+        let mut sync_offset = None;
+        if let (Some(sync), Some(sync_data)) = (&self.sync, &mut data.sync_data) {
+            let sync_value = sync.next_value(n, sync_data);
+            if data.prev_sync <= 0.0 && sync_value > 0.0 && n > 0 {
+                sync_offset = Some(sync_value / (sync_value - data.prev_sync));
+            }
+            data.prev_sync = sync_value;
+        }
+
+        let start = data.phase;
+        let mut phase;
+        match sync_offset {
+            Some(offset) => {
+                // Run up to the sync point, jump back to the start of the
+                // cycle then run on to now
+                let sync_phase = start + dt * (1.0 - offset as f64);
+                self.find_discontinuities(start, sync_phase, dt, duty, offset, &mut data.discontinuities);
+                let wrapped_sync_phase = sync_phase.fract();
+                data.discontinuities.push(Discontinuity {
+                    offset: offset,
+                    height: self.value(0.0, duty) - self.value(wrapped_sync_phase, duty),
+                    slope: self.slope(0.0, dt) - self.slope(wrapped_sync_phase, dt),
+                });
+                phase = dt * offset as f64;
+                // Only the pulse can have a step this close to the start of
+                // the cycle
+                self.find_discontinuities(0.0, phase, dt, duty, 0.0, &mut data.discontinuities);
+            },
+            None => {
+                phase = start + dt;
+                self.find_discontinuities(start, phase, dt, duty, 0.0, &mut data.discontinuities);
+            }
+        }
+        if phase >= 1.0 {
+            phase -= 1.0;
+        }
+        data.phase = phase;
+
+        let mut value = self.value(phase, duty);
+        for d in &data.discontinuities {
+            let t = d.offset;
+            // PolyBLEP for steps, PolyBLAMP for corners
+            data.prev_value += d.height * t * t / 2.0 + d.slope * t * t * t / 6.0;
+            value += -d.height * (1.0 - t) * (1.0 - t) / 2.0 + d.slope * (1.0 - t) * (1.0 - t) * (1.0 - t) / 6.0;
+        }
+
+        let output = data.prev_value * data.prev_gain;
+        data.prev_value = value;
+        data.prev_gain = gain;
+        (output, output)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let shape = match params[0].as_str() {
+            "saw" => Shape::Saw,
+            "square" => Shape::Square,
+            "triangle" => Shape::Triangle,
+            "pulse" => Shape::Pulse,
+            _ => panic!("Shape must be saw, square, triangle or pulse")
+        };
+        let freq = reader.get_knob(&params[1], 1.0 / reader.sample_rate as f32);
+        let duty = reader.get_knob(&params[2], 1.0);
+        let gain = reader.get_knob(&params[3], 1.0);
+        let duration = reader.get_duration(&params[4]);
+        let sync = params.get(5).map(|sync| reader.get_knob(sync, 1.0));
+        Box::new(Self::new(shape, freq, duty, gain, sync, duration))
+    }
+}
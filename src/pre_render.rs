@@ -1,9 +1,7 @@
-pub mod pre_render {
-
 // use std::fs::File;
 // use std::io::{Result, Write};
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime, is_forever};
 
 #[derive(Clone)]
 pub struct PreRender {
@@ -13,8 +11,11 @@ pub struct PreRender {
 impl PreRender {
     pub fn new(source: DynSoundSource) -> Self {
         let mut buf = Vec::<(f32, f32)>::new();
-        let mut sample_clock = 0i32;
+        let mut sample_clock: SampleTime = 0;
         let duration = source.duration();
+        if is_forever(duration) {
+            panic!("Can't pre-render a sound that goes on for ever, give it a duration");
+        }
         println!("PreRender {} samples", duration);
         let mut source_data = source.init_state();
         let mut report_threshold = 10.0;
@@ -32,6 +33,10 @@ impl PreRender {
         }
     }
 
+    pub fn samples(&self) -> &[(f32, f32)] {
+        &self.rendered_sound_source
+    }
+
     // pub fn debug(&self, path: &str) -> Result<()> {
     //     let mut output = File::create(path)?;
     //     for n in 0..self.rendered_sound_source.len() {
@@ -47,16 +52,16 @@ impl SoundSource for PreRender {
         Box::new(0)
     }
 
-    fn next_value(&self, n: i32, _state: &mut SoundData) -> (f32, f32) {
-        if n < self.rendered_sound_source.len() as i32 {
+    fn next_value(&self, n: SampleTime, _state: &mut SoundData) -> (f32, f32) {
+        if n < self.rendered_sound_source.len() as SampleTime {
             self.rendered_sound_source[n as usize]
         } else {
             (0.0, 0.0)
         }
     }
 
-    fn duration(&self) -> i32 {
-        self.rendered_sound_source.len() as i32
+    fn duration(&self) -> SampleTime {
+        self.rendered_sound_source.len() as SampleTime
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
@@ -64,5 +69,3 @@ impl SoundSource for PreRender {
         Box::new(Self::new(source))
     }
 }
-
-}
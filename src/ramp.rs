@@ -1,22 +1,20 @@
-pub mod ramp {
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
-
-use crate::knob::knob::Knob;
+use crate::knob::Knob;
 
 #[derive(Clone)]
 pub struct Ramp {
     period: Knob,
     amplitude: Knob,
-    duration: i32,
+    duration: SampleTime,
 }
 
 impl Ramp {
     pub fn new(
         period: Knob,
         amplitude: Knob,
-        duration: i32,
+        duration: SampleTime,
         ) -> Self {
         Ramp{
             period: period,
@@ -29,8 +27,8 @@ impl Ramp {
 struct RampData {
     period_data: SoundData,
     amplitude_data: SoundData,
-    period_lock: i32,
-    period_start: i32,
+    period_lock: SampleTime,
+    period_start: SampleTime,
 }
 
 impl SoundSource for Ramp {
@@ -42,14 +40,14 @@ impl SoundSource for Ramp {
             period_start: 0
         })
     }
-    fn next_value(&self, n: i32, status: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, status: &mut SoundData) -> (f32, f32) {
         if n < 0 || n > self.duration {
             (0.0, 0.0)
         } else {
             let data = &mut status.downcast_mut::<RampData>().unwrap();
             // lock until end of period
             if n >= data.period_start + data.period_lock {
-                data.period_lock = self.period.next_value(n, &mut data.period_data) as i32;
+                data.period_lock = self.period.next_value(n, &mut data.period_data) as SampleTime;
                 data.period_start = n;
             }
             // generate ramp as 0.0 to 1.0
@@ -60,16 +58,14 @@ impl SoundSource for Ramp {
         }
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let period = reader.get_knob(&params[0], reader.sample_rate as f32);
         let amplitude = reader.get_knob(&params[1], 1.0);
-        let duration = params[2].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Self::new(period, amplitude, duration.round() as i32))
+        let duration = reader.get_duration(&params[2]);
+        Box::new(Self::new(period, amplitude, duration))
     }
 }
-
-}
@@ -0,0 +1,119 @@
+// A melody that wanders up and down a scale at random. It starts in the middle
+// of the range and each step moves up or down by up to `max_step` notes of the
+// scale, bouncing back off the ends of the range. Some steps can be rests.
+//
+// The scale is a root and a name like "D dorian", or a root and the semitones
+// above it like "C 0,3,5,7,10".
+
+use rand::Rng;
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{DynSoundSource, SoundData};
+
+use crate::note_player::{NoteChooser, NotePlayer, parse_midi_note};
+
+fn scale_intervals(name: &str) -> Vec<u8> {
+    match name {
+        "major" | "ionian" => vec![0, 2, 4, 5, 7, 9, 11],
+        "minor" | "aeolian" => vec![0, 2, 3, 5, 7, 8, 10],
+        "harmonic_minor" => vec![0, 2, 3, 5, 7, 8, 11],
+        "dorian" => vec![0, 2, 3, 5, 7, 9, 10],
+        "phrygian" => vec![0, 1, 3, 5, 7, 8, 10],
+        "lydian" => vec![0, 2, 4, 6, 7, 9, 11],
+        "mixolydian" => vec![0, 2, 4, 5, 7, 9, 10],
+        "pentatonic" => vec![0, 2, 4, 7, 9],
+        "minor_pentatonic" => vec![0, 3, 5, 7, 10],
+        "blues" => vec![0, 3, 5, 6, 7, 10],
+        "whole_tone" => vec![0, 2, 4, 6, 8, 10],
+        "chromatic" => (0..12).collect(),
+        _ => name.split(',')
+            .map(|interval| interval.trim().parse::<u8>()
+                .unwrap_or_else(|_| panic!("Unknown scale \"{}\"", name)) % 12)
+            .collect(),
+    }
+}
+
+fn pitch_class(root: &str) -> u8 {
+    let mut chars = root.chars();
+    let base = match chars.next() {
+        Some('C') => 0, Some('D') => 2, Some('E') => 4, Some('F') => 5,
+        Some('G') => 7, Some('A') => 9, Some('B') => 11,
+        _ => panic!("Scale root must be a note name like C, F# or Bb, not \"{}\"", root)
+    };
+    match chars.as_str() {
+        "" => base,
+        "#" => (base + 1) % 12,
+        "b" => (base + 11) % 12,
+        _ => panic!("Scale root must be a note name like C, F# or Bb, not \"{}\"", root)
+    }
+}
+
+#[derive(Clone)]
+pub struct RandomWalk {
+    // The notes of the scale in the range, lowest first
+    notes: Vec<u8>,
+    max_step: usize,
+    rest_chance: f64,
+}
+
+impl RandomWalk {
+    pub fn new(root: u8, intervals: &[u8], lowest: u8, highest: u8, max_step: usize, rest_chance: f64) -> Self {
+        let notes: Vec<u8> = (lowest..=highest)
+            .filter(|note| intervals.contains(&((note + 12 - root % 12) % 12)))
+            .collect();
+        if notes.is_empty() {
+            panic!("No notes of the scale between {} and {}", lowest, highest);
+        }
+        RandomWalk { notes: notes, max_step: max_step, rest_chance: rest_chance }
+    }
+
+    // params: patch, step time, note length, scale, lowest note, highest note,
+    //         max step, rest chance, duration
+    pub fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let patch = &params[0];
+        let step = reader.get_duration(&params[1]);
+        let note_length = &params[2];
+        let (root, scale) = params[3].split_once(' ')
+            .unwrap_or_else(|| panic!("Scale should be a root and a scale like \"C major\", not \"{}\"", params[3]));
+        let lowest = parse_midi_note(&params[4], reader);
+        let highest = parse_midi_note(&params[5], reader);
+        let max_step = params[6].parse::<usize>().unwrap();
+        let rest_chance = params[7].parse::<f64>().unwrap();
+        let duration = reader.get_duration(&params[8]);
+        let walk = RandomWalk::new(pitch_class(root), &scale_intervals(scale.trim()), lowest, highest, max_step, rest_chance);
+        Box::new(NotePlayer::from_patch(Box::new(walk), patch, note_length, step, duration, reader))
+    }
+}
+
+struct RandomWalkData {
+    position: usize,
+}
+
+impl NoteChooser for RandomWalk {
+    fn init_state(&self) -> SoundData {
+        Box::new(RandomWalkData { position: self.notes.len() / 2 })
+    }
+
+    fn next_note(&self, state: &mut SoundData, rng: &mut StdRng) -> Option<u8> {
+        let data = state.downcast_mut::<RandomWalkData>().unwrap();
+        if rng.gen_bool(self.rest_chance.clamp(0.0, 1.0)) {
+            return None;
+        }
+        let max_step = self.max_step as i64;
+        let last = self.notes.len() as i64 - 1;
+        let mut position = data.position as i64 + rng.gen_range(-max_step..=max_step);
+        // Bounce off the ends of the range
+        if position < 0 {
+            position = -position;
+        }
+        if position > last {
+            position = 2 * last - position;
+        }
+        data.position = position.clamp(0, last) as usize;
+        Some(self.notes[data.position])
+    }
+
+    fn notes(&self) -> Vec<u8> {
+        self.notes.clone()
+    }
+}
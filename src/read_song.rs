@@ -1,459 +1,658 @@
-pub mod read_song {
-    use std::fs::File;
-    use std::sync::{Arc, Mutex};
-    use std::path::Path;
-    use std::collections::HashMap;
-    use serde::{Serialize, Deserialize};
-    use evalexpr;
-
-    use crate::traits::traits::{DynSoundSource, SoundSource};
-    use crate::knob::knob::{Knob, ComplexKnob};
-    use crate::midi_notes::midi_notes::{midistr2freq, midi2freq};
-
-    use crate::buffer_reader::buffer_reader::BufferReader;
-    use crate::buffer_writer::buffer_writer::BufferWriter;
-    use crate::cauchy_transfer::cauchy_transfer::CauchyTransfer;
-    use crate::clip::clip::Clip;
-    use crate::cos_transfer::cos_transfer::CosTransfer;
-    use crate::db2amp::db2amp::Db2Amp;
-    use crate::dc::dc::DC;
-    use crate::delay_line::delay_line::DelayLine;
-    use crate::envelope::envelope::Envelope;
-    use crate::export_wav::export_wav::ExportWav;
-    use crate::gaussian_transfer::gaussian_transfer::GaussianTransfer;
-    use crate::hann_window::hann_window::HannWindow;
-    use crate::import_wav::import_wav::ImportWav;
-    use crate::midi2freq::midi2freq::Midi2Freq;
-    use crate::mix::mix::Mix;
-    use crate::multiply::multiply::Multiply;
-    use crate::noise::noise::Noise;
-    use crate::oscillator::oscillator::Oscillator;
-    use crate::pitch_shift::pitch_shift::PitchShift;
-    use crate::pre_render::pre_render::PreRender;
-    use crate::pulse_train::pulse_train::PulseTrain;
-    use crate::ramp::ramp::Ramp;
-    use crate::recirculating_delay::recirculating_delay::RecirculatingDelay;
-    use crate::reverberator::reverberator::Reverberator;
-    use crate::rotation_transfer::rotation_transfer::RotationTransfer;
-    use crate::saw::saw::Saw;
-    use crate::sequence::sequence::Sequence;
-    use crate::sine::sine::Sine;
-    use crate::square::square::Square;
-    use crate::time_box::time_box::TimeBox;
-    use crate::triangle::triangle::Triangle;
-    use crate::uneven_delay::uneven_delay::UnevenDelay;
-    use crate::wavetable::wavetable::Wavetable;
-
-    use crate::filters::allpass_filter::allpass_filter::AllpassFilter;
-    use crate::filters::band_pass_filter::band_pass_filter::BandPassFilter;
-    use crate::filters::butterworth_bandpass_filter::butterworth_bandpass_filter::ButterworthBandpassFilter;
-    use crate::filters::butterworth_filter::butterworth_filter::ButterworthFilter;
-    use crate::filters::elementary_non_recirculating_filter::elementary_non_recirculating_filter::ElementaryNonRecirculatingFilter;
-    use crate::filters::elementary_non_recirculating_filter_2nd_form::elementary_non_recirculating_filter_2nd_form::ElementaryNonRecirculatingFilter2;
-    use crate::filters::elementary_recirculating_filter::elementary_recirculating_filter::ElementaryRecirculatingFilter;
-    use crate::filters::high_pass_filter::high_pass_filter::HighPassFilter;
-    use crate::filters::low_pass_filter::low_pass_filter::LowPassFilter;
-    use crate::filters::pole_zero_filter::pole_zero_filter::PoleZeroFilter;
-    use crate::filters::real_to_complex::real_to_complex::RealToComplex;
-
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
-    pub struct PatchItem {
-        name: String,
-        root: String,
-        sounds: Vec<SoundItem>,
-    }
-
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
-    pub struct SoundItem {
-        name: String,
-        sound_type: String,
-        params: Vec<String>
-    }
-
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
-    pub struct YAMLFormat {
-        include: Vec<String>,
-        patches: Vec<PatchItem>,
-        sounds: Vec<SoundItem>,
-        root: String
-    }
-
-    #[derive(Serialize, Deserialize, PartialEq, Debug)]
-    pub struct IncludeFormat {
-        include: Vec<String>,
-        patches: Vec<PatchItem>,
-    }
-
-    struct PatchContextItem {
-        params: Vec::<String>,
-        patch_source_input: Option<DynSoundSource>,
-        patch_index: usize,
-    }
-
-    impl PatchContextItem {
-        fn from_params(params: &Vec::<String>, patch_source_input: Option<DynSoundSource>, patch_index: usize) -> PatchContextItem {
-            PatchContextItem {
-                params: params.clone(),
-                patch_source_input: patch_source_input,
-                patch_index: patch_index }
-        }
+use std::fs::File;
+use std::sync::{Arc, Mutex};
+use std::path::Path;
+use std::collections::HashMap;
+use serde::{Serialize, Deserialize};
+use evalexpr;
+
+use crate::traits::{DynSoundSource, SoundSource, SampleTime, FOREVER};
+use crate::knob::{Knob, ComplexKnob};
+use crate::midi_notes::{midistr2freq, midi2freq};
+
+use crate::band_limited_random::BandLimitedRandom;
+use crate::blep_pulse::BlepPulse;
+use crate::blep_saw::BlepSaw;
+use crate::blep_square::BlepSquare;
+use crate::blep_triangle::BlepTriangle;
+use crate::buffer_reader::BufferReader;
+use crate::buffer_writer::BufferWriter;
+use crate::cauchy_transfer::CauchyTransfer;
+use crate::chorus::Chorus;
+use crate::clip::Clip;
+use crate::coloured_noise::ColouredNoise;
+use crate::compressor::Compressor;
+use crate::convolve::Convolve;
+use crate::cos_transfer::CosTransfer;
+use crate::db2amp::Db2Amp;
+use crate::dc::DC;
+use crate::delay_line::DelayLine;
+use crate::envelope::Envelope;
+use crate::export_wav::ExportWav;
+use crate::fdn_reverb::FdnReverb;
+use crate::flanger::Flanger;
+use crate::fm_operator::FmOperator;
+use crate::fm_voice::FmVoice;
+use crate::gate::Gate;
+use crate::gaussian_transfer::GaussianTransfer;
+use crate::hann_window::HannWindow;
+use crate::import_wav::ImportWav;
+use crate::impulse::Impulse;
+use crate::lfo::Lfo;
+use crate::limiter::Limiter;
+use crate::markov_chain::MarkovChain;
+use crate::midi2freq::Midi2Freq;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::noise::{Noise, derive_seed};
+use crate::oscillator::Oscillator;
+use crate::pitch_shift::PitchShift;
+use crate::poly_blep::PolyBlep;
+use crate::pre_render::PreRender;
+use crate::pulse_train::PulseTrain;
+use crate::ramp::Ramp;
+use crate::random_walk::RandomWalk;
+use crate::recirculating_delay::RecirculatingDelay;
+use crate::reverberator::Reverberator;
+use crate::rotation_transfer::RotationTransfer;
+use crate::sampler::{Sample, SampleZone, Sampler, SamplerVoice};
+use crate::sf2::read_sf2;
+use crate::sfz::read_sfz;
+use crate::saw::Saw;
+use crate::sequence::Sequence;
+use crate::sine::Sine;
+use crate::square::Square;
+use crate::stereo_delay::StereoDelay;
+use crate::time_box::TimeBox;
+use crate::triangle::Triangle;
+use crate::uneven_delay::UnevenDelay;
+use crate::varispeed::Varispeed;
+use crate::velvet_noise::VelvetNoise;
+use crate::vibrato::Vibrato;
+use crate::wavetable::Wavetable;
+use crate::wavetable_oscillator::WavetableOscillator;
+
+use crate::filters::allpass_filter::AllpassFilter;
+use crate::filters::band_pass_filter::BandPassFilter;
+use crate::filters::biquad_filter::BiquadFilter;
+use crate::filters::butterworth_bandpass_filter::ButterworthBandpassFilter;
+use crate::filters::butterworth_filter::ButterworthFilter;
+use crate::filters::elementary_non_recirculating_filter::ElementaryNonRecirculatingFilter;
+use crate::filters::elementary_non_recirculating_filter_2nd_form::ElementaryNonRecirculatingFilter2;
+use crate::filters::elementary_recirculating_filter::ElementaryRecirculatingFilter;
+use crate::filters::equaliser::Equaliser;
+use crate::filters::high_pass_filter::HighPassFilter;
+use crate::filters::ladder_filter::LadderFilter;
+use crate::filters::low_pass_filter::LowPassFilter;
+use crate::filters::pole_zero_filter::PoleZeroFilter;
+use crate::filters::real_to_complex::RealToComplex;
+use crate::filters::state_variable_filter::StateVariableFilter;
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct PatchItem {
+    name: String,
+    root: String,
+    sounds: Vec<SoundItem>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct SoundItem {
+    name: String,
+    sound_type: String,
+    params: Vec<String>
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct YAMLFormat {
+    include: Vec<String>,
+    patches: Vec<PatchItem>,
+    sounds: Vec<SoundItem>,
+    root: String,
+    // Base seed for random nodes. Each node gets its own seed derived from this.
+    #[serde(default)]
+    seed: Option<u64>,
+    // Beats (quarter notes) per minute, for tempo synced nodes
+    #[serde(default)]
+    tempo: Option<f32>,
+}
+
+#[derive(Serialize, Deserialize, PartialEq, Debug)]
+pub struct IncludeFormat {
+    include: Vec<String>,
+    patches: Vec<PatchItem>,
+}
+
+struct PatchContextItem {
+    params: Vec::<String>,
+    patch_source_input: Option<DynSoundSource>,
+    patch_index: usize,
+}
+
+impl PatchContextItem {
+    fn from_params(params: &Vec::<String>, patch_source_input: Option<DynSoundSource>, patch_index: usize) -> PatchContextItem {
+        PatchContextItem {
+            params: params.clone(),
+            patch_source_input: patch_source_input,
+            patch_index: patch_index }
     }
+}
 
-    struct PatchContext {
-        stack: Vec<PatchContextItem>,
-        current_idx: i32 // can go negative
+struct PatchContext {
+    stack: Vec<PatchContextItem>,
+    current_idx: i32 // can go negative
+}
+impl PatchContext {
+    fn new() -> PatchContext {
+        PatchContext { stack: Vec::<PatchContextItem>::new(), current_idx: -1 }
     }
-    impl PatchContext {
-        fn new() -> PatchContext {
-            PatchContext { stack: Vec::<PatchContextItem>::new(), current_idx: -1 }
-        }
-        fn push(&mut self, params: &Vec::<String>, patch_source_input: Option<DynSoundSource>, patch_index: usize) {
-            self.stack.push(PatchContextItem::from_params(params, patch_source_input, patch_index));
-            self.current_idx = self.stack.len() as i32 - 1;
-        }
-        fn pop(&mut self) -> Option<PatchContextItem> {
-            let res = self.stack.pop();
-            self.current_idx = self.stack.len() as i32 - 1;
-            res
-        }
-        fn current(&self) -> &PatchContextItem {
-            &self.stack[self.current_idx as usize]
+    fn push(&mut self, params: &Vec::<String>, patch_source_input: Option<DynSoundSource>, patch_index: usize) {
+        self.stack.push(PatchContextItem::from_params(params, patch_source_input, patch_index));
+        self.current_idx = self.stack.len() as i32 - 1;
+    }
+    fn pop(&mut self) -> Option<PatchContextItem> {
+        let res = self.stack.pop();
+        self.current_idx = self.stack.len() as i32 - 1;
+        res
+    }
+    fn current(&self) -> &PatchContextItem {
+        &self.stack[self.current_idx as usize]
+    }
+    fn get_param(&self, index: usize) -> String {
+        let params = &self.current().params;
+        params[index].clone()
+    }
+    fn active(&self) -> bool {
+        self.current_idx >= 0
+    }
+}
+
+type Buffer = Arc<Mutex<Vec<(f32,f32)>>>;
+
+// Songs that don't give a tempo play at this many beats per minute
+pub const DEFAULT_TEMPO: f32 = 120.0;
+
+pub struct SongReader {
+    yaml: YAMLFormat,
+    pub sample_rate: i32,
+    pub tempo: f32,
+    patch_context: PatchContext,
+    buffers: HashMap<String,Buffer>,
+    samplers: HashMap<String,Sampler>,
+    placeholders: HashMap<String,DynSoundSource>,
+    seed: u64,
+    seed_count: u64,
+}
+
+impl SongReader {
+
+    fn parse_knob(&mut self, knob_val: &str, dc_scale: f32) -> DynSoundSource {
+        match parse_note(knob_val) {
+            Some(f) => Box::new(DC::new(f * dc_scale, FOREVER)),
+            None => self.get_sound(knob_val),
         }
-        fn get_param(&self, index: usize) -> String {
-            let params = &self.current().params;
-            params[index].clone()
+    }
+
+    // A fixed frequency in Hz, given as a note name, midi note number or Hz
+    pub fn get_note(&self, note: &str) -> f32 {
+        match parse_note(note) {
+            Some(f) => f,
+            None => panic!("Couldn't parse \"{}\" as a note or frequency", note),
         }
-        fn active(&self) -> bool {
-            self.current_idx >= 0
+    }
+
+    // A duration in seconds, or "forever" for sounds that never end
+    pub fn get_duration(&self, duration: &str) -> SampleTime {
+        if duration == "forever" {
+            FOREVER
+        } else {
+            match duration.parse::<f64>() {
+                Ok(seconds) => (seconds * self.sample_rate as f64).round() as SampleTime,
+                Err(_) => panic!("Couldn't parse \"{}\" as a duration in seconds or \"forever\"", duration),
+            }
         }
     }
 
-    pub struct SongReader {
-        yaml: YAMLFormat,
-        pub sample_rate: i32,
-        patch_context: PatchContext,
-        buffers: HashMap<String,Arc<Mutex<Vec<(f32,f32)>>>>,
+    // The length in seconds of a note value like 1/4 (a beat), 1/8T (a
+    // triplet eighth) or 1/8. (a dotted eighth) at the song's tempo, or None
+    // if it isn't a note value
+    pub fn get_note_value(&self, note_value: &str) -> Option<f32> {
+        let (note_value, scale) = if let Some(triplet) = note_value.strip_suffix('T') {
+            (triplet, 2.0 / 3.0)
+        } else if let Some(dotted) = note_value.strip_suffix('.') {
+            (dotted, 1.5)
+        } else {
+            (note_value, 1.0)
+        };
+        let (numerator, denominator) = note_value.split_once('/')?;
+        let whole_notes = numerator.trim().parse::<f32>().ok()? / denominator.trim().parse::<f32>().ok()?;
+        Some(whole_notes * 4.0 * 60.0 / self.tempo * scale)
     }
 
-    impl SongReader {
+    // A time in seconds or a note value like 1/8. at the song's tempo
+    pub fn get_time(&self, time: &str) -> f32 {
+        self.get_note_value(time).unwrap_or_else(|| match time.parse::<f32>() {
+            Ok(seconds) => seconds,
+            Err(_) => panic!("Couldn't parse \"{}\" as a time in seconds or a note value", time),
+        })
+    }
 
-        fn parse_knob(&mut self, knob_val: &str, dc_scale: f32) -> DynSoundSource {
-            let char1 = knob_val.chars().nth(0).unwrap();
-            let note_range = 'A'..'H'; // doesn't include H
-            if  note_range.contains(&char1) && knob_val.len() <= 3 {
-                Box::new(DC::new(midistr2freq(knob_val) * dc_scale, core::i32::MAX))
-            } else {
-                let int_parse = knob_val.parse::<i8>();
-                match int_parse {
-                    Ok(i) => Box::new(DC::new(midi2freq(i) * dc_scale, core::i32::MAX)),
-                    Err(_) => {
-                        let float_parse = knob_val.parse::<f32>();
-                        match float_parse {
-                            Ok(f) => Box::new(DC::new(f * dc_scale, core::i32::MAX)),
-                            Err(_) => self.get_sound(knob_val),
-                        }
-                    }
-                }
-            }
-        }
+    // Get a seed for the next random node. Nodes are built in the same order
+    // every time the file is read so each one gets the same seed on every run.
+    pub fn next_seed(&mut self) -> u64 {
+        let seed = derive_seed(self.seed, self.seed_count);
+        self.seed_count += 1;
+        seed
+    }
 
-        pub fn get_knob(&mut self, knob_val: &str, dc_scale: f32) -> Knob {
-            println!("get_knob({})", knob_val);
-            Knob::new(self.parse_knob(knob_val, dc_scale))
+    pub fn get_knob(&mut self, knob_val: &str, dc_scale: f32) -> Knob {
+        println!("get_knob({})", knob_val);
+        Knob::new(self.parse_knob(knob_val, dc_scale))
+    }
+
+    // Like get_knob but plain numbers are values rather than midi notes, for
+    // levels, times and other knobs that aren't frequencies
+    pub fn get_value_knob(&mut self, knob_val: &str, dc_scale: f32) -> Knob {
+        match knob_val.parse::<f32>() {
+            Ok(value) => Knob::dc(value * dc_scale),
+            Err(_) => self.get_knob(knob_val, dc_scale),
         }
+    }
 
-        pub fn get_complex_knob(&mut self, knob_val: &str) -> ComplexKnob {
-            println!("get_complex_knob({})", knob_val);
-            let parts: Vec<_> = knob_val.split(",").collect();
-            let magnitude = self.parse_knob(&parts[0], 1.0);
-            let angle = self.parse_knob(&parts[1], 1.0);
-            ComplexKnob::new(Box::new(RealToComplex::new(magnitude, angle)))
+    // A rate in Hz, a note value like 1/4 at the song's tempo (one cycle per
+    // note) or a knob. Like get_value_knob, plain numbers are scaled by
+    // dc_scale.
+    pub fn get_rate_knob(&mut self, rate: &str, dc_scale: f32) -> Knob {
+        match self.get_note_value(rate) {
+            Some(seconds) => Knob::dc(dc_scale / seconds),
+            None => self.get_value_knob(rate, dc_scale),
         }
+    }
 
-        fn get_patch(&mut self, patch_str: &str, params: &Vec::<String>) -> DynSoundSource {
-            println!("get_patch({})", patch_str);
-            let parts: Vec<_> = patch_str.split(" ").collect();
-            let patch_name = parts[0].clone();
-            let patch_source_input;
-            if parts.len() > 1 {
-                patch_source_input = Some(self.get_sound(parts[1].clone()));
-            } else {
-                patch_source_input = None;
-            }
-            let patch_idx = match self.yaml.patches.binary_search_by_key(&patch_name, |s: &PatchItem| &s.name) {
-                Ok(patch_idx) => patch_idx,
-                Err(_e) => panic!("Couldn't find patch '{}'", &patch_name)
-            };
-            let patch_root = self.yaml.patches[patch_idx].root.clone();
-            self.patch_context.push(params, patch_source_input, patch_idx);
-            let res = self.get_sound(&patch_root);
-            self.patch_context.pop();
-            res
+    pub fn get_complex_knob(&mut self, knob_val: &str) -> ComplexKnob {
+        println!("get_complex_knob({})", knob_val);
+        let parts: Vec<_> = knob_val.split(",").collect();
+        let magnitude = self.parse_knob(parts[0], 1.0);
+        let angle = self.parse_knob(parts[1], 1.0);
+        ComplexKnob::new(Box::new(RealToComplex::new(magnitude, angle)))
+    }
+
+    pub fn get_patch(&mut self, patch_str: &str, params: &Vec::<String>) -> DynSoundSource {
+        println!("get_patch({})", patch_str);
+        let parts: Vec<_> = patch_str.split(" ").collect();
+        let patch_name = parts[0];
+        let patch_source_input;
+        if parts.len() > 1 {
+            patch_source_input = Some(self.get_sound(parts[1]));
+        } else {
+            patch_source_input = None;
         }
+        let patch_idx = match self.yaml.patches.binary_search_by_key(&patch_name, |s: &PatchItem| &s.name) {
+            Ok(patch_idx) => patch_idx,
+            Err(_e) => panic!("Couldn't find patch '{}'", &patch_name)
+        };
+        let patch_root = self.yaml.patches[patch_idx].root.clone();
+        self.patch_context.push(params, patch_source_input, patch_idx);
+        let res = self.get_sound(&patch_root);
+        self.patch_context.pop();
+        res
+    }
 
-        fn substitute_params_in_str(&self, param_str: &str) -> String {
-            let substitute_param: String;
-            let mut needs_substitution = false;
-            let mut start_pos: usize = 0;
-            if param_str.starts_with("INPUT(") {
-                needs_substitution = true;
-            } else if param_str.contains("INPUT(") {
-                start_pos = param_str.find("INPUT(").unwrap();
-                needs_substitution = true;
-            }
-            if needs_substitution {
-                let end_pos: usize;
-                match param_str[start_pos + 6..].find(")") {
-                    Some(p) => end_pos = p + start_pos + 6,
-                    None => end_pos = param_str.len()
-                }
-                let substitute_index = param_str[start_pos + 6..end_pos].parse::<usize>().unwrap();
-                substitute_param = param_str[0..start_pos].to_string()
-                    + &self.patch_context.get_param(substitute_index)
-                    + &self.substitute_params_in_str(&param_str[end_pos+1..]);
-            } else {
-                substitute_param = param_str.clone().to_string();
+    fn substitute_params_in_str(&self, param_str: &str) -> String {
+        let substitute_param: String;
+        let mut needs_substitution = false;
+        let mut start_pos: usize = 0;
+        if param_str.starts_with("INPUT(") {
+            needs_substitution = true;
+        } else if param_str.contains("INPUT(") {
+            start_pos = param_str.find("INPUT(").unwrap();
+            needs_substitution = true;
+        }
+        if needs_substitution {
+            let end_pos: usize;
+            match param_str[start_pos + 6..].find(")") {
+                Some(p) => end_pos = p + start_pos + 6,
+                None => end_pos = param_str.len()
             }
-            substitute_param
+            let substitute_index = param_str[start_pos + 6..end_pos].parse::<usize>().unwrap();
+            substitute_param = param_str[0..start_pos].to_string()
+                + &self.patch_context.get_param(substitute_index)
+                + &self.substitute_params_in_str(&param_str[end_pos+1..]);
+        } else {
+            substitute_param = param_str.to_string();
         }
+        substitute_param
+    }
 
-        fn substitute_params(&self, params: &Vec::<String>) -> Vec::<String> {
-            let mut new_params = Vec::<String>::new();
-            for param in params {
-                new_params.push(self.substitute_params_in_str(&param));
-            }
-            new_params
+    fn substitute_params(&self, params: &Vec::<String>) -> Vec::<String> {
+        let mut new_params = Vec::<String>::new();
+        for param in params {
+            new_params.push(self.substitute_params_in_str(param));
         }
+        new_params
+    }
 
-        fn get_const(&self, const_name: &str) -> String {
-            match const_name {
-                "sample_rate" => format!("{}", self.sample_rate),
-                "pi" => format!("{}", std::f32::consts::PI),
-                "max_int32" => format!("{}", core::i32::MAX),
-                _ => panic!("Unknown const '{}'", const_name)
-            }
+    fn get_const(&self, const_name: &str) -> String {
+        match const_name {
+            "sample_rate" => format!("{}", self.sample_rate),
+            "pi" => format!("{}", std::f32::consts::PI),
+            "tempo" => format!("{}", self.tempo),
+            "max_int32" => format!("{}", i32::MAX),
+            _ => panic!("Unknown const '{}'", const_name)
         }
+    }
 
-        fn substitute_const_params_in_str(&self, param_str: &str) -> String {
-            let substitute_param: String;
-            if param_str.contains("CONST(") {
-                let start_pos = param_str.find("CONST(").unwrap();
-                let end_pos: usize;
-                match param_str[start_pos + 6..].find(")") {
-                    Some(p) => end_pos = p + start_pos + 6,
-                    None => end_pos = param_str.len()
-                }
-                let const_name = &param_str[start_pos + 6..end_pos];
-                substitute_param = param_str[0..start_pos].to_string()
-                    + &self.get_const(const_name)
-                    + &self.substitute_const_params_in_str(&param_str[end_pos+1..]);
-            } else {
-                substitute_param = param_str.clone().to_string();
+    fn substitute_const_params_in_str(&self, param_str: &str) -> String {
+        let substitute_param: String;
+        if param_str.contains("CONST(") {
+            let start_pos = param_str.find("CONST(").unwrap();
+            let end_pos: usize;
+            match param_str[start_pos + 6..].find(")") {
+                Some(p) => end_pos = p + start_pos + 6,
+                None => end_pos = param_str.len()
             }
-            substitute_param
+            let const_name = &param_str[start_pos + 6..end_pos];
+            substitute_param = param_str[0..start_pos].to_string()
+                + &self.get_const(const_name)
+                + &self.substitute_const_params_in_str(&param_str[end_pos+1..]);
+        } else {
+            substitute_param = param_str.to_string();
         }
+        substitute_param
+    }
 
-        fn substitute_const_params(&self, params: &Vec::<String>) -> Vec::<String> {
-            let mut new_params = Vec::<String>::new();
-            for param in params {
-                new_params.push(self.substitute_const_params_in_str(&param));
-            }
-            new_params
+    fn substitute_const_params(&self, params: &Vec::<String>) -> Vec::<String> {
+        let mut new_params = Vec::<String>::new();
+        for param in params {
+            new_params.push(self.substitute_const_params_in_str(param));
         }
+        new_params
+    }
 
-        fn evaluate_params_in_str(&self, param_str: &str) -> String {
-            let evaluated_param: String;
-            let mut has_expr = false;
-            let mut start_pos: usize = 0;
-            if param_str.contains("EXPR(") {
-                has_expr = true;
-                start_pos = param_str.find("EXPR(").unwrap();
-            }
-            if has_expr {
-                // find end_pos
-                let end_pos: usize;
-                let mut bracket_count: u32 = 1;
-                let mut idx = start_pos + 5; // this should place us at the first char after the (
-                let mut chars = param_str.chars(); // iterator on string
-                chars.nth(idx-1); // consume chars up to idx
-                while bracket_count > 0 {
-                    let ch = chars.next().unwrap();
-                    if ch == '(' {
-                        bracket_count += 1;
-                    } else if ch == ')' {
-                        bracket_count -= 1;
-                    }
-                    idx += 1;
+    fn evaluate_params_in_str(&self, param_str: &str) -> String {
+        let evaluated_param: String;
+        let mut has_expr = false;
+        let mut start_pos: usize = 0;
+        if param_str.contains("EXPR(") {
+            has_expr = true;
+            start_pos = param_str.find("EXPR(").unwrap();
+        }
+        if has_expr {
+            // find end_pos
+            let end_pos: usize;
+            let mut bracket_count: u32 = 1;
+            let mut idx = start_pos + 5; // this should place us at the first char after the (
+            let mut chars = param_str.chars(); // iterator on string
+            chars.nth(idx-1); // consume chars up to idx
+            while bracket_count > 0 {
+                let ch = chars.next().unwrap();
+                if ch == '(' {
+                    bracket_count += 1;
+                } else if ch == ')' {
+                    bracket_count -= 1;
                 }
-                end_pos = idx - 1; // Don't include the last bracket. We also skip the first bracket in the next line
-                let context = evalexpr::context_map! {
-                    "midi2freq" => Function::new(|argument| {
-                        if let Ok(val) = argument.as_int() {
-                            Ok(evalexpr::Value::Float(midi2freq(val as i8).into()))
-                        } else {
-                            Err(evalexpr::EvalexprError::expected_int(argument.clone()))
-                        }
-                    }),
-                }.unwrap(); // Do proper error handling here
-                let eval: f32 = evalexpr::eval_float_with_context(&param_str[start_pos + 5..end_pos], &context).unwrap() as f32;
-                evaluated_param = param_str[0..start_pos].to_string() // prefix
-                    + &eval.to_string() // replace EXPR(blah) with evaluated expression
-                    + &self.evaluate_params_in_str(&param_str[end_pos+1..]); // evaluate any other params in the string
-            } else {
-                evaluated_param = param_str.clone().to_string();
+                idx += 1;
             }
-            evaluated_param
+            end_pos = idx - 1; // Don't include the last bracket. We also skip the first bracket in the next line
+            let context = evalexpr::context_map! {
+                "midi2freq" => Function::new(|argument| {
+                    if let Ok(val) = argument.as_int() {
+                        Ok(evalexpr::Value::Float(midi2freq(val as i8).into()))
+                    } else {
+                        Err(evalexpr::EvalexprError::expected_int(argument.clone()))
+                    }
+                }),
+            }.unwrap(); // Do proper error handling here
+            let eval: f32 = evalexpr::eval_float_with_context(&param_str[start_pos + 5..end_pos], &context).unwrap() as f32;
+            evaluated_param = param_str[0..start_pos].to_string() // prefix
+                + &eval.to_string() // replace EXPR(blah) with evaluated expression
+                + &self.evaluate_params_in_str(&param_str[end_pos+1..]); // evaluate any other params in the string
+        } else {
+            evaluated_param = param_str.to_string();
         }
+        evaluated_param
+    }
 
-        fn evaluate_params(&self, params: &Vec::<String>) -> Vec::<String> {
-            let mut new_params = Vec::<String>::new();
-            for param in params {
-                let p1 = self.evaluate_params_in_str(&param);
-                if param.contains("EXPR(") {
-                    println!("evaluate_params {} => {}", &param, &p1);
-                }
-                new_params.push(p1);
+    fn evaluate_params(&self, params: &Vec::<String>) -> Vec::<String> {
+        let mut new_params = Vec::<String>::new();
+        for param in params {
+            let p1 = self.evaluate_params_in_str(param);
+            if param.contains("EXPR(") {
+                println!("evaluate_params {} => {}", &param, &p1);
             }
-            new_params
+            new_params.push(p1);
         }
+        new_params
+    }
 
-        fn get_sound_from_type(&mut self, sound_type: &str, params: &Vec::<String>) -> DynSoundSource {
-            // Subsitute the INPUT(N) style expressions
-            let substituted_params = self.substitute_params(params);
-            // Substitute the CONST(blah) style expressions
-            let const_substituted_params = self.substitute_const_params(&substituted_params);
-            // Substitute the EXPR(maths stuff) style expressions
-            let evaluated_params = self.evaluate_params(&const_substituted_params);
-            if sound_type.starts_with("patch ") {
-                self.get_patch(&sound_type[6..], &evaluated_params)
-            } else {
-                match sound_type {
-                    "allpass_filter" => AllpassFilter::from_yaml(&evaluated_params, self),
-                    "band_pass_filter" => BandPassFilter::from_yaml(&evaluated_params, self),
-                    "buffer_reader" => BufferReader::from_yaml(&evaluated_params, self),
-                    "buffer_writer" => BufferWriter::from_yaml(&evaluated_params, self),
-                    "butterworth_bandpass_filter" => ButterworthBandpassFilter::from_yaml(&evaluated_params, self),
-                    "butterworth_filter" => ButterworthFilter::from_yaml(&evaluated_params, self),
-                    "cauchy_transfer" => CauchyTransfer::from_yaml(&evaluated_params, self),
-                    "clip" => Clip::from_yaml(&evaluated_params, self),
-                    "cos_transfer" => CosTransfer::from_yaml(&evaluated_params, self),
-                    "db2amp" => Db2Amp::from_yaml(&evaluated_params, self),
-                    "dc" => DC::from_yaml(&evaluated_params, self),
-                    "delay_line" => DelayLine::from_yaml(&evaluated_params, self),
-                    "elementary_non_recirculating_filter" => ElementaryNonRecirculatingFilter::from_yaml(&evaluated_params, self),
-                    "elementary_non_recirculating_filter_2nd_form" => ElementaryNonRecirculatingFilter2::from_yaml(&evaluated_params, self),
-                    "elementary_recirculating_filter" => ElementaryRecirculatingFilter::from_yaml(&evaluated_params, self),
-                    "envelope" => Envelope::from_yaml(&evaluated_params, self),
-                    "export_wav" => ExportWav::from_yaml(&evaluated_params, self),
-                    "gaussian_transfer" => GaussianTransfer::from_yaml(&evaluated_params, self),
-                    "hann_window" => HannWindow::from_yaml(&evaluated_params, self),
-                    "high_pass_filter" => HighPassFilter::from_yaml(&evaluated_params, self),
-                    "import_wav" => ImportWav::from_yaml(&evaluated_params, self),
-                    "low_pass_filter" => LowPassFilter::from_yaml(&evaluated_params, self),
-                    "midi2freq" => Midi2Freq::from_yaml(&evaluated_params, self),
-                    "mix" => Mix::from_yaml(&evaluated_params, self),
-                    "multiply" => Multiply::from_yaml(&evaluated_params, self),
-                    "noise" => Noise::from_yaml(&evaluated_params, self),
-                    "oscillator" => Oscillator::from_yaml(&evaluated_params, self),
-                    "pitch_shift" => PitchShift::from_yaml(&evaluated_params, self),
-                    "pre_render" => PreRender::from_yaml(&evaluated_params, self),
-                    "pole_zero_filter" => PoleZeroFilter::from_yaml(&evaluated_params, self),
-                    "pulse_train" => PulseTrain::from_yaml(&evaluated_params, self),
-                    "ramp" => Ramp::from_yaml(&evaluated_params, self),
-                    "recirculating_delay" => RecirculatingDelay::from_yaml(&evaluated_params, self),
-                    "reverberator" => Reverberator::from_yaml(&evaluated_params, self),
-                    "rotation_transfer" => RotationTransfer::from_yaml(&evaluated_params, self),
-                    "sequence" => Sequence::from_yaml(&evaluated_params, self),
-                    "saw" => Saw::from_yaml(&evaluated_params, self),
-                    "sine" => Sine::from_yaml(&evaluated_params, self),
-                    "square" => Square::from_yaml(&evaluated_params, self),
-                    "time_box" => TimeBox::from_yaml(&evaluated_params, self),
-                    "triangle" => Triangle::from_yaml(&evaluated_params, self),
-                    "uneven_delay" => UnevenDelay::from_yaml(&evaluated_params, self),
-                    "wavetable" => Wavetable::from_yaml(&evaluated_params, self),
-                    &_ => todo!("sound_type: {}", sound_type)
-                }
+    fn get_sound_from_type(&mut self, sound_type: &str, params: &Vec::<String>) -> DynSoundSource {
+        // Subsitute the INPUT(N) style expressions
+        let substituted_params = self.substitute_params(params);
+        // Substitute the CONST(blah) style expressions
+        let const_substituted_params = self.substitute_const_params(&substituted_params);
+        // Substitute the EXPR(maths stuff) style expressions
+        let evaluated_params = self.evaluate_params(&const_substituted_params);
+        if let Some(patch_str) = sound_type.strip_prefix("patch ") {
+            self.get_patch(patch_str, &evaluated_params)
+        } else {
+            match sound_type {
+                "allpass_filter" => AllpassFilter::from_yaml(&evaluated_params, self),
+                "band_limited_random" => BandLimitedRandom::from_yaml(&evaluated_params, self),
+                "band_pass_filter" => BandPassFilter::from_yaml(&evaluated_params, self),
+                "biquad" => BiquadFilter::from_yaml(&evaluated_params, self),
+                "blep_pulse" => BlepPulse::from_yaml(&evaluated_params, self),
+                "blep_saw" => BlepSaw::from_yaml(&evaluated_params, self),
+                "blep_square" => BlepSquare::from_yaml(&evaluated_params, self),
+                "blep_triangle" => BlepTriangle::from_yaml(&evaluated_params, self),
+                "buffer_reader" => BufferReader::from_yaml(&evaluated_params, self),
+                "buffer_writer" => BufferWriter::from_yaml(&evaluated_params, self),
+                "butterworth_bandpass_filter" => ButterworthBandpassFilter::from_yaml(&evaluated_params, self),
+                "butterworth_filter" => ButterworthFilter::from_yaml(&evaluated_params, self),
+                "cauchy_transfer" => CauchyTransfer::from_yaml(&evaluated_params, self),
+                "chorus" => Chorus::from_yaml(&evaluated_params, self),
+                "clip" => Clip::from_yaml(&evaluated_params, self),
+                "coloured_noise" => ColouredNoise::from_yaml(&evaluated_params, self),
+                "compressor" => Compressor::from_yaml(&evaluated_params, self),
+                "convolve" => Convolve::from_yaml(&evaluated_params, self),
+                "cos_transfer" => CosTransfer::from_yaml(&evaluated_params, self),
+                "db2amp" => Db2Amp::from_yaml(&evaluated_params, self),
+                "dc" => DC::from_yaml(&evaluated_params, self),
+                "delay_line" => DelayLine::from_yaml(&evaluated_params, self),
+                "elementary_non_recirculating_filter" => ElementaryNonRecirculatingFilter::from_yaml(&evaluated_params, self),
+                "elementary_non_recirculating_filter_2nd_form" => ElementaryNonRecirculatingFilter2::from_yaml(&evaluated_params, self),
+                "elementary_recirculating_filter" => ElementaryRecirculatingFilter::from_yaml(&evaluated_params, self),
+                "envelope" => Envelope::from_yaml(&evaluated_params, self),
+                "eq" => Equaliser::from_yaml(&evaluated_params, self),
+                "export_wav" => ExportWav::from_yaml(&evaluated_params, self),
+                "fdn_reverb" => FdnReverb::from_yaml(&evaluated_params, self),
+                "flanger" => Flanger::from_yaml(&evaluated_params, self),
+                "fm_operator" => FmOperator::from_yaml(&evaluated_params, self),
+                "fm_voice" => FmVoice::from_yaml(&evaluated_params, self),
+                "gate" => Gate::from_yaml(&evaluated_params, self),
+                "gaussian_transfer" => GaussianTransfer::from_yaml(&evaluated_params, self),
+                "hann_window" => HannWindow::from_yaml(&evaluated_params, self),
+                "high_pass_filter" => HighPassFilter::from_yaml(&evaluated_params, self),
+                "import_wav" => ImportWav::from_yaml(&evaluated_params, self),
+                "impulse" => Impulse::from_yaml(&evaluated_params, self),
+                "ladder_filter" => LadderFilter::from_yaml(&evaluated_params, self),
+                "lfo" => Lfo::from_yaml(&evaluated_params, self),
+                "limiter" => Limiter::from_yaml(&evaluated_params, self),
+                "low_pass_filter" => LowPassFilter::from_yaml(&evaluated_params, self),
+                "markov_chain" => MarkovChain::from_yaml(&evaluated_params, self),
+                "midi2freq" => Midi2Freq::from_yaml(&evaluated_params, self),
+                "mix" => Mix::from_yaml(&evaluated_params, self),
+                "multiply" => Multiply::from_yaml(&evaluated_params, self),
+                "noise" => Noise::from_yaml(&evaluated_params, self),
+                "oscillator" => Oscillator::from_yaml(&evaluated_params, self),
+                "pitch_shift" => PitchShift::from_yaml(&evaluated_params, self),
+                "poly_blep" => PolyBlep::from_yaml(&evaluated_params, self),
+                "pre_render" => PreRender::from_yaml(&evaluated_params, self),
+                "pole_zero_filter" => PoleZeroFilter::from_yaml(&evaluated_params, self),
+                "pulse_train" => PulseTrain::from_yaml(&evaluated_params, self),
+                "ramp" => Ramp::from_yaml(&evaluated_params, self),
+                "random_walk" => RandomWalk::from_yaml(&evaluated_params, self),
+                "recirculating_delay" => RecirculatingDelay::from_yaml(&evaluated_params, self),
+                "resample" | "varispeed" => Varispeed::from_yaml(&evaluated_params, self),
+                "reverberator" => Reverberator::from_yaml(&evaluated_params, self),
+                "rotation_transfer" => RotationTransfer::from_yaml(&evaluated_params, self),
+                "sequence" => Sequence::from_yaml(&evaluated_params, self),
+                "sampler" => SamplerVoice::from_yaml(&evaluated_params, self),
+                "saw" => Saw::from_yaml(&evaluated_params, self),
+                "sine" => Sine::from_yaml(&evaluated_params, self),
+                "square" => Square::from_yaml(&evaluated_params, self),
+                "state_variable_filter" => StateVariableFilter::from_yaml(&evaluated_params, self),
+                "stereo_delay" => StereoDelay::from_yaml(&evaluated_params, self),
+                "time_box" => TimeBox::from_yaml(&evaluated_params, self),
+                "triangle" => Triangle::from_yaml(&evaluated_params, self),
+                "uneven_delay" => UnevenDelay::from_yaml(&evaluated_params, self),
+                "velvet_noise" => VelvetNoise::from_yaml(&evaluated_params, self),
+                "vibrato" => Vibrato::from_yaml(&evaluated_params, self),
+                "wavetable" => Wavetable::from_yaml(&evaluated_params, self),
+                "wavetable_oscillator" => WavetableOscillator::from_yaml(&evaluated_params, self),
+                &_ => todo!("sound_type: {}", sound_type)
             }
         }
+    }
 
-        fn get_patch_sound(&self, sound_name: &str) -> &SoundItem {
-            println!("get_patch_sound({})", sound_name);
-            let idx = self.patch_context.current().patch_index;
-            let patch = &self.yaml.patches[idx];
-            let sound_idx = match patch.sounds.binary_search_by_key(&sound_name, |s: &SoundItem| &s.name) {
-                Ok(sound_idx) => sound_idx,
-                Err(_e) => panic!("Couldn't find sound '{}' in patch", &sound_name)
-            };
-            &patch.sounds[sound_idx]
+    fn get_patch_sound(&self, sound_name: &str) -> &SoundItem {
+        println!("get_patch_sound({})", sound_name);
+        let idx = self.patch_context.current().patch_index;
+        let patch = &self.yaml.patches[idx];
+        let sound_idx = match patch.sounds.binary_search_by_key(&sound_name, |s: &SoundItem| &s.name) {
+            Ok(sound_idx) => sound_idx,
+            Err(_e) => panic!("Couldn't find sound '{}' in patch", &sound_name)
+        };
+        &patch.sounds[sound_idx]
+    }
+
+    pub fn get_buffer(&mut self, buffer_name: &str) -> Arc<Mutex<Vec<(f32,f32)>>> {
+        println!("get_buffer({})", buffer_name);
+        if let Some(buf) = self.buffers.get(buffer_name) {
+            buf.clone()
+        } else {
+            let buf = Arc::new(Mutex::new(Vec::<(f32,f32)>::new()));
+            self.buffers.insert(buffer_name.to_string(), buf.clone());
+            buf
         }
+    }
 
-        pub fn get_buffer(&mut self, buffer_name: &str) -> Arc<Mutex<Vec<(f32,f32)>>> {
-            println!("get_buffer({})", buffer_name);
-            if let Some(buf) = self.buffers.get(buffer_name) {
-                buf.clone()
+    // Samplers are loaded once per song however many notes they play. SFZ
+    // files are read as whole instruments and SF2 files as the given preset
+    // ("bank:program", or bank 0 program 0 if none is given). Anything else is
+    // a single WAV file covering every key.
+    pub fn get_sampler(&mut self, filename: &str, preset: Option<&str>) -> Sampler {
+        let key = format!("{} {}", filename, preset.unwrap_or(""));
+        if let Some(sampler) = self.samplers.get(&key) {
+            sampler.clone()
+        } else {
+            let lower = filename.to_lowercase();
+            let sampler = if lower.ends_with(".sfz") {
+                read_sfz(filename, self.sample_rate)
+            } else if lower.ends_with(".sf2") {
+                read_sf2(filename, preset.unwrap_or("0:0"), self.sample_rate)
             } else {
-                let buf = Arc::new(Mutex::new(Vec::<(f32,f32)>::new()));
-                self.buffers.insert(buffer_name.to_string(), buf.clone());
-                buf
-            }
+                let sample = Arc::new(Sample::load(filename));
+                Sampler::new(self.sample_rate, vec![SampleZone::new(sample, 0, 127)])
+            };
+            self.samplers.insert(key, sampler.clone());
+            sampler
         }
+    }
 
-        pub fn get_sound(&mut self, sound_name: &str) -> DynSoundSource {
-            println!("get_sound({})", sound_name);
-            if sound_name == "PATCH_INPUT" {
-                match &self.patch_context.current().patch_source_input {
-                    Some(res) => res.clone(),
-                    None => panic!("PATCH_INPUT not found for patch")
-                }
+    // Use this sound wherever the song asks for sound_name, whether or not the
+    // song defines it, so a filter can be fed a test signal in place of its
+    // usual input
+    pub fn set_placeholder(&mut self, sound_name: &str, sound: DynSoundSource) {
+        self.placeholders.insert(sound_name.to_owned(), sound);
+    }
+
+    pub fn get_root(&mut self) -> DynSoundSource {
+        self.get_sound(&self.yaml.root.clone())
+    }
+
+    pub fn get_sound(&mut self, sound_name: &str) -> DynSoundSource {
+        println!("get_sound({})", sound_name);
+        if let Some(sound) = self.placeholders.get(sound_name) {
+            sound.clone()
+        } else if sound_name == "PATCH_INPUT" {
+            match &self.patch_context.current().patch_source_input {
+                Some(res) => res.clone(),
+                None => panic!("PATCH_INPUT not found for patch")
+            }
+        } else {
+            let item;
+            if self.patch_context.active() {
+                item = self.get_patch_sound(sound_name);
             } else {
-                let item;
-                if self.patch_context.active() {
-                    item = self.get_patch_sound(sound_name);
+                if let Ok(sound_idx) = self.yaml.sounds.binary_search_by_key(&sound_name, |s: &SoundItem| &s.name) {
+                    item = &self.yaml.sounds[sound_idx];
                 } else {
-                    if let Ok(sound_idx) = self.yaml.sounds.binary_search_by_key(&sound_name, |s: &SoundItem| &s.name) {
-                        item = &self.yaml.sounds[sound_idx];
-                    } else {
-                        panic!("get_sound: Couldn't find {}", sound_name);
-                    }
+                    panic!("get_sound: Couldn't find {}", sound_name);
                 }
-                self.get_sound_from_type(&item.sound_type.clone(), &item.params.clone())
             }
+            self.get_sound_from_type(&item.sound_type.clone(), &item.params.clone())
         }
     }
+}
 
-    fn process_includes(path: &Path, includes: &Vec<String>) -> Vec<PatchItem> {
-        let mut res = Vec::<PatchItem>::new();
-        for include_fname in includes {
-            let full_include_fname = path.join(include_fname);
-            let include_file = File::open(full_include_fname).unwrap();
-            let mut patch_file: IncludeFormat = serde_yaml::from_reader(&include_file).unwrap();
-            let mut sub_patches = process_includes(path, &patch_file.include);
-            patch_file.patches.append(&mut sub_patches);
-            res.append(&mut patch_file.patches);
-        }
-        res
+fn process_includes(path: &Path, includes: &Vec<String>) -> Vec<PatchItem> {
+    let mut res = Vec::<PatchItem>::new();
+    for include_fname in includes {
+        let full_include_fname = path.join(include_fname);
+        let include_file = File::open(full_include_fname).unwrap();
+        let mut patch_file: IncludeFormat = serde_yaml::from_reader(&include_file).unwrap();
+        let mut sub_patches = process_includes(path, &patch_file.include);
+        patch_file.patches.append(&mut sub_patches);
+        res.append(&mut patch_file.patches);
     }
+    res
+}
 
-    pub fn read_song(filename: &str, sample_rate: i32) -> DynSoundSource {
-        let f = File::open(filename).unwrap();
-        let mut yaml:YAMLFormat = serde_yaml::from_reader(&f).unwrap();
-        // get path of base file then look for include files in that location
-        let path = Path::new(filename);
-        let parent = path.parent().unwrap();
-        let mut patches = process_includes(parent, &yaml.include);
-        yaml.patches.append(&mut patches);
-        yaml.sounds.sort_by(|s1: &SoundItem, s2: &SoundItem| s1.name.cmp(&s2.name));
-        yaml.patches.sort_by(|s1: &PatchItem, s2: &PatchItem| s1.name.cmp(&s2.name));
-        for patch in yaml.patches.iter_mut() {
-            patch.sounds.sort_by(|s1: &SoundItem, s2: &SoundItem| s1.name.cmp(&s2.name));
+// Note names like C#4 and integers (midi note numbers) are frequencies in Hz,
+// as are other numbers
+fn parse_note(val: &str) -> Option<f32> {
+    let char1 = val.chars().next()?;
+    let note_range = 'A'..'H'; // doesn't include H
+    if  note_range.contains(&char1) && val.len() <= 3 {
+        Some(midistr2freq(val))
+    } else {
+        match val.parse::<i8>() {
+            Ok(i) => Some(midi2freq(i)),
+            Err(_) => val.parse::<f32>().ok(),
         }
-        let mut reader = SongReader {
-            yaml: yaml,
-            sample_rate: sample_rate,
-            patch_context: PatchContext::new(),
-            buffers: HashMap::<String, Arc<Mutex<Vec<(f32,f32)>>>>::new(),
-        };
-        reader.get_sound(&reader.yaml.root.clone())
     }
+}
+
+// If seed is given it overrides any seed in the song file. Songs without a seed
+// use 0 so that they render the same way every time.
+pub fn read_song(filename: &str, sample_rate: i32, seed: Option<u64>) -> DynSoundSource {
+    open_song(filename, sample_rate, seed).get_root()
+}
 
+// A reader for the song's sounds and patches, for building parts of the song
+// rather than the whole thing. The seed works as for read_song.
+pub fn open_song(filename: &str, sample_rate: i32, seed: Option<u64>) -> SongReader {
+    let f = File::open(filename).unwrap();
+    let mut yaml:YAMLFormat = serde_yaml::from_reader(&f).unwrap();
+    // get path of base file then look for include files in that location
+    let path = Path::new(filename);
+    let parent = path.parent().unwrap();
+    let mut patches = process_includes(parent, &yaml.include);
+    yaml.patches.append(&mut patches);
+    yaml.sounds.sort_by(|s1: &SoundItem, s2: &SoundItem| s1.name.cmp(&s2.name));
+    yaml.patches.sort_by(|s1: &PatchItem, s2: &PatchItem| s1.name.cmp(&s2.name));
+    for patch in yaml.patches.iter_mut() {
+        patch.sounds.sort_by(|s1: &SoundItem, s2: &SoundItem| s1.name.cmp(&s2.name));
+    }
+    let seed = seed.or(yaml.seed).unwrap_or(0);
+    let tempo = yaml.tempo.unwrap_or(DEFAULT_TEMPO);
+    SongReader {
+        yaml: yaml,
+        sample_rate: sample_rate,
+        tempo: tempo,
+        patch_context: PatchContext::new(),
+        buffers: HashMap::<String, Buffer>::new(),
+        samplers: HashMap::<String, Sampler>::new(),
+        placeholders: HashMap::<String, DynSoundSource>::new(),
+        seed: seed,
+        seed_count: 0,
+    }
 }
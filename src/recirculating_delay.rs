@@ -1,16 +1,14 @@
-pub mod recirculating_delay {
-
 use std::sync::{Arc, Mutex};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::dc::dc::DC;
-use crate::buffer_reader::buffer_reader::BufferReader;
-use crate::buffer_writer::buffer_writer::BufferWriter;
-use crate::mix::mix::Mix;
-use crate::multiply::multiply::Multiply;
-use crate::sequence::sequence::Sequence;
+use crate::dc::DC;
+use crate::buffer_reader::BufferReader;
+use crate::buffer_writer::BufferWriter;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::sequence::Sequence;
 
 
 #[derive(Clone)]
@@ -54,9 +52,9 @@ pub struct RecirculatingDelay {
 impl RecirculatingDelay {
     pub fn new(
         input: DynSoundSource,
-        delay: i32,
+        delay: SampleTime,
         delay_gain: f32,
-        duration: i32
+        duration: SampleTime
     ) -> Self {
         let buffer = Arc::new(Mutex::new(Vec::<(f32,f32)>::new()));
         let buffer_reader = BufferReader::new(buffer.clone(), duration);
@@ -85,22 +83,20 @@ impl SoundSource for RecirculatingDelay {
     fn init_state(&self) -> SoundData {
         Box::new(RecirculatingDelayData{source_data: self.source.init_state()})
     }
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<RecirculatingDelayData>().unwrap();
         self.source.next_value(n, &mut data.source_data)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.source.duration()
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
-        let delay = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
+        let delay = params[1].parse::<f64>().unwrap() * reader.sample_rate as f64;
         let delay_gain = params[2].parse::<f32>().unwrap();
-        let duration = params[3].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(RecirculatingDelay::new(input, delay.round() as i32, delay_gain, duration.round() as i32))
+        let duration = reader.get_duration(&params[3]);
+        Box::new(RecirculatingDelay::new(input, delay.round() as SampleTime, delay_gain, duration))
     }
 }
-
-}
\ No newline at end of file
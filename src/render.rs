@@ -0,0 +1,38 @@
+// Helpers for rendering a sound source offline, for use when embedding the
+// synth rather than playing it through an audio device.
+
+use crate::traits::{DynSoundSource, is_forever};
+
+// Render the whole of a sound source into a buffer of stereo samples
+pub fn render(source: &DynSoundSource) -> Vec<(f32, f32)> {
+    let duration = source.duration();
+    if is_forever(duration) {
+        panic!("Can't render a sound that goes on for ever, give it a duration");
+    }
+    let mut buf = Vec::<(f32, f32)>::with_capacity(duration.max(0) as usize);
+    let mut state = source.init_state();
+    for n in 0..duration {
+        buf.push(source.next_value(n, &mut state));
+    }
+    buf
+}
+
+// Write stereo samples to a 32 bit float wav file
+pub fn write_wav(filename: &str, sample_rate: i32, samples: &[(f32, f32)]) -> Result<(), hound::Error> {
+    let spec = hound::WavSpec {
+        channels: 2,
+        sample_rate: sample_rate as u32,
+        bits_per_sample: 32,
+        sample_format: hound::SampleFormat::Float,
+    };
+    let mut writer = hound::WavWriter::create(filename, spec)?;
+    for val in samples {
+        writer.write_sample(val.0)?;
+        writer.write_sample(val.1)?;
+    }
+    writer.finalize()
+}
+
+pub fn render_to_wav(source: &DynSoundSource, filename: &str, sample_rate: i32) -> Result<(), hound::Error> {
+    write_wav(filename, sample_rate, &render(source))
+}
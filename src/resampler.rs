@@ -0,0 +1,172 @@
+// Reads a signal between its samples, for changing sample rate or playback
+// speed. The cheap qualities interpolate between the nearest samples like a
+// wavetable. The sinc qualities are band limited: each output sample is a sum
+// of the input samples around it weighted by a Kaiser windowed sinc, so
+// nothing above nyquist gets through to alias.
+//
+// The windowed sinc is stored as a polyphase table: `PHASES` points between
+// each zero crossing, with linear interpolation between them. When reading
+// faster than one input sample per output sample the sinc is stretched so
+// that its cutoff follows the new nyquist, which means more taps per output.
+
+use std::f64::consts::PI;
+use std::sync::Arc;
+
+use crate::wavetable::cubic_interpolate;
+
+const PHASES: usize = 512;
+
+// Stretching the sinc for high speeds is capped so that one output sample
+// never needs more than this many times the usual taps
+const MAX_STRETCH: f64 = 16.0;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Quality {
+    Rounding,
+    Linear,
+    Cubic,
+    // Windowed sinc with 8, 16 and 32 zero crossings each side
+    Fast,
+    Medium,
+    Best,
+}
+
+impl Quality {
+    pub fn from_param(param: &str) -> Self {
+        match param.to_lowercase().as_str() {
+            "rounding" => Quality::Rounding,
+            "linear" => Quality::Linear,
+            "cubic" => Quality::Cubic,
+            "fast" => Quality::Fast,
+            "medium" => Quality::Medium,
+            "best" => Quality::Best,
+            _ => panic!("Quality must be rounding, linear, cubic, fast, medium or best")
+        }
+    }
+
+    // Zero crossings each side, Kaiser beta and cutoff as a fraction of nyquist
+    fn sinc_settings(&self) -> Option<(usize, f64, f64)> {
+        match self {
+            Quality::Fast => Some((8, 6.0, 0.85)),
+            Quality::Medium => Some((16, 8.0, 0.92)),
+            Quality::Best => Some((32, 10.0, 0.96)),
+            Quality::Rounding | Quality::Linear | Quality::Cubic => None,
+        }
+    }
+}
+
+// Zeroth order modified Bessel function of the first kind, for the Kaiser
+// window
+fn bessel_i0(x: f64) -> f64 {
+    let mut sum = 1.0;
+    let mut term = 1.0;
+    let mut k = 1.0;
+    while term > sum * 1e-12 {
+        term *= (x / (2.0 * k)) * (x / (2.0 * k));
+        sum += term;
+        k += 1.0;
+    }
+    sum
+}
+
+struct SincTable {
+    zero_crossings: usize,
+    cutoff: f64,
+    // The right half of the windowed sinc, PHASES points per zero crossing
+    table: Vec<f32>,
+}
+
+impl SincTable {
+    fn new(zero_crossings: usize, beta: f64, cutoff: f64) -> Self {
+        let len = zero_crossings * PHASES;
+        let table = (0..=len + 1).map(|i| {
+            let x = i as f64 / PHASES as f64;
+            let sinc = if i == 0 { 1.0 } else { (PI * x).sin() / (PI * x) };
+            let r = x / zero_crossings as f64;
+            let window = if r < 1.0 { bessel_i0(beta * (1.0 - r * r).sqrt()) / bessel_i0(beta) } else { 0.0 };
+            (sinc * window) as f32
+        }).collect();
+        SincTable { zero_crossings: zero_crossings, cutoff: cutoff, table: table }
+    }
+
+    // The windowed sinc at x zero crossings from its centre
+    fn value(&self, x: f64) -> f32 {
+        let x = x.abs() * PHASES as f64;
+        let i = x.floor() as usize;
+        if i >= self.zero_crossings * PHASES {
+            return 0.0;
+        }
+        let f = (x - i as f64) as f32;
+        self.table[i] + (self.table[i + 1] - self.table[i]) * f
+    }
+}
+
+#[derive(Clone)]
+pub struct Resampler {
+    quality: Quality,
+    sinc: Option<Arc<SincTable>>,
+}
+
+impl Resampler {
+    pub fn new(quality: Quality) -> Self {
+        let sinc = quality.sinc_settings()
+            .map(|(zero_crossings, beta, cutoff)| Arc::new(SincTable::new(zero_crossings, beta, cutoff)));
+        Resampler { quality: quality, sinc: sinc }
+    }
+
+    // How many input samples either side of a position `value_at` may read
+    pub fn reach(&self, speed: f64) -> usize {
+        match &self.sinc {
+            Some(sinc) => (sinc.zero_crossings as f64 * speed.abs().clamp(1.0, MAX_STRETCH) / sinc.cutoff).ceil() as usize + 1,
+            None => 2,
+        }
+    }
+
+    // The signal at `pos` (in input samples) when it is being read at `speed`
+    // input samples per output sample. `input` gives the sample at an index
+    // and should return silence outside the signal.
+    pub fn value_at(&self, input: impl Fn(i64) -> (f32, f32), pos: f64, speed: f64) -> (f32, f32) {
+        let x0 = pos.floor() as i64;
+        let f = (pos - x0 as f64) as f32;
+        match self.quality {
+            Quality::Rounding => input(pos.round() as i64),
+            Quality::Linear => {
+                let (y0, y1) = (input(x0), input(x0 + 1));
+                (y0.0 + (y1.0 - y0.0) * f, y0.1 + (y1.1 - y0.1) * f)
+            },
+            Quality::Cubic => {
+                let (ym1, y0, y1, y2) = (input(x0 - 1), input(x0), input(x0 + 1), input(x0 + 2));
+                (cubic_interpolate(f, ym1.0, y0.0, y1.0, y2.0), cubic_interpolate(f, ym1.1, y0.1, y1.1, y2.1))
+            },
+            Quality::Fast | Quality::Medium | Quality::Best => {
+                let sinc = self.sinc.as_ref().unwrap();
+                // Zero crossings per input sample, lowered when reading fast
+                // so the cutoff stays under the output's nyquist
+                let scale = sinc.cutoff / speed.abs().clamp(1.0, MAX_STRETCH);
+                let reach = self.reach(speed) as i64;
+                let (mut left, mut right) = (0.0, 0.0);
+                for x in x0 - reach + 1..=x0 + reach {
+                    let weight = sinc.value((pos - x as f64) * scale);
+                    if weight != 0.0 {
+                        let y = input(x);
+                        left += y.0 * weight;
+                        right += y.1 * weight;
+                    }
+                }
+                (left * scale as f32, right * scale as f32)
+            },
+        }
+    }
+}
+
+// Convert a whole buffer from one sample rate to another
+pub fn resample(samples: &[(f32, f32)], from_rate: i32, to_rate: i32, quality: Quality) -> Vec<(f32, f32)> {
+    if from_rate == to_rate {
+        return samples.to_vec();
+    }
+    let resampler = Resampler::new(quality);
+    let speed = from_rate as f64 / to_rate as f64;
+    let len = (samples.len() as f64 / speed).ceil() as usize;
+    let input = |x: i64| if x >= 0 && (x as usize) < samples.len() { samples[x as usize] } else { (0.0, 0.0) };
+    (0..len).map(|n| resampler.value_at(input, n as f64 * speed, speed)).collect()
+}
@@ -1,5 +1,3 @@
-pub mod reverberator {
-
 // This is a very basic reverberator example that uses too few delays, rotations
 // and feedback loops and lacks filtering. The hard coded parameters here were
 // chosen at random and there hasn't been much experimentation to make it sound
@@ -7,45 +5,54 @@ pub mod reverberator {
 
 use std::sync::{Arc, Mutex};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::buffer_reader::buffer_reader::BufferReader;
-use crate::buffer_writer::buffer_writer::BufferWriter;
-use crate::dc::dc::DC;
-use crate::mix::mix::Mix;
-use crate::multiply::multiply::Multiply;
-use crate::rotation_transfer::rotation_transfer::RotationTransfer;
-use crate::uneven_delay::uneven_delay::UnevenDelay;
+use crate::buffer_reader::BufferReader;
+use crate::buffer_writer::BufferWriter;
+use crate::dc::DC;
+use crate::mix::Mix;
+use crate::multiply::Multiply;
+use crate::rotation_transfer::RotationTransfer;
+use crate::uneven_delay::UnevenDelay;
 
 #[derive(Clone)]
 pub struct Reverberator {
     output: DynSoundSource,
-    duration: i32,
+    duration: SampleTime,
 }
 
+// Delay times in seconds
+const DELAY_1: f32 = 0.030;
+const DELAY_2: f32 = 0.055;
+const DELAY_3: f32 = 0.080;
+const RECIRCULATING_DELAY_LEFT: f32 = 0.070;
+const RECIRCULATING_DELAY_RIGHT: f32 = 0.040;
+
 impl Reverberator {
-    pub fn new(input: DynSoundSource, gain: f32) -> Self {
-        // We assume the sample rate is around 48000 for this code.
+    pub fn new(input: DynSoundSource, gain: f32, sample_rate: i32) -> Self {
+        let t2n = |t: f32| (t * sample_rate as f32).round() as SampleTime;
         // The usual measure of reverberation time (RT) is the time at which the
         // gain drops by sixty decibels. Add 10% to that for good measure.
+        let recirculating_delay = t2n(RECIRCULATING_DELAY_LEFT) + t2n(RECIRCULATING_DELAY_RIGHT);
         let duration;
         if gain < 1.0 {
-            duration = input.duration() + (-3.0 * (1920 + 3360) as f32 / 2.0 / gain.log10() * 1.1) as i32;
+            duration = input.duration() + (-3.0 * recirculating_delay as f32 / 2.0 / gain.log10() * 1.1) as SampleTime;
         } else {
             duration = input.duration();
         }
         let buffer = Arc::new(Mutex::new(Vec::<(f32,f32)>::new()));
         // Rotate + delaychain of: pi/10, 30ms, pi/10, 55ms, -pi/5, 80ms
         let rotation_1 = RotationTransfer::new(input, std::f32::consts::PI * 0.1);
-        let uneven_delay1 = UnevenDelay::new(Box::new(rotation_1), 0, 1440);
+        let uneven_delay1 = UnevenDelay::new(Box::new(rotation_1), 0, t2n(DELAY_1));
         let rotation_2 = RotationTransfer::new(Box::new(uneven_delay1), std::f32::consts::PI * 0.1);
-        let uneven_delay2 = UnevenDelay::new(Box::new(rotation_2), 0, 2640);
+        let uneven_delay2 = UnevenDelay::new(Box::new(rotation_2), 0, t2n(DELAY_2));
         let rotation_3 = RotationTransfer::new(Box::new(uneven_delay2), -std::f32::consts::PI * 0.2);
-        let uneven_delay3 = UnevenDelay::new(Box::new(rotation_3), 0, 3840);
+        let uneven_delay3 = UnevenDelay::new(Box::new(rotation_3), 0, t2n(DELAY_3));
         // recirculating delay 0.07, 0.04 and gain
         let buffer_reader = BufferReader::new(buffer.clone(), duration);
-        let uneven_delay4 = UnevenDelay::new(Box::new(buffer_reader), 3360, 1920);
+        let uneven_delay4 = UnevenDelay::new(Box::new(buffer_reader),
+            t2n(RECIRCULATING_DELAY_LEFT), t2n(RECIRCULATING_DELAY_RIGHT));
         let rotation_4 = RotationTransfer::new(Box::new(uneven_delay4), std::f32::consts::PI * 0.3);
         let mut recirculating_gain = Multiply::new();
         recirculating_gain.add(Box::new(DC::new(gain, duration)), 0.0);
@@ -66,21 +73,18 @@ impl SoundSource for Reverberator {
         Box::new( ReverberatorData { output_data: self.output.init_state() } )
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<ReverberatorData>().unwrap();
         self.output.next_value(n, &mut data.output_data)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
         let gain = params[1].parse::<f32>().unwrap();
-        Box::new(Reverberator::new(input, gain))
+        Box::new(Reverberator::new(input, gain, reader.sample_rate))
     }
 }
-
-
-}
@@ -1,7 +1,5 @@
-pub mod rotation_transfer {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub struct RotationTransfer {
@@ -28,7 +26,7 @@ impl SoundSource for RotationTransfer {
         })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<RotationTransferData>().unwrap();
         let c = self.angle_of_rotation.cos();
         let s = self.angle_of_rotation.sin();
@@ -38,7 +36,7 @@ impl SoundSource for RotationTransfer {
         (y0, y1)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.input.duration()
     }
 
@@ -49,5 +47,3 @@ impl SoundSource for RotationTransfer {
     }
 
 }
-
-}
\ No newline at end of file
@@ -0,0 +1,351 @@
+// A sampler plays recordings. Each zone maps a range of keys and velocities to
+// a sample, and notes are repitched from the zone's root key, so one recording
+// can cover several notes. Which zone plays is decided by the note's pitch
+// (rounded to the nearest key) and strength (strength 1.0 is velocity 127).
+// When zones overlap they all play.
+//
+// Samples can loop so that a short recording can hold a long note. Loop points
+// are read from the WAV file's smpl chunk if it has one, along with the root
+// key. The loop modes are:
+//   no_loop          play the sample once, cut short at the end of the release
+//   one_shot         play the whole sample whatever the note length
+//   loop_continuous  loop until the release has finished
+//   loop_sustain     loop until the note ends then play on through the release
+//
+// Each zone has an attack-decay-sustain-release amp envelope. The defaults are
+// those of SFZ: no attack or decay, full sustain and a 1 ms release.
+
+use std::fs;
+use std::sync::Arc;
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, Instrument, SampleTime, FOREVER};
+
+use crate::dc::DC;
+use crate::envelope::Envelope;
+use crate::import_wav::{read_audio, Channels};
+use crate::knob::Knob;
+use crate::midi_notes::{freq2midi, midi2freq};
+use crate::mix::Mix;
+use crate::wavetable::cubic_interpolate;
+
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum LoopMode {
+    NoLoop,
+    OneShot,
+    Continuous,
+    Sustain,
+}
+
+impl LoopMode {
+    pub fn from_param(param: &str) -> Self {
+        match param {
+            "no_loop" => LoopMode::NoLoop,
+            "one_shot" => LoopMode::OneShot,
+            "loop_continuous" => LoopMode::Continuous,
+            "loop_sustain" => LoopMode::Sustain,
+            _ => panic!("Loop mode must be no_loop, one_shot, loop_continuous or loop_sustain")
+        }
+    }
+}
+
+// The parts of a WAV smpl chunk we use
+struct SmplChunk {
+    root_key: u8,
+    // Start and end of the first loop. The end is the last sample played.
+    loop_points: Option<(usize, usize)>,
+}
+
+fn read_u32(bytes: &[u8], pos: usize) -> Option<u32> {
+    Some(u32::from_le_bytes(bytes.get(pos..pos + 4)?.try_into().ok()?))
+}
+
+// hound doesn't read smpl chunks, so walk the RIFF chunks ourselves
+fn read_smpl_chunk(filename: &str) -> Option<SmplChunk> {
+    let bytes = fs::read(filename).ok()?;
+    if bytes.get(0..4)? != b"RIFF" || bytes.get(8..12)? != b"WAVE" {
+        return None;
+    }
+    let mut pos = 12;
+    while pos + 8 <= bytes.len() {
+        let id = &bytes[pos..pos + 4];
+        let size = read_u32(&bytes, pos + 4)? as usize;
+        let data = pos + 8;
+        if id == b"smpl" {
+            let root_key = read_u32(&bytes, data + 12)?.min(127) as u8;
+            let loop_count = read_u32(&bytes, data + 28)?;
+            let mut loop_points = None;
+            if loop_count > 0 {
+                // The first loop follows the 36 byte header: cue id, type,
+                // start, end, fraction and play count
+                let start = read_u32(&bytes, data + 36 + 8)? as usize;
+                let end = read_u32(&bytes, data + 36 + 12)? as usize;
+                if end > start {
+                    loop_points = Some((start, end));
+                }
+            }
+            return Some(SmplChunk { root_key: root_key, loop_points: loop_points });
+        }
+        // Chunks are padded to an even length
+        pos = data + size + (size & 1);
+    }
+    None
+}
+
+pub struct Sample {
+    samples: Vec<(f32, f32)>,
+    sample_rate: i32,
+    root_key: Option<u8>,
+    loop_points: Option<(usize, usize)>,
+}
+
+impl Sample {
+    pub fn new(samples: Vec<(f32, f32)>, sample_rate: i32, root_key: Option<u8>, loop_points: Option<(usize, usize)>) -> Self {
+        Sample { samples: samples, sample_rate: sample_rate, root_key: root_key, loop_points: loop_points }
+    }
+
+    pub fn load(filename: &str) -> Self {
+        let (samples, sample_rate) = read_audio(filename, Channels::Auto);
+        let smpl = read_smpl_chunk(filename);
+        let root_key = smpl.as_ref().map(|smpl| smpl.root_key);
+        let loop_points = smpl.and_then(|smpl| smpl.loop_points);
+        Self::new(samples, sample_rate, root_key, loop_points)
+    }
+
+    pub fn root_key(&self) -> Option<u8> {
+        self.root_key
+    }
+
+    pub fn loop_points(&self) -> Option<(usize, usize)> {
+        self.loop_points
+    }
+
+    // In samples per channel
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    // Inside a loop the samples after its end are those at its start, so the
+    // seam is interpolated across as well
+    fn value_at(&self, pos: f64, loop_points: Option<(usize, usize)>) -> (f32, f32) {
+        let len = self.samples.len() as i64;
+        let x0 = pos.floor() as i64;
+        let f = (pos - x0 as f64) as f32;
+        let y = |x: i64| {
+            let x = match loop_points {
+                Some((start, end)) if x0 >= start as i64 && x > end as i64 => x - (end + 1 - start) as i64,
+                _ => x,
+            };
+            self.samples[x.clamp(0, len - 1) as usize]
+        };
+        let (ym1, y0, y1, y2) = (y(x0 - 1), y(x0), y(x0 + 1), y(x0 + 2));
+        (cubic_interpolate(f, ym1.0, y0.0, y1.0, y2.0), cubic_interpolate(f, ym1.1, y0.1, y1.1, y2.1))
+    }
+}
+
+// Times are in seconds, tune is in cents, gain is linear and pan runs from -1
+// (left) to 1 (right)
+#[derive(Clone)]
+pub struct SampleZone {
+    pub sample: Arc<Sample>,
+    pub root_key: u8,
+    pub lokey: u8,
+    pub hikey: u8,
+    pub lovel: u8,
+    pub hivel: u8,
+    pub tune: f32,
+    pub gain: f32,
+    pub pan: f32,
+    pub loop_mode: LoopMode,
+    // Start and end of the loop. The end is the last sample played.
+    pub loop_points: Option<(usize, usize)>,
+    pub attack: f32,
+    pub decay: f32,
+    pub sustain: f32,
+    pub release: f32,
+}
+
+impl SampleZone {
+    // A zone for all velocities. The root key and loop come from the sample if
+    // it has them, otherwise the root key is middle C and it doesn't loop.
+    pub fn new(sample: Arc<Sample>, lokey: u8, hikey: u8) -> Self {
+        let loop_points = fit_loop(sample.loop_points(), sample.len());
+        SampleZone {
+            root_key: sample.root_key().unwrap_or(60),
+            sample: sample,
+            lokey: lokey,
+            hikey: hikey,
+            lovel: 0,
+            hivel: 127,
+            tune: 0.0,
+            gain: 1.0,
+            pan: 0.0,
+            loop_mode: if loop_points.is_some() { LoopMode::Continuous } else { LoopMode::NoLoop },
+            loop_points: loop_points,
+            attack: 0.0,
+            decay: 0.0,
+            sustain: 1.0,
+            release: 0.001,
+        }
+    }
+
+    pub fn contains(&self, key: u8, velocity: u8) -> bool {
+        (self.lokey..=self.hikey).contains(&key) && (self.lovel..=self.hivel).contains(&velocity)
+    }
+}
+
+// Cut a loop off at the last sample, or drop it if nothing is left
+fn fit_loop(loop_points: Option<(usize, usize)>, len: usize) -> Option<(usize, usize)> {
+    let (start, end) = loop_points?;
+    let end = end.min(len.checked_sub(1)?);
+    if start < end { Some((start, end)) } else { None }
+}
+
+#[derive(Clone)]
+pub struct SamplerVoice {
+    sample: Arc<Sample>,
+    speed: f64, // Samples of the recording per output sample
+    loop_mode: LoopMode,
+    loop_points: Option<(usize, usize)>,
+    envelope: Knob,
+    pan: f32,
+    note_duration: SampleTime,
+    duration: SampleTime,
+}
+
+struct SamplerVoiceData {
+    envelope_data: SoundData,
+    pos: f64,
+}
+
+impl SoundSource for SamplerVoice {
+    fn init_state(&self) -> SoundData {
+        Box::new(SamplerVoiceData { envelope_data: self.envelope.init_state(), pos: 0.0 })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<SamplerVoiceData>().unwrap();
+        let gain = self.envelope.next_value(n, &mut data.envelope_data);
+        let looping = match self.loop_mode {
+            LoopMode::Continuous => true,
+            LoopMode::Sustain => n < self.note_duration,
+            LoopMode::NoLoop | LoopMode::OneShot => false,
+        };
+        let loop_points = if looping { self.loop_points } else { None };
+        // Wrap before checking for the end so a loop that ends on the last
+        // sample keeps going
+        if let Some((start, end)) = loop_points {
+            let loop_len = (end + 1 - start) as f64;
+            while data.pos >= (end + 1) as f64 {
+                data.pos -= loop_len;
+            }
+        }
+        if data.pos >= self.sample.samples.len() as f64 {
+            return (0.0, 0.0);
+        }
+        let (l, r) = self.sample.value_at(data.pos, loop_points);
+        data.pos += self.speed;
+        // Panning turns down one side, so a centred sample is unchanged
+        let left = gain * (1.0 - self.pan).min(1.0);
+        let right = gain * (1.0 + self.pan).min(1.0);
+        (l * left, r * right)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    // Play one note from a WAV, SFZ or SF2 file
+    // params: filename, note, strength, duration, [SF2 preset as bank:program]
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let sampler = reader.get_sampler(&params[0], params.get(4).map(|preset| preset.as_str()));
+        let freq = reader.get_note(&params[1]) / reader.sample_rate as f32;
+        let strength = params[2].parse::<f32>().unwrap();
+        let duration = reader.get_duration(&params[3]);
+        sampler.play(freq, duration, strength)
+    }
+}
+
+#[derive(Clone)]
+pub struct Sampler {
+    sample_rate: i32,
+    zones: Vec<SampleZone>,
+}
+
+impl Sampler {
+    pub fn new(sample_rate: i32, zones: Vec<SampleZone>) -> Self {
+        Sampler { sample_rate: sample_rate, zones: zones }
+    }
+
+    pub fn zones(&self) -> &[SampleZone] {
+        &self.zones
+    }
+
+    fn t2n(&self, t: f32) -> SampleTime {
+        (t * self.sample_rate as f32).round() as SampleTime
+    }
+
+    // A voice for a note of `duration` samples using the given zone
+    pub fn voice(&self, zone: &SampleZone, freq: f32, duration: SampleTime, strength: f32) -> SamplerVoice {
+        let note_freq = freq * self.sample_rate as f32;
+        let root_freq = midi2freq(zone.root_key as i8);
+        let speed = note_freq as f64 / root_freq as f64
+            * zone.sample.sample_rate as f64 / self.sample_rate as f64
+            * 2.0_f64.powf(zone.tune as f64 / 1200.0);
+        let sample_duration = (zone.sample.samples.len() as f64 / speed).ceil().min(FOREVER as f64) as SampleTime;
+        let note_duration = if zone.loop_mode == LoopMode::OneShot { sample_duration } else { duration };
+        let release = if zone.loop_mode == LoopMode::OneShot { 0 } else { self.t2n(zone.release) };
+        let envelope = Envelope::adsr(strength * zone.gain, self.t2n(zone.attack), self.t2n(zone.decay),
+            zone.sustain, release, note_duration);
+        let loop_points = fit_loop(zone.loop_points, zone.sample.len());
+        let mut voice_duration = note_duration + release;
+        let loops = loop_points.is_some() && matches!(zone.loop_mode, LoopMode::Continuous | LoopMode::Sustain);
+        if !loops {
+            voice_duration = voice_duration.min(sample_duration);
+        }
+        SamplerVoice {
+            sample: zone.sample.clone(),
+            speed: speed,
+            loop_mode: zone.loop_mode,
+            loop_points: loop_points,
+            envelope: Knob::new(Box::new(envelope)),
+            pan: zone.pan.clamp(-1.0, 1.0),
+            note_duration: note_duration,
+            duration: voice_duration,
+        }
+    }
+}
+
+impl Instrument for Sampler {
+    fn play(&self, freq: f32, duration: SampleTime, strength: f32) -> DynSoundSource {
+        let key = freq2midi(freq * self.sample_rate as f32).round().clamp(0.0, 127.0) as u8;
+        let velocity = (strength * 127.0).round().clamp(1.0, 127.0) as u8;
+        // Every zone covering the note plays, which layers stereo pairs and
+        // stacked samples
+        let mut voices: Vec<DynSoundSource> = self.zones.iter()
+            .filter(|zone| zone.contains(key, velocity))
+            .map(|zone| Box::new(self.voice(zone, freq, duration, strength)) as DynSoundSource)
+            .collect();
+        match voices.len() {
+            0 => {
+                println!("Sampler has no zone for key {} velocity {}", key, velocity);
+                Box::new(DC::new(0.0, duration))
+            },
+            1 => voices.pop().unwrap(),
+            _ => {
+                let mut mix = Mix::new();
+                for voice in voices {
+                    mix.add(voice);
+                }
+                Box::new(mix)
+            }
+        }
+    }
+}
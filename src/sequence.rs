@@ -1,26 +1,24 @@
-pub mod sequence {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime, FOREVER};
 
 
 #[derive(Clone)]
 struct SequenceMember {
     sound_source: DynSoundSource,
-    start_time: i32,
+    start_time: SampleTime,
 }
 
 struct SequenceMemberData {
     note_source_data: SoundData,
     is_playing: bool,
-    playing_start_time: i32
+    playing_start_time: SampleTime
 }
 
 #[derive(Clone)]
 pub struct Sequence {
     notes: Vec<SequenceMember>,
     repeat: u32,
-    duration: i32
+    duration: SampleTime
 }
 
 impl Sequence {
@@ -29,22 +27,22 @@ impl Sequence {
     }
 
     // Add notes into the sequence at arbitrary time offsets
-    pub fn add(&mut self, start_time: i32, note: DynSoundSource) -> &mut Sequence {
+    pub fn add(&mut self, start_time: SampleTime, note: DynSoundSource) -> &mut Sequence {
         self.notes.push( SequenceMember { sound_source: note, start_time: start_time } );
         self.duration = self.calculate_duration();
         self
     }
 
     // Use new for evenly spaced notes (or pass empty notes vector)
-    pub fn new_with_sequence(period: i32, mut notes: Vec<DynSoundSource>, repeat: u32) -> Self {
+    pub fn new_with_sequence(period: SampleTime, mut notes: Vec<DynSoundSource>, repeat: u32) -> Self {
         let mut seq = Sequence::new();
         seq.repeat = repeat;
-        let mut t_idx: i32 = 0;
+        let mut t_idx: SampleTime = 0;
         for note in notes.drain(..) {
             seq.add(t_idx, note);
             t_idx += period;
         }
-        seq.duration = seq.notes.len() as i32 * period;
+        seq.duration = seq.notes.len() as SampleTime * period;
         seq
     }
 
@@ -52,15 +50,15 @@ impl Sequence {
         self.repeat = repeat;
     }
 
-    pub fn set_duration(&mut self, duration: i32) {
+    pub fn set_duration(&mut self, duration: SampleTime) {
         self.duration = duration;
     }
 
-    fn calculate_duration(&self) -> i32 {
+    fn calculate_duration(&self) -> SampleTime {
         // self.duration is subtly different to calculated_duration. The first case is the time
         // we use to start repeating and doesn't include any 'ring' time of notes that overlap.
         // This function does account for ring time of whatever notes are playing.
-        let mut duration: i32 = 0;
+        let mut duration: SampleTime = 0;
         for note in self.notes.iter() {
             duration = duration.max((*note.sound_source).duration() + note.start_time)
         }
@@ -82,32 +80,26 @@ impl SoundSource for Sequence {
         Box::new(res)
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<Vec::<SequenceMemberData>>().unwrap();
         let mut res1: f32 = 0.0;
         let mut res2: f32 = 0.0;
-        let mut time_offset: i32 = 0;
-        let mut repeat_count: u32 = 0;
-        while n - time_offset >= self.duration {
-            time_offset += self.duration;
-            repeat_count += 1;
-        }
-        if repeat_count < self.repeat {
-            let mut note_idx: usize = 0;
-            for note in &self.notes {
-                let mut note_data = &mut data[note_idx];
+        // Which repeat we are in and when it started
+        let repeat_count = if self.duration > 0 { n / self.duration } else { 0 };
+        let time_offset = repeat_count * self.duration;
+        if repeat_count < self.repeat as SampleTime {
+            for (note_idx, note) in self.notes.iter().enumerate() {
+                let note_data = &mut data[note_idx];
                 if n - time_offset >= note.start_time
                         && n - time_offset < note.start_time + note.sound_source.duration()
                         && !note_data.is_playing {
                             note_data.is_playing = true;
                     note_data.playing_start_time = time_offset + note.start_time;
                 }
-                note_idx += 1;
             }
         }
-        let mut note_idx: usize = 0;
-        for note in &self.notes {
-            let mut note_data = &mut data[note_idx];
+        for (note_idx, note) in self.notes.iter().enumerate() {
+            let note_data = &mut data[note_idx];
             if note_data.is_playing {
                 if n - note_data.playing_start_time < note.sound_source.duration() {
                     let (v1, v2) = note.sound_source.next_value(n - note_data.playing_start_time, &mut note_data.note_source_data);
@@ -117,31 +109,29 @@ impl SoundSource for Sequence {
                     note_data.is_playing = false;
                 }
             }
-            note_idx += 1;
         }
         (res1, res2)
     }
 
-    fn duration(&self) -> i32 {
-        self.calculate_duration() * self.repeat as i32
+    fn duration(&self) -> SampleTime {
+        // Saturate so that repeating a sound that goes on for ever can't overflow
+        self.calculate_duration().saturating_mul(self.repeat as SampleTime).min(FOREVER)
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let mut sequence = Sequence::new();
         let repeats = params[0].parse::<u32>().unwrap();
         sequence.set_repeat(repeats);
-        let duration = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
+        let duration = reader.get_duration(&params[1]);
         for source_def in &params[2..] {
             let parts: Vec<_> = source_def.split(" ").collect();
-            let start_time = parts[0].parse::<f32>().unwrap() * reader.sample_rate as f32;
-            let source = reader.get_sound(&parts[1]);
-            sequence.add(start_time.round() as i32, source);
+            let start_time = parts[0].parse::<f64>().unwrap() * reader.sample_rate as f64;
+            let source = reader.get_sound(parts[1]);
+            sequence.add(start_time.round() as SampleTime, source);
         }
-        if duration > 0.0 {
-            sequence.set_duration(duration.round() as i32);
+        if duration > 0 {
+            sequence.set_duration(duration);
         }
         Box::new(sequence)
     }
 }
-
-}
\ No newline at end of file
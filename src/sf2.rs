@@ -0,0 +1,324 @@
+// Reads SoundFont 2 presets into a Sampler. An SF2 file is a RIFF file with
+// all the sample data in one block, a list of sample headers saying where each
+// sample starts, ends and loops, and two layers of zones on top:
+//   instruments map key and velocity ranges to samples
+//   presets (the sounds you pick by bank and program number) map key and
+//   velocity ranges to instruments
+// Each zone is a list of generators (settings). The first zone of a preset or
+// instrument is a global zone with defaults for the others if it doesn't pick
+// an instrument or sample. Preset generators are added to the instrument ones,
+// apart from key and velocity ranges which have to overlap.
+//
+// The generators understood are key and velocity ranges, root key override,
+// coarse and fine tune, sample modes (loops), attenuation, pan, the sample
+// address offsets and the volume envelope's attack, decay, sustain and release.
+// Stereo samples are played as a left and a right zone.
+
+use std::collections::HashMap;
+use std::fs;
+use std::sync::Arc;
+
+use crate::sampler::{Sample, SampleZone, Sampler, LoopMode};
+
+// Generator numbers from the SF2 spec
+const START_ADDRS_OFFSET: u16 = 0;
+const END_ADDRS_OFFSET: u16 = 1;
+const STARTLOOP_ADDRS_OFFSET: u16 = 2;
+const ENDLOOP_ADDRS_OFFSET: u16 = 3;
+const START_ADDRS_COARSE_OFFSET: u16 = 4;
+const END_ADDRS_COARSE_OFFSET: u16 = 12;
+const PAN: u16 = 17;
+const ATTACK_VOL_ENV: u16 = 34;
+const DECAY_VOL_ENV: u16 = 36;
+const SUSTAIN_VOL_ENV: u16 = 37;
+const RELEASE_VOL_ENV: u16 = 38;
+const INSTRUMENT: u16 = 41;
+const KEY_RANGE: u16 = 43;
+const VEL_RANGE: u16 = 44;
+const STARTLOOP_ADDRS_COARSE_OFFSET: u16 = 45;
+const INITIAL_ATTENUATION: u16 = 48;
+const ENDLOOP_ADDRS_COARSE_OFFSET: u16 = 50;
+const COARSE_TUNE: u16 = 51;
+const FINE_TUNE: u16 = 52;
+const SAMPLE_ID: u16 = 53;
+const SAMPLE_MODES: u16 = 54;
+const OVERRIDING_ROOT_KEY: u16 = 58;
+
+// Sample types
+const RIGHT_SAMPLE: u16 = 2;
+const LEFT_SAMPLE: u16 = 4;
+
+type Generators = HashMap<u16, [u8; 2]>;
+
+fn get_i32(generators: &Generators, generator: u16, default: i32) -> i32 {
+    generators.get(&generator).map(|amount| i16::from_le_bytes(*amount) as i32).unwrap_or(default)
+}
+
+fn get_range(generators: &Generators, generator: u16) -> (u8, u8) {
+    generators.get(&generator).map(|amount| (amount[0], amount[1])).unwrap_or((0, 127))
+}
+
+// Envelope times are in timecents, where 1200 is a doubling and 0 is a second
+fn timecents_to_secs(timecents: i32) -> f32 {
+    2.0_f32.powf(timecents as f32 / 1200.0)
+}
+
+// Attenuation is in centibels
+fn centibels_to_gain(centibels: i32) -> f32 {
+    10.0_f32.powf(-centibels as f32 / 200.0)
+}
+
+fn u16_at(bytes: &[u8], pos: usize) -> u16 {
+    u16::from_le_bytes([bytes[pos], bytes[pos + 1]])
+}
+
+fn u32_at(bytes: &[u8], pos: usize) -> u32 {
+    u32::from_le_bytes([bytes[pos], bytes[pos + 1], bytes[pos + 2], bytes[pos + 3]])
+}
+
+fn name_at(bytes: &[u8], pos: usize) -> String {
+    let name = &bytes[pos..pos + 20];
+    let len = name.iter().position(|c| *c == 0).unwrap_or(20);
+    String::from_utf8_lossy(&name[..len]).to_string()
+}
+
+// The (id, contents) of each chunk in a block of RIFF chunks
+fn read_chunks(mut bytes: &[u8]) -> Vec<(&[u8], &[u8])> {
+    let mut chunks = Vec::new();
+    while bytes.len() >= 8 {
+        let size = (u32_at(bytes, 4) as usize).min(bytes.len() - 8);
+        chunks.push((&bytes[0..4], &bytes[8..8 + size]));
+        // Chunks are padded to an even length
+        bytes = &bytes[(8 + size + (size & 1)).min(bytes.len())..];
+    }
+    chunks
+}
+
+// The chunks inside the LIST chunk of the given type
+fn read_list<'a>(chunks: &[(&[u8], &'a [u8])], list_type: &[u8]) -> HashMap<String, &'a [u8]> {
+    let mut found = HashMap::new();
+    for (id, contents) in chunks {
+        if *id == b"LIST" && contents.len() >= 4 && &contents[0..4] == list_type {
+            for (id, contents) in read_chunks(&contents[4..]) {
+                found.insert(String::from_utf8_lossy(id).to_string(), contents);
+            }
+        }
+    }
+    found
+}
+
+#[derive(Clone, Debug)]
+pub struct Sf2Preset {
+    pub bank: u16,
+    pub program: u16,
+    pub name: String,
+}
+
+struct SampleHeader {
+    start: usize,
+    end: usize,
+    loop_start: usize,
+    loop_end: usize,
+    sample_rate: i32,
+    original_pitch: u8,
+    pitch_correction: i8,
+    sample_type: u16,
+}
+
+pub struct SoundFont {
+    presets: Vec<(Sf2Preset, Vec<Generators>)>,
+    instruments: Vec<Vec<Generators>>,
+    sample_headers: Vec<SampleHeader>,
+    data: Vec<f32>,
+}
+
+// Read the zones of each preset or instrument. Each record (of
+// `record_size` bytes) has its first bag at `bag_offset`, and the last record
+// only marks the end of the bags before it.
+fn read_zones(records: &[u8], record_size: usize, bag_offset: usize, bags: &[u8], generators: &[u8]) -> Vec<Vec<Generators>> {
+    let count = records.len() / record_size;
+    let mut all_zones = Vec::new();
+    for i in 0..count.saturating_sub(1) {
+        let first_bag = u16_at(records, i * record_size + bag_offset) as usize;
+        let end_bag = u16_at(records, (i + 1) * record_size + bag_offset) as usize;
+        let mut zones = Vec::new();
+        for bag in first_bag..end_bag {
+            let first_gen = u16_at(bags, bag * 4) as usize;
+            let end_gen = u16_at(bags, (bag + 1) * 4) as usize;
+            let mut zone = Generators::new();
+            for gen in first_gen..end_gen {
+                let pos = gen * 4;
+                zone.insert(u16_at(generators, pos), [generators[pos + 2], generators[pos + 3]]);
+            }
+            zones.push(zone);
+        }
+        all_zones.push(zones);
+    }
+    all_zones
+}
+
+// Split off the global zone if there is one
+fn split_global(zones: &[Generators], marker: u16) -> (Generators, &[Generators]) {
+    match zones.first() {
+        Some(first) if !first.contains_key(&marker) => (first.clone(), &zones[1..]),
+        _ => (Generators::new(), zones),
+    }
+}
+
+impl SoundFont {
+    pub fn load(filename: &str) -> Self {
+        println!("Reading SF2 file: {}", filename);
+        let bytes = fs::read(filename).unwrap();
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"sfbk" {
+            panic!("{} isn't an SF2 file", filename);
+        }
+        let chunks = read_chunks(&bytes[12..]);
+        let sdta = read_list(&chunks, b"sdta");
+        let pdta = read_list(&chunks, b"pdta");
+        let pdta_chunk = |id: &str| *pdta.get(id).unwrap_or_else(|| panic!("{} has no {} chunk", filename, id));
+
+        // 16 bit samples, with an optional extra 8 bits of resolution
+        let smpl = sdta.get("smpl").unwrap_or_else(|| panic!("{} has no sample data", filename));
+        let sm24 = sdta.get("sm24").filter(|sm24| sm24.len() >= smpl.len() / 2);
+        let data = (0..smpl.len() / 2).map(|i| {
+            let high = i16::from_le_bytes([smpl[i * 2], smpl[i * 2 + 1]]) as i32;
+            match sm24 {
+                Some(sm24) => ((high << 8) | sm24[i] as i32) as f32 / 8388608.0,
+                None => high as f32 / 32768.0,
+            }
+        }).collect();
+
+        let phdr = pdta_chunk("phdr");
+        let preset_zones = read_zones(phdr, 38, 24, pdta_chunk("pbag"), pdta_chunk("pgen"));
+        let presets = preset_zones.into_iter().enumerate().map(|(i, zones)| {
+            let preset = Sf2Preset {
+                name: name_at(phdr, i * 38),
+                program: u16_at(phdr, i * 38 + 20),
+                bank: u16_at(phdr, i * 38 + 22),
+            };
+            (preset, zones)
+        }).collect();
+        let instruments = read_zones(pdta_chunk("inst"), 22, 20, pdta_chunk("ibag"), pdta_chunk("igen"));
+
+        let shdr = pdta_chunk("shdr");
+        let sample_headers = (0..shdr.len() / 46).map(|i| {
+            let pos = i * 46;
+            SampleHeader {
+                start: u32_at(shdr, pos + 20) as usize,
+                end: u32_at(shdr, pos + 24) as usize,
+                loop_start: u32_at(shdr, pos + 28) as usize,
+                loop_end: u32_at(shdr, pos + 32) as usize,
+                sample_rate: u32_at(shdr, pos + 36) as i32,
+                original_pitch: shdr[pos + 40],
+                pitch_correction: shdr[pos + 41] as i8,
+                sample_type: u16_at(shdr, pos + 44),
+            }
+        }).collect();
+
+        SoundFont { presets: presets, instruments: instruments, sample_headers: sample_headers, data: data }
+    }
+
+    pub fn presets(&self) -> Vec<Sf2Preset> {
+        self.presets.iter().map(|(preset, _)| preset.clone()).collect()
+    }
+
+    // A sampler playing the preset with this bank and program number
+    pub fn sampler(&self, bank: u16, program: u16, sample_rate: i32) -> Sampler {
+        let (preset, preset_zones) = match self.presets.iter().find(|(preset, _)| preset.bank == bank && preset.program == program) {
+            Some(found) => found,
+            None => panic!("There is no preset {}:{} in this SF2 file", bank, program),
+        };
+        println!("Using SF2 preset {}:{} {}", bank, program, preset.name);
+        let mut samples = HashMap::<(usize, usize, i32), Arc<Sample>>::new();
+        let mut zones = Vec::new();
+        let (preset_global, preset_zones) = split_global(preset_zones, INSTRUMENT);
+        for preset_zone in preset_zones {
+            let mut preset_gens = preset_global.clone();
+            preset_gens.extend(preset_zone);
+            let instrument = match self.instruments.get(get_i32(&preset_gens, INSTRUMENT, -1) as u16 as usize) {
+                Some(instrument) => instrument,
+                None => continue,
+            };
+            let (instrument_global, instrument_zones) = split_global(instrument, SAMPLE_ID);
+            for instrument_zone in instrument_zones {
+                let mut gens = instrument_global.clone();
+                gens.extend(instrument_zone);
+                let header = match self.sample_headers.get(get_i32(&gens, SAMPLE_ID, -1) as u16 as usize) {
+                    Some(header) => header,
+                    None => continue,
+                };
+                let (lokey, hikey) = get_range(&gens, KEY_RANGE);
+                let (preset_lokey, preset_hikey) = get_range(&preset_gens, KEY_RANGE);
+                let (lovel, hivel) = get_range(&gens, VEL_RANGE);
+                let (preset_lovel, preset_hivel) = get_range(&preset_gens, VEL_RANGE);
+                let (lokey, hikey) = (lokey.max(preset_lokey), hikey.min(preset_hikey));
+                let (lovel, hivel) = (lovel.max(preset_lovel), hivel.min(preset_hivel));
+                if lokey > hikey || lovel > hivel {
+                    continue;
+                }
+                // Preset generators add to the instrument ones
+                let total = |generator: u16, default: i32| get_i32(&gens, generator, default) + get_i32(&preset_gens, generator, 0);
+
+                let offset = |fine: u16, coarse: u16| total(fine, 0) + total(coarse, 0) * 32768;
+                let clamp = |addr: i64| addr.clamp(0, self.data.len() as i64) as usize;
+                let start = clamp(header.start as i64 + offset(START_ADDRS_OFFSET, START_ADDRS_COARSE_OFFSET) as i64);
+                let end = clamp(header.end as i64 + offset(END_ADDRS_OFFSET, END_ADDRS_COARSE_OFFSET) as i64).max(start + 1);
+                let loop_start = header.loop_start as i64 + offset(STARTLOOP_ADDRS_OFFSET, STARTLOOP_ADDRS_COARSE_OFFSET) as i64;
+                let loop_end = header.loop_end as i64 + offset(ENDLOOP_ADDRS_OFFSET, ENDLOOP_ADDRS_COARSE_OFFSET) as i64;
+
+                let sample = samples.entry((start, end, header.sample_rate)).or_insert_with(|| {
+                    let data = self.data[start..end.min(self.data.len())].iter().map(|v| (*v, *v)).collect();
+                    Arc::new(Sample::new(data, header.sample_rate, None, None))
+                }).clone();
+                let mut zone = SampleZone::new(sample, lokey, hikey);
+                zone.lovel = lovel;
+                zone.hivel = hivel;
+                zone.root_key = match get_i32(&gens, OVERRIDING_ROOT_KEY, -1) {
+                    key @ 0..=127 => key as u8,
+                    _ if header.original_pitch <= 127 => header.original_pitch,
+                    _ => 60,
+                };
+                zone.tune = (total(COARSE_TUNE, 0) * 100 + total(FINE_TUNE, 0) + header.pitch_correction as i32) as f32;
+                zone.gain = centibels_to_gain(total(INITIAL_ATTENUATION, 0).max(0));
+                zone.pan = match (gens.contains_key(&PAN), header.sample_type) {
+                    (false, LEFT_SAMPLE) => -1.0,
+                    (false, RIGHT_SAMPLE) => 1.0,
+                    _ => total(PAN, 0) as f32 / 500.0,
+                };
+                zone.loop_mode = match get_i32(&gens, SAMPLE_MODES, 0) {
+                    1 => LoopMode::Continuous,
+                    3 => LoopMode::Sustain,
+                    _ => LoopMode::NoLoop,
+                };
+                // SF2 loop ends are the sample after the loop
+                if loop_start >= start as i64 && loop_end > loop_start + 1 {
+                    zone.loop_points = Some(((loop_start - start as i64) as usize, (loop_end - start as i64 - 1) as usize));
+                } else {
+                    zone.loop_mode = LoopMode::NoLoop;
+                }
+                zone.attack = timecents_to_secs(total(ATTACK_VOL_ENV, -12000));
+                zone.decay = timecents_to_secs(total(DECAY_VOL_ENV, -12000));
+                zone.sustain = centibels_to_gain(total(SUSTAIN_VOL_ENV, 0).clamp(0, 1440));
+                zone.release = timecents_to_secs(total(RELEASE_VOL_ENV, -12000));
+                zones.push(zone);
+            }
+        }
+        println!("Read {} zones", zones.len());
+        Sampler::new(sample_rate, zones)
+    }
+}
+
+// Presets are given as "bank:program" or just "program" for bank 0
+pub fn parse_preset(preset: &str) -> (u16, u16) {
+    let parse = |number: &str| number.trim().parse::<u16>()
+        .unwrap_or_else(|_| panic!("SF2 preset must be \"bank:program\" or \"program\", not \"{}\"", preset));
+    match preset.split_once(':') {
+        Some((bank, program)) => (parse(bank), parse(program)),
+        None => (0, parse(preset)),
+    }
+}
+
+pub fn read_sf2(filename: &str, preset: &str, sample_rate: i32) -> Sampler {
+    let (bank, program) = parse_preset(preset);
+    SoundFont::load(filename).sampler(bank, program, sample_rate)
+}
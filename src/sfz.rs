@@ -0,0 +1,238 @@
+// Reads SFZ instruments into a Sampler. SFZ is a plain text format: headers
+// like <region> start a section and opcodes like lokey=60 set its values.
+// Opcodes in <global>, <master> and <group> sections are defaults for the
+// regions that follow them, and each region becomes one SampleZone.
+//
+// The opcodes understood are:
+//   sample, default_path (in <control>)
+//   key, lokey, hikey, pitch_keycenter, lovel, hivel
+//   loop_mode, loop_start, loop_end (or loopstart, loopend)
+//   tune, transpose, volume, pan
+//   ampeg_attack, ampeg_decay, ampeg_sustain, ampeg_release
+// Anything else is reported and ignored. Keys can be numbers or note names
+// like c#4, where c4 is middle C (60).
+
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::sampler::{Sample, SampleZone, Sampler, LoopMode};
+
+fn parse_key(value: &str) -> u8 {
+    if let Ok(key) = value.parse::<i32>() {
+        return key.clamp(0, 127) as u8;
+    }
+    let value = value.to_lowercase();
+    let mut chars = value.chars();
+    let semitone = match chars.next() {
+        Some('c') => 0, Some('d') => 2, Some('e') => 4, Some('f') => 5,
+        Some('g') => 7, Some('a') => 9, Some('b') => 11,
+        _ => panic!("Couldn't parse \"{}\" as an SFZ key", value)
+    };
+    let rest = chars.as_str();
+    let (accidental, octave) = match rest.chars().next() {
+        Some('#') => (1, &rest[1..]),
+        Some('b') => (-1, &rest[1..]),
+        _ => (0, rest),
+    };
+    let octave = octave.parse::<i32>().unwrap_or_else(|_| panic!("Couldn't parse \"{}\" as an SFZ key", value));
+    ((octave + 1) * 12 + semitone + accidental).clamp(0, 127) as u8
+}
+
+fn parse_velocity(name: &str, value: &str) -> u8 {
+    value.parse::<i32>().unwrap_or_else(|_| panic!("SFZ opcode {} should be a whole number, not \"{}\"", name, value)).clamp(0, 127) as u8
+}
+
+fn parse_f32(name: &str, value: &str) -> f32 {
+    value.parse::<f32>().unwrap_or_else(|_| panic!("SFZ opcode {} should be a number, not \"{}\"", name, value))
+}
+
+// Split one line into opcodes. Values run up to the next opcode, so sample
+// names can have spaces in them.
+fn parse_opcodes(line: &str, opcodes: &mut HashMap<String, String>) {
+    let mut current: Option<(String, String)> = None;
+    for token in line.split_whitespace() {
+        let new_opcode = token.split_once('=')
+            .filter(|(name, _)| !name.is_empty() && name.chars().all(|c| c.is_alphanumeric() || c == '_'));
+        match new_opcode {
+            Some((name, value)) => {
+                if let Some((name, value)) = current.take() {
+                    opcodes.insert(name, value);
+                }
+                current = Some((name.to_string(), value.to_string()));
+            },
+            None => match &mut current {
+                Some((_, value)) => {
+                    value.push(' ');
+                    value.push_str(token);
+                },
+                None => println!("Ignoring SFZ text \"{}\"", token),
+            }
+        }
+    }
+    if let Some((name, value)) = current {
+        opcodes.insert(name, value);
+    }
+}
+
+struct SfzReader {
+    dir: String,
+    samples: HashMap<String, Arc<Sample>>,
+    ignored: HashSet<String>,
+}
+
+impl SfzReader {
+    fn get_sample(&mut self, filename: &str) -> Arc<Sample> {
+        if let Some(sample) = self.samples.get(filename) {
+            sample.clone()
+        } else {
+            let sample = Arc::new(Sample::load(filename));
+            self.samples.insert(filename.to_string(), sample.clone());
+            sample
+        }
+    }
+
+    // Make a zone from the opcodes of a region and the headings above it
+    fn make_zone(&mut self, levels: &[HashMap<String, String>; 5]) -> Option<SampleZone> {
+        let control = &levels[Level::Control as usize];
+        let levels = &levels[Level::Global as usize..];
+        let sample_name = match levels.iter().rev().find_map(|opcodes| opcodes.get("sample")) {
+            Some(sample) => sample.replace('\\', "/"),
+            None => {
+                println!("Skipping SFZ region without a sample");
+                return None;
+            }
+        };
+        let default_path = control.get("default_path").map(|path| path.replace('\\', "/")).unwrap_or_default();
+        let path = Path::new(&self.dir).join(default_path).join(sample_name);
+        let sample = self.get_sample(path.to_str().unwrap());
+        let mut zone = SampleZone::new(sample, 0, 127);
+        zone.lovel = 1;
+        let mut transpose = 0.0;
+        let (mut loop_start, mut loop_end) = (None, None);
+        // Each level overrides the ones above it. Within a level `key` goes
+        // first so that lokey, hikey and pitch_keycenter win over it, and the
+        // rest go in a fixed order so the result is the same every time.
+        for opcodes in levels {
+            if let Some(value) = opcodes.get("key") {
+                let key = parse_key(value);
+                (zone.lokey, zone.hikey, zone.root_key) = (key, key, key);
+            }
+            let mut names: Vec<_> = opcodes.keys().collect();
+            names.sort();
+            for name in names {
+                let value = &opcodes[name];
+                match name.as_str() {
+                    "sample" | "key" => {},
+                    "lokey" => zone.lokey = parse_key(value),
+                    "hikey" => zone.hikey = parse_key(value),
+                    "pitch_keycenter" => zone.root_key = parse_key(value),
+                    "lovel" => zone.lovel = parse_velocity(name, value),
+                    "hivel" => zone.hivel = parse_velocity(name, value),
+                    "loop_mode" | "loopmode" => zone.loop_mode = LoopMode::from_param(value),
+                    "loop_start" | "loopstart" => loop_start = Some(parse_f32(name, value) as usize),
+                    "loop_end" | "loopend" => loop_end = Some(parse_f32(name, value) as usize),
+                    "tune" => zone.tune = parse_f32(name, value),
+                    "transpose" => transpose = parse_f32(name, value),
+                    "volume" => zone.gain = 10.0_f32.powf(parse_f32(name, value) / 20.0),
+                    "pan" => zone.pan = parse_f32(name, value) / 100.0,
+                    "ampeg_attack" => zone.attack = parse_f32(name, value),
+                    "ampeg_decay" => zone.decay = parse_f32(name, value),
+                    "ampeg_sustain" => zone.sustain = parse_f32(name, value) / 100.0,
+                    "ampeg_release" => zone.release = parse_f32(name, value),
+                    _ => if self.ignored.insert(name.clone()) {
+                        println!("Ignoring SFZ opcode {}", name);
+                    }
+                }
+            }
+        }
+        if let (Some(start), Some(end)) = (loop_start, loop_end) {
+            if start < end && end < zone.sample.len() {
+                zone.loop_points = Some((start, end));
+            } else {
+                println!("Not looping {}, loop {}..{} doesn't fit in its {} samples",
+                    path.display(), start, end, zone.sample.len());
+                zone.loop_points = None;
+                zone.loop_mode = LoopMode::NoLoop;
+            }
+        }
+        zone.tune += transpose * 100.0;
+        Some(zone)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Level {
+    Control,
+    Global,
+    Master,
+    Group,
+    Region,
+}
+
+pub fn read_sfz(filename: &str, sample_rate: i32) -> Sampler {
+    println!("Reading SFZ file: {}", filename);
+    let text = fs::read_to_string(filename).unwrap();
+    let dir = Path::new(filename).parent().and_then(|dir| dir.to_str()).unwrap_or("");
+    parse_sfz(&text, dir, sample_rate)
+}
+
+// Samples are found relative to dir
+pub fn parse_sfz(text: &str, dir: &str, sample_rate: i32) -> Sampler {
+    let mut reader = SfzReader {
+        dir: dir.to_string(),
+        samples: HashMap::new(),
+        ignored: HashSet::new(),
+    };
+    // Opcodes for each level of heading, from <control> to <region>
+    let mut opcodes: [HashMap<String, String>; 5] = Default::default();
+    let mut level = None;
+    let mut zones = Vec::new();
+
+    // Put headers on lines of their own
+    let text = text.lines()
+        .map(|line| line.split("//").next().unwrap())
+        .collect::<Vec<_>>().join("\n")
+        .replace('<', "\n<").replace('>', ">\n");
+    for line in text.lines() {
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if line.starts_with('#') {
+            println!("Ignoring SFZ directive {}", line);
+            continue;
+        }
+        if line.starts_with('<') {
+            if level == Some(Level::Region) {
+                zones.extend(reader.make_zone(&opcodes));
+            }
+            let new_level = match line {
+                "<control>" => Level::Control,
+                "<global>" => Level::Global,
+                "<master>" => Level::Master,
+                "<group>" => Level::Group,
+                "<region>" => Level::Region,
+                _ => {
+                    println!("Ignoring SFZ header {}", line);
+                    level = None;
+                    continue;
+                }
+            };
+            // A new heading clears its own opcodes and those of the levels
+            // below it
+            for level_opcodes in opcodes[new_level as usize..].iter_mut() {
+                level_opcodes.clear();
+            }
+            level = Some(new_level);
+        } else if let Some(level) = level {
+            parse_opcodes(line, &mut opcodes[level as usize]);
+        }
+    }
+    if level == Some(Level::Region) {
+        zones.extend(reader.make_zone(&opcodes));
+    }
+    println!("Read {} regions", zones.len());
+    Sampler::new(sample_rate, zones)
+}
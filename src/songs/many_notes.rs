@@ -1,11 +1,9 @@
-pub mod many_notes {
-
-use crate::traits::traits::{DynSoundSource, DynInstrument};
-use crate::midi_notes::midi_notes::midi2freq;
-use crate::sequence::sequence::Sequence;
+use crate::traits::{DynSoundSource, DynInstrument, SampleTime};
+use crate::midi_notes::midi2freq;
+use crate::sequence::Sequence;
 
 pub fn many_notes(sample_rate: i32, instrument: DynInstrument)  -> DynSoundSource {
-    let note_duration = 1 * sample_rate / 6;
+    let note_duration = sample_rate as SampleTime / 6;
 
     let mut vec = Vec::<DynSoundSource>::new();
     for n in [58, 63, 70, 72, 65, 60, 67, 62] {
@@ -14,5 +12,3 @@ pub fn many_notes(sample_rate: i32, instrument: DynInstrument)  -> DynSoundSourc
     let sound_source = Sequence::new_with_sequence(note_duration, vec, 10);
     Box::new(sound_source)
 }
-
-}
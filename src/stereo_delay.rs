@@ -0,0 +1,181 @@
+// An echo effect with its own delay for each side, so the echoes can fall at
+// different times on the left and right, or bounce from side to side in ping
+// pong mode. The echoes are fed back through a filter, so with a low pass each
+// echo comes back duller than the last, like tape echo, and with a high pass
+// thinner.
+//
+// In ping pong mode the input is mixed to mono and goes into the left delay,
+// the left delay's echoes go into the right delay and the right delay's
+// echoes come back round to the left. The first echo comes after the left
+// time and the ones after come alternately after the right and left times.
+//
+// The feedback is the level of each echo relative to the one before, and the
+// tail runs until the echoes have died away by 60 dB.
+
+use crate::delay_buffer::DelayBuffer;
+use crate::knob::Knob;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::filters::state_variable_filter::{FilterMode, SvfChannel, damping, prewarp};
+
+// Keeps the echoes dying away however the filter is set
+const MAX_FEEDBACK: f32 = 0.99;
+
+#[derive(Clone)]
+pub struct StereoDelay {
+    input: DynSoundSource,
+    // Samples
+    left: usize,
+    right: usize,
+    feedback: Knob,
+    mix: Knob,
+    ping_pong: bool,
+    filter: Option<(FilterMode, Knob)>, // Cutoff in Hz
+    tail: SampleTime,
+    sample_rate: i32,
+}
+
+impl StereoDelay {
+    // Left and right times in samples, with no filter and a tail long enough
+    // for a feedback of 0.5
+    pub fn new(input: DynSoundSource, left: usize, right: usize, feedback: Knob, mix: Knob, sample_rate: i32) -> Self {
+        let delay = StereoDelay {
+            input: input,
+            left: left.max(1),
+            right: right.max(1),
+            feedback: feedback,
+            mix: mix,
+            ping_pong: false,
+            filter: None,
+            tail: 0,
+            sample_rate: sample_rate,
+        };
+        delay.tail_for(0.5)
+    }
+
+    pub fn ping_pong(mut self, ping_pong: bool) -> Self {
+        self.ping_pong = ping_pong;
+        self
+    }
+
+    // Filter the echoes each time they go round
+    pub fn filter(mut self, mode: FilterMode, cutoff: Knob) -> Self {
+        self.filter = Some((mode, cutoff));
+        self
+    }
+
+    // Samples after the input ends
+    pub fn tail(mut self, tail: SampleTime) -> Self {
+        self.tail = tail;
+        self
+    }
+
+    // A tail long enough for the echoes to die away by 60 dB with a fixed
+    // feedback
+    pub fn tail_for(self, feedback: f32) -> Self {
+        let feedback = feedback.abs().clamp(0.001, MAX_FEEDBACK);
+        let echoes = (0.001_f32.ln() / feedback.ln()).ceil() as SampleTime;
+        let longest = self.left.max(self.right) as SampleTime;
+        self.tail((echoes + 1) * longest)
+    }
+
+    fn new_data(&self) -> StereoDelayData {
+        StereoDelayData {
+            input_data: self.input.init_state(),
+            feedback_data: self.feedback.init_state(),
+            mix_data: self.mix.init_state(),
+            cutoff_data: self.filter.as_ref().map(|(_, cutoff)| cutoff.init_state()),
+            next_n: 0,
+            buffers: [DelayBuffer::new(self.left), DelayBuffer::new(self.right)],
+            filters: [SvfChannel::default(); 2],
+        }
+    }
+}
+
+struct StereoDelayData {
+    input_data: SoundData,
+    feedback_data: SoundData,
+    mix_data: SoundData,
+    cutoff_data: Option<SoundData>,
+    next_n: SampleTime,
+    buffers: [DelayBuffer; 2],
+    filters: [SvfChannel; 2],
+}
+
+impl SoundSource for StereoDelay {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<StereoDelayData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start, so forget the old echoes
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let dry = if n < self.input.duration() { self.input.next_value(n, &mut data.input_data) } else { (0.0, 0.0) };
+        let feedback = self.feedback.next_value(n, &mut data.feedback_data).clamp(-MAX_FEEDBACK, MAX_FEEDBACK);
+        let mix = self.mix.next_value(n, &mut data.mix_data).clamp(0.0, 1.0);
+
+        let wet = (data.buffers[0].tap(self.left), data.buffers[1].tap(self.right));
+        let mut fed_back = [wet.0 * feedback, wet.1 * feedback];
+        if let (Some((mode, cutoff)), Some(cutoff_data)) = (&self.filter, &mut data.cutoff_data) {
+            let g = prewarp(cutoff.next_value(n, cutoff_data), self.sample_rate);
+            let k = damping(0.0);
+            for (x, filter) in fed_back.iter_mut().zip(data.filters.iter_mut()) {
+                *x = filter.process(*x, g, k, *mode);
+            }
+        }
+        if self.ping_pong {
+            data.buffers[0].push((dry.0 + dry.1) / 2.0 + fed_back[1]);
+            data.buffers[1].push(fed_back[0]);
+        } else {
+            data.buffers[0].push(dry.0 + fed_back[0]);
+            data.buffers[1].push(dry.1 + fed_back[1]);
+        }
+        (dry.0 * (1.0 - mix) + wet.0 * mix, dry.1 * (1.0 - mix) + wet.1 * mix)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(self.tail)
+    }
+
+    // params: input, left time, right time, feedback, filter, mix, mode,
+    // [tail in seconds]
+    // Times are in seconds or note values like 1/8. (a dotted eighth) at the
+    // song's tempo. Feedback runs from -0.99 to 0.99 and mix from 0 (dry) to 1
+    // (only echoes). Both can be sounds, and if the feedback is a sound the
+    // tail is needed to say how long the echoes go on for. The filter is
+    // "none" or a mode (lp, hp, bp or notch) and a cutoff in Hz or a sound,
+    // like "lp 2000". The mode is stereo or ping_pong.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let sample_rate = reader.sample_rate;
+        let t2n = |t: f32| (t * sample_rate as f32).round() as usize;
+        let left = t2n(reader.get_time(&params[1]));
+        let right = t2n(reader.get_time(&params[2]));
+        let feedback = reader.get_value_knob(&params[3], 1.0);
+        let mix = reader.get_value_knob(&params[5], 1.0);
+        let ping_pong = match params[6].as_str() {
+            "stereo" => false,
+            "ping_pong" => true,
+            _ => panic!("Stereo delay mode must be stereo or ping_pong, not \"{}\"", params[6]),
+        };
+        let mut delay = StereoDelay::new(input, left, right, feedback, mix, sample_rate).ping_pong(ping_pong);
+        if params[4] != "none" {
+            let filter: Vec<_> = params[4].split_whitespace().collect();
+            match filter[..] {
+                [mode, cutoff] => delay = delay.filter(FilterMode::from_param(mode), reader.get_value_knob(cutoff, 1.0)),
+                _ => panic!("Stereo delay filter must be none or \"mode cutoff\", not \"{}\"", params[4]),
+            }
+        }
+        delay = match (params.get(7), params[3].parse::<f32>()) {
+            (Some(tail), _) => delay.tail(reader.get_duration(tail)),
+            (None, Ok(feedback)) => delay.tail_for(feedback),
+            (None, Err(_)) => panic!("Stereo delay needs a tail when the feedback \"{}\" is a sound", params[3]),
+        };
+        Box::new(delay)
+    }
+}
@@ -1,19 +1,21 @@
-pub mod time_box {
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+// How long instruments take to fade notes in and out so that they don't click,
+// in seconds (88 samples at 48 kHz)
+pub const DECLICK_TIME: f32 = 0.00183;
 
 #[derive(Clone)]
 pub struct TimeBox {
-    duration: i32,
-    ramp_time: i32,
+    duration: SampleTime,
+    ramp_time: SampleTime,
     source: DynSoundSource,
 }
 
 impl TimeBox {
     pub fn new(
-        duration: i32,
-        ramp_time: i32,
+        duration: SampleTime,
+        ramp_time: SampleTime,
         source: DynSoundSource
     ) -> Self {
         TimeBox{
@@ -33,7 +35,7 @@ impl SoundSource for TimeBox {
         Box::new(TimeBoxState { source_state: self.source.init_state() })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<TimeBoxState>().unwrap();
         let source_val = self.source.next_value(n, &mut data.source_state);
         let mut gain = 1.0;
@@ -51,16 +53,14 @@ impl SoundSource for TimeBox {
         (source_val.0 * gain, source_val.1 * gain)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
-        let duration = params[0].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        let ramp_time = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
+        let duration = reader.get_duration(&params[0]);
+        let ramp_time = params[1].parse::<f64>().unwrap() * reader.sample_rate as f64;
         let source = reader.get_sound(&params[2]);
-        Box::new(Self::new(duration.round() as i32, ramp_time.round() as i32, source))
+        Box::new(Self::new(duration, ramp_time.round() as SampleTime, source))
     }
 }
-
-}
@@ -1,33 +1,49 @@
-pub mod traits {
-
 use std::any::Any;
 use dyn_clone::DynClone;
 use num::complex::Complex;
 
-use crate::read_song::read_song::SongReader;
+use crate::read_song::SongReader;
+use crate::frequency_response::TransferFunction;
 
 pub type SoundData = Box<dyn Any + Send + Sync>;
 
+// Sample numbers and durations in samples. 64 bits won't run out however long
+// a piece plays.
+pub type SampleTime = i64;
+
+// The duration of sources that go on for ever. It is far short of
+// SampleTime::MAX so that adding delays and offsets to it can't overflow.
+pub const FOREVER: SampleTime = SampleTime::MAX / 4;
+
+// Whether a duration means the sound never ends. Delaying or mixing a sound
+// that goes on for ever gives durations past FOREVER, so they count too.
+pub fn is_forever(duration: SampleTime) -> bool {
+    duration >= FOREVER
+}
+
 pub trait SoundSource: DynClone {
     fn init_state(&self) -> SoundData;
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32);
-    fn duration(&self) -> i32;
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32);
+    fn duration(&self) -> SampleTime;
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource where Self: Sized;
+    // Filters made from poles and zeros describe themselves so their response
+    // can be worked out exactly rather than measured
+    fn transfer_function(&self) -> Option<TransferFunction> {
+        None
+    }
 }
 dyn_clone::clone_trait_object!(SoundSource);
 pub type DynSoundSource = Box<dyn SoundSource + Send + Sync>;
 
 pub trait ComplexSoundSource: DynClone {
     fn init_state(&self) -> SoundData;
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (Complex::<f32>, Complex::<f32>);
-    fn duration(&self) -> i32;
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (Complex::<f32>, Complex::<f32>);
+    fn duration(&self) -> SampleTime;
 }
 dyn_clone::clone_trait_object!(ComplexSoundSource);
 pub type DynComplexSoundSource = Box<dyn ComplexSoundSource + Send + Sync>;
 
 pub trait Instrument {
-    fn play(&self, freq: f32, duration: i32, strength: f32) -> DynSoundSource;
+    fn play(&self, freq: f32, duration: SampleTime, strength: f32) -> DynSoundSource;
 }
 pub type DynInstrument = Box<dyn Instrument + Send + Sync>;
-
-}
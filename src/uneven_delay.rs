@@ -1,17 +1,15 @@
-pub mod uneven_delay {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub struct UnevenDelay {
     input: DynSoundSource,
-    left_delay: i32,
-    right_delay: i32,
+    left_delay: SampleTime,
+    right_delay: SampleTime,
 }
 
 impl UnevenDelay {
-    pub fn new(input: DynSoundSource, left_delay: i32, right_delay: i32) -> Self {
+    pub fn new(input: DynSoundSource, left_delay: SampleTime, right_delay: SampleTime) -> Self {
         UnevenDelay { input: input, left_delay: left_delay, right_delay: right_delay }
     }
 }
@@ -32,7 +30,7 @@ impl SoundSource for UnevenDelay {
          } )
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<UnevenDelayData>().unwrap();
         let left;
         if n >= self.left_delay {
@@ -49,16 +47,14 @@ impl SoundSource for UnevenDelay {
         (left.0, right.1)
     }
 
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.input.duration() + (self.left_delay).max(self.right_delay)
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let input = reader.get_sound(&params[0]);
-        let left_delay = params[1].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        let right_delay = params[2].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(UnevenDelay::new(input, left_delay.round() as i32, right_delay.round() as i32))
+        let left_delay = params[1].parse::<f64>().unwrap() * reader.sample_rate as f64;
+        let right_delay = params[2].parse::<f64>().unwrap() * reader.sample_rate as f64;
+        Box::new(UnevenDelay::new(input, left_delay.round() as SampleTime, right_delay.round() as SampleTime))
     }
 }
-
-}
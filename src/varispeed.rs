@@ -0,0 +1,98 @@
+// Plays a sound at a varying speed, like a tape or record running fast or
+// slow. Speed 1 plays normally, 2 plays twice as fast and an octave up, 0.5
+// half as fast and an octave down, and 0 stops. Pitch and time change
+// together. The sound is read through a Resampler, so the sinc qualities don't
+// alias when speeding up.
+//
+// The input is pulled one sample at a time as the read position moves on and
+// kept in a buffer just long enough for the resampler to look either side of
+// the position, so any sound can be used, however long.
+
+use std::collections::VecDeque;
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+use crate::resampler::{Resampler, Quality};
+
+#[derive(Clone)]
+pub struct Varispeed {
+    input: DynSoundSource,
+    speed: Knob, // Input samples per output sample, 0 or more
+    resampler: Resampler,
+    duration: SampleTime,
+}
+
+impl Varispeed {
+    pub fn new(input: DynSoundSource, speed: Knob, quality: Quality, duration: SampleTime) -> Self {
+        Varispeed { input: input, speed: speed, resampler: Resampler::new(quality), duration: duration }
+    }
+}
+
+struct VarispeedData {
+    input_data: SoundData,
+    speed_data: SoundData,
+    // Input samples from index `first` onwards
+    buffer: VecDeque<(f32, f32)>,
+    first: SampleTime,
+    pos: f64,
+}
+
+impl SoundSource for Varispeed {
+    fn init_state(&self) -> SoundData {
+        Box::new(VarispeedData {
+            input_data: self.input.init_state(),
+            speed_data: self.speed.init_state(),
+            buffer: VecDeque::new(),
+            first: 0,
+            pos: 0.0,
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<VarispeedData>().unwrap();
+        let speed = self.speed.next_value(n, &mut data.speed_data).max(0.0) as f64;
+        let reach = self.resampler.reach(speed) as SampleTime;
+
+        // Read ahead far enough for the resampler
+        let input_duration = self.input.duration();
+        let wanted = (data.pos.floor() as SampleTime + reach).min(input_duration);
+        while data.first + (data.buffer.len() as SampleTime) <= wanted {
+            let x = data.first + data.buffer.len() as SampleTime;
+            data.buffer.push_back(self.input.next_value(x, &mut data.input_data));
+        }
+        // And forget what's now too far behind
+        while data.first < data.pos.floor() as SampleTime - reach && !data.buffer.is_empty() {
+            data.buffer.pop_front();
+            data.first += 1;
+        }
+
+        let buffer = &data.buffer;
+        let first = data.first;
+        let input = |x: SampleTime| if x >= first && x < first + buffer.len() as SampleTime {
+            buffer[(x - first) as usize]
+        } else {
+            (0.0, 0.0)
+        };
+        let val = self.resampler.value_at(input, data.pos, speed);
+        data.pos += speed;
+        val
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    // params: input, speed, quality, duration
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let speed = reader.get_value_knob(&params[1], 1.0);
+        let quality = Quality::from_param(&params[2]);
+        let duration = reader.get_duration(&params[3]);
+        Box::new(Varispeed::new(input, speed, quality, duration))
+    }
+}
@@ -0,0 +1,84 @@
+// Velvet noise is a sparse train of +1 and -1 impulses. Time is split into
+// frames of 1/density seconds and each frame gets a single impulse at a random
+// position with a random sign. At around 2000 impulses per second it sounds as
+// smooth as white noise but is mostly zeros, which makes it a cheap excitation
+// for reverbs and decorrelation filters. At low densities it crackles.
+
+use rand::{Rng, SeedableRng};
+use rand::rngs::StdRng;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+use crate::knob::Knob;
+
+#[derive(Clone)]
+pub struct VelvetNoise {
+    density: Knob, // Impulses per sample (impulses per second / sample rate)
+    seed: u64,
+    duration: SampleTime,
+}
+
+impl VelvetNoise {
+    pub fn new(density: Knob, seed: u64, duration: SampleTime) -> Self {
+        VelvetNoise { density: density, seed: seed, duration: duration }
+    }
+}
+
+struct VelvetNoiseData {
+    rng: StdRng,
+    density_data: SoundData,
+    frame_end: SampleTime,
+    pulse_pos: SampleTime,
+    sign: f32,
+}
+
+impl SoundSource for VelvetNoise {
+    fn init_state(&self) -> SoundData {
+        Box::new(VelvetNoiseData {
+            rng: StdRng::seed_from_u64(self.seed),
+            density_data: self.density.init_state(),
+            frame_end: 0,
+            pulse_pos: -1,
+            sign: 1.0,
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            (0.0, 0.0)
+        } else {
+            let data = state.downcast_mut::<VelvetNoiseData>().unwrap();
+            let density = self.density.next_value(n, &mut data.density_data);
+            if n >= data.frame_end {
+                // The density is only read at the start of each frame. With
+                // none there's no impulse, and it's read again next sample
+                // in case it comes back.
+                if density > 0.0 {
+                    let frame_len = (1.0 / density).round().max(1.0) as SampleTime;
+                    data.pulse_pos = n + data.rng.gen_range(0..frame_len);
+                    data.frame_end = n + frame_len;
+                    data.sign = if data.rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+                } else {
+                    data.frame_end = n + 1;
+                }
+            }
+            let val = if n == data.pulse_pos { data.sign } else { 0.0 };
+            (val, val)
+        }
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let density = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let duration = reader.get_duration(&params[1]);
+        // An optional third parameter fixes the seed for this node
+        let seed = match params.get(2) {
+            Some(seed) => seed.parse::<u64>().unwrap(),
+            None => reader.next_seed(),
+        };
+        Box::new(Self::new(density, seed, duration))
+    }
+}
@@ -0,0 +1,112 @@
+// Vibrato from a wobbling delay, like doppler_vibrato.yaml but with the delay
+// read from a buffer rather than by re-evaluating the input. As the delay gets
+// longer the sound is played back slower and lower and as it gets shorter it
+// is played faster and higher. Only the delayed sound is heard.
+//
+// The pitch goes up and down by 2 pi × rate × depth (as a fraction of the
+// pitch), so 5 Hz at a depth of 1 ms is about ±3%, or half a semitone.
+
+use std::f64::consts::PI;
+
+use crate::delay_buffer::DelayBuffer;
+use crate::knob::Knob;
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
+
+#[derive(Clone)]
+pub struct Vibrato {
+    input: DynSoundSource,
+    rate: Knob, // Cycles per sample
+    depth: Knob, // Samples either side of the middle
+    max_depth: f32, // Samples
+}
+
+impl Vibrato {
+    // The depth is kept to at most 5 ms
+    pub fn new(input: DynSoundSource, rate: Knob, depth: Knob, sample_rate: i32) -> Self {
+        Vibrato {
+            input: input,
+            rate: rate,
+            depth: depth,
+            max_depth: 0.005 * sample_rate as f32,
+        }
+    }
+
+    // In samples. The sound is delayed by this much plus a sample.
+    pub fn max_depth(mut self, max_depth: f32) -> Self {
+        self.max_depth = max_depth.max(0.0);
+        self
+    }
+
+    // The middle of the delay's wobble, at least a sample so there's always a
+    // sample to read
+    fn centre(&self) -> f32 {
+        self.max_depth + 1.0
+    }
+
+    fn new_data(&self) -> VibratoData {
+        let buffer = DelayBuffer::new(2 * self.centre().ceil() as usize);
+        VibratoData {
+            input_data: self.input.init_state(),
+            rate_data: self.rate.init_state(),
+            depth_data: self.depth.init_state(),
+            next_n: 0,
+            buffers: [buffer.clone(), buffer],
+            phase: 0.0,
+        }
+    }
+}
+
+struct VibratoData {
+    input_data: SoundData,
+    rate_data: SoundData,
+    depth_data: SoundData,
+    next_n: SampleTime,
+    buffers: [DelayBuffer; 2],
+    phase: f64, // Fraction of a cycle
+}
+
+impl SoundSource for Vibrato {
+    fn init_state(&self) -> SoundData {
+        Box::new(self.new_data())
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        let data = state.downcast_mut::<VibratoData>().unwrap();
+        if n < data.next_n {
+            // Played again from the start
+            *data = self.new_data();
+        }
+        data.next_n = n + 1;
+        let input = if n < self.input.duration() { self.input.next_value(n, &mut data.input_data) } else { (0.0, 0.0) };
+        let rate = self.rate.next_value(n, &mut data.rate_data) as f64;
+        let depth = self.depth.next_value(n, &mut data.depth_data).clamp(0.0, self.max_depth);
+
+        let delay = self.centre() + depth * (2.0 * PI * data.phase).sin() as f32;
+        data.phase = (data.phase + rate).rem_euclid(1.0);
+        let output = (data.buffers[0].read(delay), data.buffers[1].read(delay));
+        data.buffers[0].push(input.0);
+        data.buffers[1].push(input.1);
+        output
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.input.duration().saturating_add(2 * self.centre().ceil() as SampleTime)
+    }
+
+    // params: input, rate, depth in ms, [max depth in ms]
+    // The rate is in Hz or a note value like 1/4 at the song's tempo. Rate and
+    // depth can be sounds, in which case the max depth is needed to size the
+    // delay. Otherwise it defaults to the depth.
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let input = reader.get_sound(&params[0]);
+        let sample_rate = reader.sample_rate as f32;
+        let rate = reader.get_rate_knob(&params[1], 1.0 / sample_rate);
+        let depth = reader.get_value_knob(&params[2], sample_rate / 1000.0);
+        let max_depth = match params.get(3).unwrap_or(&params[2]).parse::<f32>() {
+            Ok(max_depth) => max_depth * sample_rate / 1000.0,
+            Err(_) => panic!("Vibrato needs a max depth when the depth \"{}\" is a sound", params[2]),
+        };
+        Box::new(Vibrato::new(input, rate, depth, reader.sample_rate).max_depth(max_depth))
+    }
+}
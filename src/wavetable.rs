@@ -1,7 +1,5 @@
-pub mod wavetable {
-
-use crate::read_song::read_song::SongReader;
-use crate::traits::traits::{SoundSource, DynSoundSource, SoundData};
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime};
 
 #[derive(Clone)]
 pub enum Interpolation {
@@ -10,18 +8,38 @@ pub enum Interpolation {
     Cubic,
 }
 
+impl Interpolation {
+    pub fn from_param(param: &str) -> Self {
+        match param.to_lowercase().as_str() {
+            "rounding" => Interpolation::Rounding,
+            "linear" => Interpolation::Linear,
+            "cubic" => Interpolation::Cubic,
+            _ => panic!("Value must be rounding, linear or cubic")
+        }
+    }
+}
+
+// Cubic (Lagrange) interpolation at fraction f of the way from y0 to y1, given
+// the points either side of them
+pub fn cubic_interpolate(f: f32, ym1: f32, y0: f32, y1: f32, y2: f32) -> f32 {
+    -f * (f - 1.0) * (f - 2.0) / 6.0 * ym1
+        + (f + 1.0) * (f - 1.0) * (f - 2.0) / 2.0 * y0
+        -(f + 1.0) * f * (f - 2.0) / 2.0 * y1
+        + (f + 1.0) * f * (f - 1.0) / 6.0 * y2
+}
+
 #[derive(Clone)]
 pub struct Wavetable {
     table: Vec::<(f32,f32)>,
     sweep: DynSoundSource,
     interpolation: Interpolation,
-    duration: i32
+    duration: SampleTime
 }
 
 impl Wavetable {
-    pub fn new(table: DynSoundSource, sweep: DynSoundSource, interpolation: Interpolation, duration: i32) -> Self {
+    pub fn new(table: DynSoundSource, sweep: DynSoundSource, interpolation: Interpolation, duration: SampleTime) -> Self {
         let mut buf = Vec::<(f32, f32)>::new();
-        let mut sample_clock = 0i32;
+        let mut sample_clock: SampleTime = 0;
         let table_duration = table.duration();
         let mut table_state = table.init_state();
         while sample_clock < table_duration {
@@ -35,7 +53,7 @@ impl Wavetable {
         buffer: Vec<(f32, f32)>,
         sweep: DynSoundSource,
         interpolation: Interpolation,
-        duration: i32
+        duration: SampleTime
     ) -> Self {
         Wavetable { table: buffer, sweep: sweep, interpolation: interpolation, duration: duration }
     }
@@ -60,7 +78,7 @@ impl SoundSource for Wavetable {
         Box::new(WavetableState { sweep_state: self.sweep.init_state() })
     }
 
-    fn next_value(&self, n: i32, state: &mut SoundData) -> (f32, f32) {
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
         let data = &mut state.downcast_mut::<WavetableState>().unwrap();
         let sweep_value = self.sweep.next_value(n, &mut data.sweep_state).0;
         if n < self.duration || (sweep_value.floor() >= 0.0 && (sweep_value.ceil() as usize) < self.table.len()) {
@@ -86,14 +104,8 @@ impl SoundSource for Wavetable {
                     let y2 = y[wrap_x(x0 as i32 + 2, y.len() as i32)]; // y[x0 + 2]
                     let ym1 = y[wrap_x(x0 as i32 - 1, y.len() as i32)]; // y[x0 - 1]
                     let f = sweep_value - x0 as f32;
-                    output0 = -f * (f - 1.0) * (f - 2.0) / 6.0 * ym1.0
-                        + (f + 1.0) * (f - 1.0) * (f - 2.0) / 2.0 * y[x0].0
-                        -(f + 1.0) * f * (f - 2.0) / 2.0 * y1.0
-                        + (f + 1.0) * f * (f - 1.0) / 6.0 * y2.0;
-                    output1 = -f * (f - 1.0) * (f - 2.0) / 6.0 * ym1.1
-                        + (f + 1.0) * (f - 1.0) * (f - 2.0) / 2.0 * y[x0].1
-                        -(f + 1.0) * f * (f - 2.0) / 2.0 * y1.1
-                        + (f + 1.0) * f * (f - 1.0) / 6.0 * y2.1;
+                    output0 = cubic_interpolate(f, ym1.0, y[x0].0, y1.0, y2.0);
+                    output1 = cubic_interpolate(f, ym1.1, y[x0].1, y1.1, y2.1);
                 }
             }
             (output0, output1)
@@ -101,23 +113,15 @@ impl SoundSource for Wavetable {
             (0.0, 0.0)
         }
     }
-    fn duration(&self) -> i32 {
+    fn duration(&self) -> SampleTime {
         self.duration
     }
 
     fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
         let table = reader.get_sound(&params[0]);
         let sweep = reader.get_sound(&params[1]);
-        let interpolation: Interpolation;
-        match params[2].to_lowercase().as_str() {
-            "rounding" => interpolation = Interpolation::Rounding,
-            "linear" => interpolation = Interpolation::Linear,
-            "cubic" => interpolation = Interpolation::Cubic,
-            _ => panic!("Value must be rounding, linear or cubic")
-        }
-        let duration = params[3].parse::<f32>().unwrap() * reader.sample_rate as f32;
-        Box::new(Wavetable::new(table, sweep, interpolation, duration.round() as i32))
+        let interpolation = Interpolation::from_param(&params[2]);
+        let duration = reader.get_duration(&params[3]);
+        Box::new(Wavetable::new(table, sweep, interpolation, duration))
     }
 }
-
-}
\ No newline at end of file
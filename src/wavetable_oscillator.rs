@@ -0,0 +1,202 @@
+// A wavetable oscillator plays a bank of single cycle frames. The position
+// knob picks a frame (0 is the first, 1 is the last) and crossfades between
+// neighbouring frames, so sweeping it morphs from one waveform to the next.
+//
+// Frames can be rendered from sounds (each sound's whole duration is one cycle)
+// or cut from a sound file split into equal frames. Every frame is resampled to
+// FRAME_LEN samples through an FFT, which doesn't care whether the source frame
+// length was a power of two.
+//
+// Playing a table with lots of harmonics at a high pitch would push the upper
+// harmonics past nyquist, where they alias. So each frame is stored as a set of
+// mipmaps, one per octave, each with half the harmonics of the one before, and
+// the oscillator plays the brightest mipmap that fits under nyquist at its
+// current frequency.
+
+use std::sync::Arc;
+use rustfft::FftPlanner;
+use rustfft::num_complex::Complex;
+
+use crate::read_song::SongReader;
+use crate::traits::{SoundSource, DynSoundSource, SoundData, SampleTime, is_forever};
+
+use crate::import_wav::{read_audio, is_sound_file, Channels};
+use crate::knob::Knob;
+use crate::wavetable::{Interpolation, cubic_interpolate};
+
+pub const FRAME_LEN: usize = 2048;
+
+pub struct WavetableBank {
+    // mipmaps[level][frame] is the frame with at most max_harmonics[level]
+    // harmonics
+    mipmaps: Vec<Vec<Vec<f32>>>,
+    max_harmonics: Vec<usize>,
+}
+
+impl WavetableBank {
+    // Each frame is one cycle of any length
+    pub fn new(frames: Vec<Vec<f32>>) -> Self {
+        if frames.is_empty() {
+            panic!("A wavetable needs at least one frame");
+        }
+        if frames.iter().any(|frame| frame.is_empty()) {
+            panic!("A wavetable frame needs at least one sample");
+        }
+        let mut planner = FftPlanner::<f32>::new();
+        let inverse = planner.plan_fft_inverse(FRAME_LEN);
+
+        let mut max_harmonics = Vec::new();
+        let mut harmonics = FRAME_LEN / 2 - 1;
+        while harmonics >= 1 {
+            max_harmonics.push(harmonics);
+            harmonics /= 2;
+        }
+
+        let mut mipmaps = vec![Vec::new(); max_harmonics.len()];
+        for frame in &frames {
+            let forward = planner.plan_fft_forward(frame.len());
+            let mut spectrum: Vec<_> = frame.iter().map(|x| Complex::new(*x, 0.0)).collect();
+            forward.process(&mut spectrum);
+            // Harmonics the source frame has room for
+            let available = (frame.len() - 1) / 2;
+            for (level, &harmonics) in max_harmonics.iter().enumerate() {
+                // Rebuild the frame from its harmonics, leaving out DC
+                let mut buffer = vec![Complex::new(0.0, 0.0); FRAME_LEN];
+                for k in 1..=harmonics.min(available) {
+                    buffer[k] = spectrum[k];
+                    buffer[FRAME_LEN - k] = spectrum[k].conj();
+                }
+                inverse.process(&mut buffer);
+                let scale = 1.0 / frame.len() as f32;
+                mipmaps[level].push(buffer.iter().map(|x| x.re * scale).collect());
+            }
+        }
+        WavetableBank { mipmaps: mipmaps, max_harmonics: max_harmonics }
+    }
+
+    // Render each source for its whole duration as one frame, using the left
+    // channel
+    pub fn from_sources(sources: &[DynSoundSource]) -> Self {
+        let mut frames = Vec::new();
+        for source in sources {
+            if is_forever(source.duration()) {
+                panic!("Can't render a sound that goes on for ever into a wavetable frame, give it a duration");
+            }
+            let mut state = source.init_state();
+            frames.push((0..source.duration()).map(|n| source.next_value(n, &mut state).0).collect());
+        }
+        Self::new(frames)
+    }
+
+    // Split a sound file into `frame_count` equal frames, mixing it to mono
+    pub fn from_wav(filename: &str, frame_count: usize) -> Self {
+        let (samples, _sample_rate) = read_audio(filename, Channels::Mono);
+        let frame_len = samples.len() / frame_count.max(1);
+        if frame_len == 0 {
+            panic!("{} is too short to split into {} frames", filename, frame_count);
+        }
+        let frames = samples.chunks_exact(frame_len)
+            .map(|frame| frame.iter().map(|(l, _)| *l).collect())
+            .collect();
+        Self::new(frames)
+    }
+
+    pub fn frame_count(&self) -> usize {
+        self.mipmaps[0].len()
+    }
+
+    // The brightest mipmap that doesn't alias at this frequency (fraction of
+    // the sample rate)
+    fn level(&self, freq: f32) -> usize {
+        let freq = freq.abs();
+        self.max_harmonics.iter()
+            .position(|&harmonics| harmonics as f32 * freq < 0.5)
+            .unwrap_or(self.max_harmonics.len() - 1)
+    }
+}
+
+fn read_frame(frame: &[f32], x: f32, interpolation: &Interpolation) -> f32 {
+    let mask = FRAME_LEN - 1;
+    let x0 = x.floor();
+    let f = x - x0;
+    let x0 = x0 as usize;
+    match interpolation {
+        Interpolation::Rounding => frame[x.round() as usize & mask],
+        Interpolation::Linear => frame[x0 & mask] + (frame[(x0 + 1) & mask] - frame[x0 & mask]) * f,
+        Interpolation::Cubic => cubic_interpolate(f,
+            frame[(x0 + mask) & mask], frame[x0 & mask], frame[(x0 + 1) & mask], frame[(x0 + 2) & mask]),
+    }
+}
+
+#[derive(Clone)]
+pub struct WavetableOscillator {
+    bank: Arc<WavetableBank>,
+    freq: Knob, // Frequency as fraction of sample rate
+    position: Knob, // 0 to 1 across the frames
+    interpolation: Interpolation,
+    duration: SampleTime,
+}
+
+impl WavetableOscillator {
+    pub fn new(bank: Arc<WavetableBank>, freq: Knob, position: Knob, interpolation: Interpolation, duration: SampleTime) -> Self {
+        WavetableOscillator { bank: bank, freq: freq, position: position, interpolation: interpolation, duration: duration }
+    }
+}
+
+struct WavetableOscillatorData {
+    freq_data: SoundData,
+    position_data: SoundData,
+    phase: f64,
+}
+
+impl SoundSource for WavetableOscillator {
+    fn init_state(&self) -> SoundData {
+        Box::new(WavetableOscillatorData {
+            freq_data: self.freq.init_state(),
+            position_data: self.position.init_state(),
+            phase: 0.0,
+        })
+    }
+
+    fn next_value(&self, n: SampleTime, state: &mut SoundData) -> (f32, f32) {
+        if n > self.duration {
+            return (0.0, 0.0);
+        }
+        let data = state.downcast_mut::<WavetableOscillatorData>().unwrap();
+        let freq = self.freq.next_value(n, &mut data.freq_data);
+        let position = self.position.next_value(n, &mut data.position_data).clamp(0.0, 1.0);
+
+        let frames = &self.bank.mipmaps[self.bank.level(freq)];
+        let frame_pos = position * (frames.len() - 1) as f32;
+        let frame0 = (frame_pos.floor() as usize).min(frames.len() - 1);
+        let frame1 = (frame0 + 1).min(frames.len() - 1);
+        let fade = frame_pos - frame0 as f32;
+        let x = data.phase as f32 * FRAME_LEN as f32;
+        let y0 = read_frame(&frames[frame0], x, &self.interpolation);
+        let y1 = read_frame(&frames[frame1], x, &self.interpolation);
+        let val = y0 + (y1 - y0) * fade;
+
+        data.phase = (data.phase + freq as f64).rem_euclid(1.0);
+        (val, val)
+    }
+
+    fn duration(&self) -> SampleTime {
+        self.duration
+    }
+
+    fn from_yaml(params: &Vec::<String>, reader: &mut SongReader) -> DynSoundSource {
+        let freq = reader.get_knob(&params[0], 1.0 / reader.sample_rate as f32);
+        let position = reader.get_knob(&params[1], 1.0);
+        let interpolation = Interpolation::from_param(&params[2]);
+        let duration = reader.get_duration(&params[3]);
+        // The frames are either a sound file and how many frames to split it
+        // into, or the names of the sounds to render as frames
+        let bank = if is_sound_file(&params[4]) {
+            WavetableBank::from_wav(&params[4], params[5].parse::<usize>().unwrap())
+        } else {
+            let sources: Vec<_> = params[4..].iter().map(|name| reader.get_sound(name)).collect();
+            WavetableBank::from_sources(&sources)
+        };
+        Box::new(Self::new(Arc::new(bank), freq, position, interpolation, duration))
+    }
+}
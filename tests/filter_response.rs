@@ -0,0 +1,43 @@
+// A Butterworth filter built for a cutoff should be 3 dB down at that cutoff,
+// and measuring it with an impulse should agree with working its response out
+// from the poles and zeros.
+
+use std::f32::consts::PI;
+
+use mattmusic::filters::butterworth_filter::ButterworthFilter;
+use mattmusic::frequency_response::{FrequencyResponse, log_frequencies};
+use mattmusic::impulse::Impulse;
+use mattmusic::traits::DynSoundSource;
+
+const SAMPLE_RATE: i32 = 48000;
+const CUTOFF: f32 = 440.0;
+const LENGTH: i64 = 48000;
+
+fn low_pass(normalize: f32) -> DynSoundSource {
+    let pole_r = (PI * CUTOFF / SAMPLE_RATE as f32).tan();
+    Box::new(ButterworthFilter::new(Box::new(Impulse::new(1.0, LENGTH)), 4, pole_r, 1e10, normalize))
+}
+
+#[test]
+fn butterworth_cutoff_is_where_asked() {
+    let freqs = log_frequencies(20.0, 20000.0, 400);
+    // Scale the filter to about 0 dB in the pass band so it doesn't lose
+    // precision measuring it
+    let unscaled = low_pass(1.0).transfer_function().unwrap();
+    let filter = low_pass(1.0 / unscaled.response(0.0).norm() as f32);
+    let exact = FrequencyResponse::from_transfer_function(&filter.transfer_function().unwrap(), &freqs, SAMPLE_RATE);
+
+    let crossings = exact.crossings(exact.peak().magnitude - 3.0);
+    assert_eq!(crossings.len(), 1, "crossings at {:?}", crossings);
+    let error = (crossings[0] / CUTOFF as f64 - 1.0).abs();
+    assert!(error < 0.01, "-3 dB at {} Hz rather than {} Hz", crossings[0], CUTOFF);
+
+    let measured = FrequencyResponse::measure(&filter, 1.0, LENGTH, &freqs, SAMPLE_RATE);
+    for (exact, measured) in exact.points.iter().zip(&measured.points) {
+        // Down in the stop band the measurement is only rounding errors
+        if exact.magnitude > -60.0 {
+            assert!((exact.magnitude - measured.magnitude).abs() < 0.1,
+                "{} dB exactly but {} dB measured at {} Hz", exact.magnitude, measured.magnitude, exact.freq);
+        }
+    }
+}
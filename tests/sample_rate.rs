@@ -0,0 +1,42 @@
+// Rendering a song at different sample rates should give the same sound. Each
+// render is resampled to 48 kHz and compared with the 48 kHz render.
+
+use mattmusic::instruments::vibraphone::Vibraphone;
+use mattmusic::render;
+use mattmusic::resampler::{resample, Quality};
+use mattmusic::reverberator::Reverberator;
+use mattmusic::songs::two_notes::two_notes;
+
+const COMPARE_RATE: i32 = 48000;
+
+fn render_at(sample_rate: i32) -> Vec<(f32, f32)> {
+    let song = two_notes(sample_rate, Box::new(Vibraphone::new(sample_rate)));
+    let reverb = Reverberator::new(song, 0.5, sample_rate);
+    let samples = render(&(Box::new(reverb) as mattmusic::DynSoundSource));
+    resample(&samples, sample_rate, COMPARE_RATE, Quality::Medium)
+}
+
+// Power of the difference between two renders relative to the power of the
+// first, in dB
+fn difference_db(a: &[(f32, f32)], b: &[(f32, f32)]) -> f32 {
+    let len = a.len().min(b.len());
+    let power: f32 = a[..len].iter().map(|(l, r)| l * l + r * r).sum();
+    let error: f32 = a[..len].iter().zip(&b[..len])
+        .map(|((al, ar), (bl, br))| (al - bl) * (al - bl) + (ar - br) * (ar - br))
+        .sum();
+    10.0 * (error / power).log10()
+}
+
+#[test]
+fn renders_match_across_sample_rates() {
+    let reference = render_at(COMPARE_RATE);
+    for sample_rate in [44100, 96000] {
+        let other = render_at(sample_rate);
+        // Lengths can only differ by rounding
+        let expected_len = reference.len() as f32;
+        assert!((other.len() as f32 - expected_len).abs() < expected_len * 0.001,
+            "{} Hz render is {} samples long at 48 kHz, expected about {}", sample_rate, other.len(), expected_len);
+        let difference = difference_db(&reference, &other);
+        assert!(difference < -30.0, "{} Hz render differs from the 48 kHz render by {} dB", sample_rate, difference);
+    }
+}
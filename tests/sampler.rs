@@ -0,0 +1,42 @@
+// A looping sample should keep playing through its loop however the loop
+// points line up with the end of the recording, and the samples after the
+// loop shouldn't leak into the seam.
+
+use std::sync::Arc;
+
+use mattmusic::midi_notes::midi2freq;
+use mattmusic::sampler::{Sample, SampleZone, Sampler};
+use mattmusic::traits::Instrument;
+
+const SAMPLE_RATE: i32 = 48000;
+
+// 0.5 up to the loop end then -1 after it
+fn looped_sample(loop_points: (usize, usize), len: usize) -> Sampler {
+    let samples = (0..len).map(|n| if n <= loop_points.1 { (0.5, 0.5) } else { (-1.0, -1.0) }).collect();
+    let sample = Arc::new(Sample::new(samples, SAMPLE_RATE, None, Some(loop_points)));
+    Sampler::new(SAMPLE_RATE, vec![SampleZone::new(sample, 0, 127)])
+}
+
+fn play(sampler: &Sampler, speed: f32) -> Vec<f32> {
+    let freq = midi2freq(60) * speed / SAMPLE_RATE as f32;
+    let voice = sampler.play(freq, 2000, 1.0);
+    let mut state = voice.init_state();
+    (0..1000).map(|n| voice.next_value(n, &mut state).0).collect()
+}
+
+#[test]
+fn loop_past_the_end_keeps_playing() {
+    // The loop end is one past the last sample
+    let sampler = looped_sample((10, 100), 100);
+    for (n, x) in play(&sampler, 1.7).into_iter().enumerate() {
+        assert!((x - 0.5).abs() < 1e-4, "sample {} is {}", n, x);
+    }
+}
+
+#[test]
+fn loop_seam_ignores_samples_after_the_loop() {
+    let sampler = looped_sample((10, 59), 100);
+    for (n, x) in play(&sampler, 1.37).into_iter().enumerate() {
+        assert!((x - 0.5).abs() < 1e-4, "sample {} is {}", n, x);
+    }
+}
@@ -0,0 +1,71 @@
+// Key ranges from SFZ opcodes that overlap. `key` sets the low key, high key
+// and root key together, so lokey, hikey and pitch_keycenter at the same level
+// should win over it, and a region's opcodes over its group's.
+
+use mattmusic::sampler::LoopMode;
+use mattmusic::sfz::parse_sfz;
+
+const SFZ: &str = "
+<group> key=40
+<region> sample=tone.wav key=60 lokey=55 hikey=65 lovel=10 hivel=100
+<group> lokey=30 hikey=50 pitch_keycenter=45
+<region> sample=tone.wav key=62
+<region> sample=tone.wav pitch_keycenter=c4
+";
+
+fn write_tone(dir: &std::path::Path) {
+    let spec = hound::WavSpec {
+        channels: 1,
+        sample_rate: 48000,
+        bits_per_sample: 16,
+        sample_format: hound::SampleFormat::Int,
+    };
+    let mut writer = hound::WavWriter::create(dir.join("tone.wav"), spec).unwrap();
+    for i in 0..480 {
+        writer.write_sample(((i as f32 * 0.1).sin() * 10000.0) as i16).unwrap();
+    }
+    writer.finalize().unwrap();
+}
+
+#[test]
+fn key_opcodes_apply_in_a_fixed_order() {
+    let dir = std::env::temp_dir().join(format!("mattmusic-sfz-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    write_tone(&dir);
+    // HashMaps iterate in a different order each time, so parse it a few
+    // times to catch anything that depends on it
+    for _ in 0..20 {
+        let sampler = parse_sfz(SFZ, dir.to_str().unwrap(), 48000);
+        let keys: Vec<_> = sampler.zones().iter()
+            .map(|zone| (zone.lokey, zone.hikey, zone.root_key, zone.lovel, zone.hivel))
+            .collect();
+        assert_eq!(keys, vec![
+            (55, 65, 60, 10, 100),
+            (62, 62, 62, 1, 127),
+            (30, 50, 60, 1, 127),
+        ]);
+    }
+    std::fs::remove_dir_all(&dir).unwrap();
+}
+
+#[test]
+fn loops_that_dont_fit_are_dropped() {
+    let dir = std::env::temp_dir().join(format!("mattmusic-sfz-loop-{}", std::process::id()));
+    std::fs::create_dir_all(&dir).unwrap();
+    write_tone(&dir);
+    let sfz = "
+<region> sample=tone.wav loop_mode=loop_continuous loop_start=100 loop_end=400
+<region> sample=tone.wav loop_mode=loop_continuous loop_start=400 loop_end=100
+<region> sample=tone.wav loop_mode=loop_continuous loop_start=100 loop_end=480
+";
+    let sampler = parse_sfz(sfz, dir.to_str().unwrap(), 48000);
+    let loops: Vec<_> = sampler.zones().iter()
+        .map(|zone| (zone.loop_mode, zone.loop_points))
+        .collect();
+    assert_eq!(loops, vec![
+        (LoopMode::Continuous, Some((100, 400))),
+        (LoopMode::NoLoop, None),
+        (LoopMode::NoLoop, None),
+    ]);
+    std::fs::remove_dir_all(&dir).unwrap();
+}
@@ -0,0 +1,28 @@
+// Velvet noise whose density starts at zero should stay silent until the
+// density comes up, then start making impulses.
+
+use mattmusic::envelope::{Envelope, EnvelopePoint};
+use mattmusic::knob::Knob;
+use mattmusic::traits::SoundSource;
+use mattmusic::velvet_noise::VelvetNoise;
+
+const SAMPLE_RATE: i32 = 48000;
+
+#[test]
+fn impulses_start_when_the_density_rises() {
+    let density = 2000.0 / SAMPLE_RATE as f32;
+    let envelope = Envelope::new(vec![
+        EnvelopePoint::new(0, 0.0),
+        EnvelopePoint::new(4800, 0.0),
+        EnvelopePoint::new(1, density),
+        EnvelopePoint::new(43200, density),
+    ]);
+    let noise = VelvetNoise::new(Knob::new(Box::new(envelope)), 1, 48000);
+    let mut state = noise.init_state();
+    let impulses: Vec<_> = (0..48000)
+        .filter(|&n| noise.next_value(n, &mut state).0 != 0.0)
+        .collect();
+    assert!(impulses.iter().all(|&n| n > 4800), "impulse before the density rose at {:?}", impulses.first());
+    // One impulse a frame, give or take the frames at either end
+    assert!((1700..=1801).contains(&impulses.len()), "{} impulses", impulses.len());
+}